@@ -3,18 +3,88 @@
 out vec4 FragColor;
 
 in vec2 TexCoord;
+in vec3 WorldPos;
+in vec3 Normal;
+in float ViewDepth;
 
-uniform sampler2D texture1;
-uniform bool has_alpha;
+#include "include/lighting.glsl"
+
+// Factors the textures are scaled by, set by `Material::bind`.
+struct Material {
+    vec4 base_color;
+    float metallic;
+    float roughness;
+    vec3 emissive;
+    float normal_scale;
+    float occlusion_strength;
+    float alpha_cutoff;
+};
+
+uniform Material material;
+uniform sampler2D albedo_map;
+uniform sampler2D normal_map;
+uniform sampler2D metallic_roughness_map;
+uniform sampler2D occlusion_map;
+uniform sampler2D emissive_map;
+
+#ifdef HAS_NORMAL_MAP
+// Tangent frame from screen space derivatives, meshes don't need tangents.
+vec3 perturb_normal(vec3 normal) {
+    vec3 tangent_normal = texture(normal_map, TexCoord).xyz * 2.0 - 1.0;
+    tangent_normal.xy *= material.normal_scale;
+
+    vec3 dp1 = dFdx(WorldPos);
+    vec3 dp2 = dFdy(WorldPos);
+    vec2 duv1 = dFdx(TexCoord);
+    vec2 duv2 = dFdy(TexCoord);
+
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+    float scale = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+    return normalize(mat3(tangent * scale, bitangent * scale, normal) * tangent_normal);
+}
+#endif
 
 void main() {
-    vec4 texColor = texture(texture1, TexCoord);
-
-	if (has_alpha) {
-        if (texColor.a < 0.1)
-            discard;
-        FragColor = vec4(texColor.rgb, texColor.a);
-    } else {
-        FragColor = vec4(texColor.rgb, 1.0); // Apply texture without alpha handling
-    }
+    // Only the texel is stored as sRGB, the base color factor is linear already.
+    vec4 texel = texture(albedo_map, TexCoord);
+    vec3 albedo = srgb_to_linear(texel.rgb) * material.base_color.rgb;
+    float alpha = texel.a * material.base_color.a;
+
+#ifdef ALPHA_TEST
+    if (alpha < material.alpha_cutoff)
+        discard;
+#endif
+
+    vec3 normal = normalize(Normal);
+    // Back faces of double sided materials face the camera too.
+    if (!gl_FrontFacing)
+        normal = -normal;
+#ifdef HAS_NORMAL_MAP
+    normal = perturb_normal(normal);
+#endif
+
+    // Roughness in green and metalness in blue, like glTF's ARM textures.
+    vec4 metallic_roughness = texture(metallic_roughness_map, TexCoord);
+    float roughness = metallic_roughness.g * material.roughness;
+    float metallic = metallic_roughness.b * material.metallic;
+    float occlusion = mix(1.0, texture(occlusion_map, TexCoord).r, material.occlusion_strength);
+    vec3 emissive = srgb_to_linear(texture(emissive_map, TexCoord).rgb) * material.emissive;
+
+    vec3 color = shade(WorldPos, normal, ViewDepth, albedo, metallic, roughness, occlusion) + emissive;
+
+#ifdef HDR_OUTPUT
+    // Tone mapped by the post-process chain (src/core/engine/postprocess), exposed already.
+    vec3 result = color * camera.w;
+#else
+    vec3 result = linear_to_srgb(tone_map(color));
+#endif
+
+#ifdef ALPHA_TEST
+    FragColor = vec4(result, alpha);
+#else
+    FragColor = vec4(result, 1.0);
+#endif
 }
\ No newline at end of file
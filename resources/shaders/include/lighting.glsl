@@ -0,0 +1,162 @@
+// Cook-Torrance lighting for the metallic-roughness workflow, the lights come from
+// `Lights::upload` (src/core/engine/lighting/manager.rs).
+
+#include "color.glsl"
+
+#define MAX_LIGHTS 16
+#define MAX_SHADOW_MAPS 8
+#define CASCADES 4
+
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+const float PI = 3.14159265359;
+
+struct Light {
+    vec4 position;  // xyz, range in w (0 = no limit)
+    vec4 direction; // xyz, kind in w
+    vec4 color;     // rgb, intensity in w
+    vec4 cone;      // cosines of the inner and outer angle
+    vec4 shadow;    // first shadow map layer (-1 for none), depth bias, normal bias, PCF radius
+};
+
+layout (std140) uniform Lights {
+    Light lights[MAX_LIGHTS];
+    vec4 ambient; // rgb, light count in w
+    vec4 camera;  // position, exposure in w
+};
+
+// Written by `ShadowMaps::fit` (src/core/engine/lighting/shadow.rs).
+layout (std140) uniform Shadows {
+    mat4 light_space[MAX_SHADOW_MAPS];
+    vec4 cascade_splits; // view space depth every cascade ends at
+};
+
+uniform sampler2DArrayShadow shadow_maps;
+
+// How much of `light` reaches `position`, 0 in full shadow. Directional lights pick the
+// cascade by the depth from the camera.
+float shadow_factor(Light light, vec3 position, vec3 normal, float view_depth) {
+    int layer = int(light.shadow.x);
+    if (layer < 0)
+        return 1.0;
+
+    if (int(light.direction.w) == LIGHT_DIRECTIONAL) {
+        if (view_depth > cascade_splits[CASCADES - 1])
+            return 1.0;
+        int cascade = 0;
+        for (int i = 0; i < CASCADES - 1; i++) {
+            if (view_depth > cascade_splits[i])
+                cascade = i + 1;
+        }
+        layer += cascade;
+    }
+
+    vec4 projected = light_space[layer] * vec4(position + normal * light.shadow.z, 1.0);
+    vec3 coords = projected.xyz / projected.w * 0.5 + 0.5;
+    if (coords.z > 1.0)
+        return 1.0;
+    float depth = coords.z - light.shadow.y;
+
+    // Percentage closer filtering over a square of texels
+    int radius = int(light.shadow.w);
+    vec2 texel = 1.0 / vec2(textureSize(shadow_maps, 0).xy);
+    float lit = 0.0;
+    for (int x = -radius; x <= radius; x++) {
+        for (int y = -radius; y <= radius; y++) {
+            lit += texture(shadow_maps, vec4(coords.xy + vec2(x, y) * texel, float(layer), depth));
+        }
+    }
+    float size = float(2 * radius + 1);
+    return lit / (size * size);
+}
+
+// GGX / Trowbridge-Reitz normal distribution.
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+// Smith's geometry term with Schlick-GGX for direct lighting.
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    float view = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    float light = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return view * light;
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Inverse square falloff that reaches 0 at the light's range.
+float attenuation(float dist, float range) {
+    float falloff = 1.0 / max(dist * dist, 0.0001);
+    if (range <= 0.0)
+        return falloff;
+    float ratio = dist / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return falloff * window * window;
+}
+
+// Light reaching `position` from `light` and the direction towards it.
+vec3 incoming_light(Light light, vec3 position, out vec3 to_light) {
+    int kind = int(light.direction.w);
+    vec3 radiance = light.color.rgb * light.color.w;
+
+    if (kind == LIGHT_DIRECTIONAL) {
+        to_light = -light.direction.xyz;
+        return radiance;
+    }
+
+    vec3 offset = light.position.xyz - position;
+    float dist = length(offset);
+    to_light = offset / max(dist, 0.0001);
+    radiance *= attenuation(dist, light.position.w);
+
+    if (kind == LIGHT_SPOT) {
+        float cos_angle = dot(-to_light, light.direction.xyz);
+        radiance *= smoothstep(light.cone.y, light.cone.x, cos_angle);
+    }
+    return radiance;
+}
+
+// Outgoing radiance towards the camera, `albedo` is linear. `view_depth` is the distance from
+// the camera along its forward axis.
+vec3 shade(vec3 position, vec3 normal, float view_depth, vec3 albedo, float metallic, float roughness, float occlusion) {
+    vec3 to_camera = normalize(camera.xyz - position);
+    float n_dot_v = max(dot(normal, to_camera), 0.0001);
+    // Dielectrics reflect about 4% at normal incidence.
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    roughness = clamp(roughness, 0.045, 1.0);
+
+    vec3 color = vec3(0.0);
+    int count = min(int(ambient.w), MAX_LIGHTS);
+    for (int i = 0; i < count; i++) {
+        vec3 to_light;
+        vec3 radiance = incoming_light(lights[i], position, to_light);
+        float n_dot_l = max(dot(normal, to_light), 0.0);
+        if (n_dot_l <= 0.0)
+            continue;
+        radiance *= shadow_factor(lights[i], position, normal, view_depth);
+
+        vec3 halfway = normalize(to_camera + to_light);
+        float n_dot_h = max(dot(normal, halfway), 0.0);
+        vec3 fresnel = fresnel_schlick(max(dot(halfway, to_camera), 0.0), f0);
+
+        vec3 specular = distribution_ggx(n_dot_h, roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * fresnel
+            / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+        color += (diffuse + specular) * radiance * n_dot_l;
+    }
+
+    return color + ambient.rgb * albedo * occlusion;
+}
+
+// Exposure from `Lights::exposure`, then the ACES curve.
+vec3 tone_map(vec3 color) {
+    return aces(color * camera.w);
+}
\ No newline at end of file
@@ -0,0 +1,27 @@
+#version 410 core
+
+// Keeps the colour above `threshold`, drawn into the half size bloom target.
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D image;
+uniform float threshold;
+
+void main() {
+    // Four bilinear taps average 4x4 texels, so small highlights don't flicker as they move.
+    vec2 texel = 1.0 / vec2(textureSize(image, 0));
+    vec3 color = (texture(image, TexCoord + texel * vec2(-1.0, -1.0)).rgb
+        + texture(image, TexCoord + texel * vec2(1.0, -1.0)).rgb
+        + texture(image, TexCoord + texel * vec2(-1.0, 1.0)).rgb
+        + texture(image, TexCoord + texel * vec2(1.0, 1.0)).rgb) * 0.25;
+
+    // Soft knee, colours just under the threshold still glow a little.
+    float brightness = max(color.r, max(color.g, color.b));
+    float knee = threshold * 0.5;
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.0001);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 0.0001);
+
+    FragColor = vec4(color * contribution, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,22 @@
+#version 410 core
+
+// One direction of a separable 9 tap gaussian blur.
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D image;
+// (1, 0) for horizontal, (0, 1) for vertical.
+uniform vec2 direction;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 offset = direction / vec2(textureSize(image, 0));
+    vec3 color = texture(image, TexCoord).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        color += texture(image, TexCoord + offset * float(i)).rgb * weights[i];
+        color += texture(image, TexCoord - offset * float(i)).rgb * weights[i];
+    }
+    FragColor = vec4(color, 1.0);
+}
\ No newline at end of file
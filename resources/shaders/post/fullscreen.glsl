@@ -0,0 +1,10 @@
+#version 410 core
+
+// One triangle covering the screen, drawn without vertex buffers by `PostProcess`.
+out vec2 TexCoord;
+
+void main() {
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    TexCoord = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
\ No newline at end of file
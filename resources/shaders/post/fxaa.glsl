@@ -0,0 +1,48 @@
+#version 410 core
+
+// Lottes' FXAA, blurs along the edges found from the luma of the tone mapped image.
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D image;
+
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(image, 0));
+    vec3 rgb_nw = texture(image, TexCoord + vec2(-1.0, -1.0) * texel).rgb;
+    vec3 rgb_ne = texture(image, TexCoord + vec2(1.0, -1.0) * texel).rgb;
+    vec3 rgb_sw = texture(image, TexCoord + vec2(-1.0, 1.0) * texel).rgb;
+    vec3 rgb_se = texture(image, TexCoord + vec2(1.0, 1.0) * texel).rgb;
+    vec3 rgb_m = texture(image, TexCoord).rgb;
+
+    float luma_nw = dot(rgb_nw, LUMA);
+    float luma_ne = dot(rgb_ne, LUMA);
+    float luma_sw = dot(rgb_sw, LUMA);
+    float luma_se = dot(rgb_se, LUMA);
+    float luma_m = dot(rgb_m, LUMA);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Across the edge, shortened where the contrast is low.
+    vec2 direction = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+    vec3 rgb_a = 0.5 * (texture(image, TexCoord + direction * (1.0 / 3.0 - 0.5)).rgb
+        + texture(image, TexCoord + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgb_b = rgb_a * 0.5 + 0.25 * (texture(image, TexCoord - direction * 0.5).rgb
+        + texture(image, TexCoord + direction * 0.5).rgb);
+
+    // The wider blur crossed another edge, the narrow one is kept.
+    float luma_b = dot(rgb_b, LUMA);
+    FragColor = vec4((luma_b < luma_min || luma_b > luma_max) ? rgb_a : rgb_b, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+#version 410 core
+
+// Colour grading through a 16x16x16 lookup table, loaded from a 256x16 strip by `PostProcess`.
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D image;
+uniform sampler3D lut;
+uniform float strength;
+
+const float LUT_SIZE = 16.0;
+
+void main() {
+    vec3 color = texture(image, TexCoord).rgb;
+    // Sample between the texel centres of the first and last entry.
+    vec3 graded = texture(lut, color * ((LUT_SIZE - 1.0) / LUT_SIZE) + 0.5 / LUT_SIZE).rgb;
+    FragColor = vec4(mix(color, graded, strength), 1.0);
+}
\ No newline at end of file
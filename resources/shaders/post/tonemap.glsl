@@ -0,0 +1,20 @@
+#version 410 core
+
+// Adds the bloom to the HDR scene and maps it to the screen's range and gamma.
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+#include "../include/color.glsl"
+
+uniform sampler2D image;
+uniform sampler2D bloom;
+uniform float bloom_intensity;
+// Clamps instead of using the ACES curve when unset.
+uniform bool use_aces;
+
+void main() {
+    vec3 color = texture(image, TexCoord).rgb + texture(bloom, TexCoord).rgb * bloom_intensity;
+    color = use_aces ? aces(color) : clamp(color, 0.0, 1.0);
+    FragColor = vec4(linear_to_srgb(color), 1.0);
+}
\ No newline at end of file
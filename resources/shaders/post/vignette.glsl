@@ -0,0 +1,15 @@
+#version 410 core
+
+// Darkens the corners of the screen.
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D image;
+uniform float strength;
+
+void main() {
+    vec3 color = texture(image, TexCoord).rgb;
+    float falloff = smoothstep(0.8, 0.2, length(TexCoord - 0.5));
+    FragColor = vec4(color * mix(1.0, falloff, strength), 1.0);
+}
\ No newline at end of file
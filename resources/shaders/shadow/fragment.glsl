@@ -0,0 +1,5 @@
+#version 410 core
+
+// Only the depth is written.
+void main() {
+}
\ No newline at end of file
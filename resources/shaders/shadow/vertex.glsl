@@ -0,0 +1,25 @@
+#version 410 core
+
+// Depth only pass of `ShadowMaps::render`.
+layout (location = 0) in vec3 aPos;
+#ifdef SKINNED
+layout (location = 5) in uvec4 aJoints;
+layout (location = 6) in vec4 aWeights;
+
+layout (std140) uniform Joints {
+	mat4 joints[128];
+};
+#endif
+
+uniform mat4 model;
+uniform mat4 light_space;
+
+void main() {
+#ifdef SKINNED
+	mat4 skin = aWeights.x * joints[aJoints.x] + aWeights.y * joints[aJoints.y]
+		+ aWeights.z * joints[aJoints.z] + aWeights.w * joints[aJoints.w];
+	gl_Position = light_space * model * skin * vec4(aPos, 1.0);
+#else
+	gl_Position = light_space * model * vec4(aPos, 1.0);
+#endif
+}
\ No newline at end of file
@@ -2,14 +2,45 @@
 
 layout (location = 0) in vec3 aPos;
 layout(location = 1) in vec2 aTexCoord;
+layout(location = 2) in vec3 aNormal;
+#ifdef SKINNED
+layout(location = 5) in uvec4 aJoints;
+layout(location = 6) in vec4 aWeights;
+#endif
 
 out vec2 TexCoord;
+out vec3 WorldPos;
+out vec3 Normal;
+out float ViewDepth;
 
 uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
+
+// Shared by the 3D shaders, written once per frame.
+layout (std140) uniform Camera {
+	mat4 projection;
+	mat4 view;
+};
+
+#ifdef SKINNED
+// Joint matrices of the mesh's skin, `MAX_JOINTS` of src/core/engine/animation/skin.rs.
+layout (std140) uniform Joints {
+	mat4 joints[128];
+};
+#endif
 
 void main() {
-	gl_Position = projection * view * model * vec4(aPos, 1.0);
+#ifdef SKINNED
+	mat4 transform = model * (aWeights.x * joints[aJoints.x] + aWeights.y * joints[aJoints.y]
+		+ aWeights.z * joints[aJoints.z] + aWeights.w * joints[aJoints.w]);
+#else
+	mat4 transform = model;
+#endif
+	vec4 world = transform * vec4(aPos, 1.0);
+	vec4 eye = view * world;
+	gl_Position = projection * eye;
+	ViewDepth = -eye.z;
 	TexCoord = aTexCoord;
+	WorldPos = world.xyz;
+	// Keeps normals perpendicular under non-uniform scaling.
+	Normal = mat3(transpose(inverse(transform))) * aNormal;
 }
\ No newline at end of file
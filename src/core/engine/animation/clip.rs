@@ -0,0 +1,143 @@
+use nalgebra::{Quaternion, UnitQuaternion, Vector4};
+
+use super::player::Transform;
+
+/// How the values between two keyframes are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+	/// The value of the previous keyframe.
+	Step,
+	/// Straight from a keyframe to the next, spherical for rotations.
+	Linear,
+	/// A Hermite spline through the keyframes and their tangents.
+	CubicSpline,
+}
+
+/// The part of a node's transform a channel animates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+	Translation,
+	/// A quaternion, `xyzw`.
+	Rotation,
+	Scale,
+}
+
+impl Property {
+	/// Floats of one value.
+	pub fn width(self) -> usize {
+		match self {
+			Property::Rotation => 4,
+			_ => 3,
+		}
+	}
+}
+
+/// Keyframes of one property of one node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+	pub node: usize,
+	pub property: Property,
+	pub interpolation: Interpolation,
+	/// Seconds of the keyframes, increasing.
+	pub times: Vec<f32>,
+	/// `width` floats per keyframe, cubic splines have the in-tangent, the value and the
+	/// out-tangent of each keyframe in a row.
+	pub values: Vec<f32>,
+}
+
+impl Channel {
+	/// Whether there is a value for every keyframe.
+	pub fn is_valid(&self) -> bool {
+		let parts = if self.interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+		!self.times.is_empty() && self.values.len() == self.times.len() * parts * self.property.width()
+	}
+
+	/// `part` of the keyframe `key`, the value is part 1 of cubic splines and 0 otherwise.
+	fn keyframe(&self, key: usize, part: usize) -> Vector4<f32> {
+		let width = self.property.width();
+		let parts = if self.interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+		let start = (key * parts + part) * width;
+		let mut value = Vector4::zeros();
+		for component in 0..width {
+			value[component] = self.values[start + component];
+		}
+		value
+	}
+
+	/// Value at `time` in seconds, the first and last keyframes are held before and after them.
+	pub fn sample(&self, time: f32) -> Vector4<f32> {
+		let cubic = self.interpolation == Interpolation::CubicSpline;
+		let value = if cubic { 1 } else { 0 };
+		let next = self.times.partition_point(|key| *key <= time);
+		if next == 0 {
+			return self.keyframe(0, value);
+		}
+		if next == self.times.len() {
+			return self.keyframe(next - 1, value);
+		}
+
+		let previous = next - 1;
+		let span = self.times[next] - self.times[previous];
+		let t = if span > 0.0 { (time - self.times[previous]) / span } else { 0.0 };
+		let result = match self.interpolation {
+			Interpolation::Step => return self.keyframe(previous, 0),
+			Interpolation::Linear if self.property == Property::Rotation => {
+				let from = rotation(self.keyframe(previous, 0));
+				let mut to = rotation(self.keyframe(next, 0));
+				// The shortest way around
+				if from.coords.dot(&to.coords) < 0.0 {
+					to = UnitQuaternion::new_unchecked(-to.into_inner());
+				}
+				return from.try_slerp(&to, t, 1.0e-6).unwrap_or_else(|| from.nlerp(&to, t)).coords;
+			}
+			Interpolation::Linear => self.keyframe(previous, 0).lerp(&self.keyframe(next, 0), t),
+			Interpolation::CubicSpline => {
+				let (t2, t3) = (t * t, t * t * t);
+				self.keyframe(previous, 1) * (2.0 * t3 - 3.0 * t2 + 1.0)
+					+ self.keyframe(previous, 2) * span * (t3 - 2.0 * t2 + t)
+					+ self.keyframe(next, 1) * (-2.0 * t3 + 3.0 * t2)
+					+ self.keyframe(next, 0) * span * (t3 - t2)
+			}
+		};
+		match self.property {
+			Property::Rotation => rotation(result).coords,
+			_ => result,
+		}
+	}
+}
+
+/// A unit quaternion from `xyzw`.
+fn rotation(value: Vector4<f32>) -> UnitQuaternion<f32> {
+	UnitQuaternion::from_quaternion(Quaternion::from(value))
+}
+
+/// An animation of some of the nodes of a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+	pub name: String,
+	pub channels: Vec<Channel>,
+	/// Seconds until the last keyframe.
+	pub duration: f32,
+}
+
+impl Clip {
+	pub fn new(name: &str, channels: Vec<Channel>) -> Self {
+		let duration = channels.iter().filter_map(|channel| channel.times.last()).fold(0.0, |duration: f32, time| duration.max(*time));
+		Clip { name: name.to_string(), channels, duration }
+	}
+
+	/// Sets the properties the clip animates in `pose` to their value at `time`.
+	pub fn apply(&self, time: f32, pose: &mut [Transform]) {
+		for channel in self.channels.iter() {
+			let Some(transform) = pose.get_mut(channel.node) else {
+				continue;
+			};
+			let value = channel.sample(time);
+			match channel.property {
+				Property::Translation => transform.translation = value.xyz(),
+				Property::Rotation => transform.rotation = rotation(value),
+				Property::Scale => transform.scale = value.xyz(),
+			}
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,3 @@
+pub mod clip;
+pub mod player;
+pub mod skin;
\ No newline at end of file
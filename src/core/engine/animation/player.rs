@@ -0,0 +1,155 @@
+use nalgebra::{Quaternion, UnitQuaternion, Vector3, Vector4};
+
+use crate::core::utils::model::node::Node;
+use super::clip::Clip;
+
+/// Translation, rotation and scale of a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+	pub translation: Vector3<f32>,
+	pub rotation: UnitQuaternion<f32>,
+	pub scale: Vector3<f32>,
+}
+
+impl Transform {
+	pub fn of(node: &Node) -> Self {
+		Transform { translation: node.translation, rotation: node.rotation, scale: node.scale }
+	}
+}
+
+/// A clip the player is playing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+	/// Index of the clip in the model's clips.
+	pub clip: usize,
+	/// Seconds into the clip.
+	pub time: f32,
+	pub speed: f32,
+	/// Starts over at the end, the last pose is held otherwise.
+	pub looping: bool,
+	/// Share of the track in the pose, relative to the other tracks.
+	pub weight: f32,
+	/// Weight the track fades to and by how much per second.
+	fade: Option<(f32, f32)>,
+}
+
+impl Track {
+	fn new(clip: usize, weight: f32, looping: bool) -> Self {
+		Track { clip, time: 0.0, speed: 1.0, looping, weight, fade: None }
+	}
+
+	fn fade_to(&mut self, weight: f32, duration: f32) {
+		if duration <= 0.0 {
+			self.weight = weight;
+			self.fade = None;
+		} else {
+			self.fade = Some((weight, (weight - self.weight).abs() / duration));
+		}
+	}
+}
+
+/// Plays the clips of a model and poses its nodes with the blend of the tracks playing.
+/// Nodes no track animates stay in their rest pose.
+#[derive(Debug, Clone, Default)]
+pub struct AnimationPlayer {
+	pub tracks: Vec<Track>,
+	/// Transforms of the nodes before any clip moved them.
+	rest: Vec<Transform>,
+}
+
+impl AnimationPlayer {
+	pub fn new(nodes: &[Node]) -> Self {
+		AnimationPlayer { tracks: Vec::new(), rest: nodes.iter().map(Transform::of).collect() }
+	}
+
+	/// Plays `clip` alone from its start.
+	pub fn play(&mut self, clip: usize, looping: bool) {
+		self.tracks = vec![Track::new(clip, 1.0, looping)];
+	}
+
+	/// Plays `clip` on top of the other tracks with `weight`, or changes its weight when it's playing.
+	pub fn blend(&mut self, clip: usize, weight: f32, looping: bool) {
+		match self.track_mut(clip) {
+			Some(track) => {
+				track.weight = weight;
+				track.fade = None;
+			}
+			None => self.tracks.push(Track::new(clip, weight, looping)),
+		}
+	}
+
+	/// Fades the tracks out and `clip` in over `duration` seconds.
+	pub fn cross_fade(&mut self, clip: usize, duration: f32, looping: bool) {
+		for track in self.tracks.iter_mut() {
+			track.fade_to(0.0, duration);
+		}
+		if self.track_mut(clip).is_none() {
+			self.tracks.push(Track::new(clip, 0.0, looping));
+		}
+		if let Some(track) = self.track_mut(clip) {
+			track.looping = looping;
+			track.fade_to(1.0, duration);
+		}
+	}
+
+	pub fn stop(&mut self, clip: usize) {
+		self.tracks.retain(|track| track.clip != clip);
+	}
+
+	pub fn is_playing(&self, clip: usize) -> bool {
+		self.tracks.iter().any(|track| track.clip == clip)
+	}
+
+	pub fn track_mut(&mut self, clip: usize) -> Option<&mut Track> {
+		self.tracks.iter_mut().find(|track| track.clip == clip)
+	}
+
+	/// Advances the tracks by `delta` seconds and sets the transforms of `nodes` to their blend.
+	/// Tracks that faded out are removed.
+	pub fn update(&mut self, delta: f32, clips: &[Clip], nodes: &mut [Node]) {
+		self.tracks.retain(|track| track.clip < clips.len());
+		for track in self.tracks.iter_mut() {
+			let duration = clips[track.clip].duration;
+			track.time += delta * track.speed;
+			track.time = if track.looping && duration > 0.0 { track.time.rem_euclid(duration) } else { track.time.clamp(0.0, duration) };
+
+			if let Some((target, rate)) = track.fade {
+				let step = rate * delta;
+				track.weight = if (target - track.weight).abs() <= step { target } else { track.weight + step.copysign(target - track.weight) };
+				if track.weight == target {
+					track.fade = None;
+				}
+			}
+		}
+		self.tracks.retain(|track| track.weight > 0.0 || track.fade.is_some());
+
+		let total = self.tracks.iter().map(|track| track.weight).sum::<f32>();
+		if total <= 0.0 || self.rest.len() != nodes.len() {
+			return;
+		}
+
+		// Translations and scales are averaged, rotations too once they're on the same side
+		// of the quaternion sphere
+		let mut translations = vec![Vector3::zeros(); nodes.len()];
+		let mut rotations = vec![Vector4::zeros(); nodes.len()];
+		let mut scales = vec![Vector3::zeros(); nodes.len()];
+		for track in self.tracks.iter() {
+			let mut pose = self.rest.clone();
+			clips[track.clip].apply(track.time, &mut pose);
+			let weight = track.weight / total;
+			for (index, transform) in pose.iter().enumerate() {
+				let rotation = transform.rotation.coords;
+				let sign = if rotations[index].dot(&rotation) < 0.0 { -1.0 } else { 1.0 };
+				translations[index] += transform.translation * weight;
+				rotations[index] += rotation * sign * weight;
+				scales[index] += transform.scale * weight;
+			}
+		}
+
+		for (index, node) in nodes.iter_mut().enumerate() {
+			node.translation = translations[index];
+			node.rotation = UnitQuaternion::from_quaternion(Quaternion::from(rotations[index]));
+			node.scale = scales[index];
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+use nalgebra::Matrix4;
+
+use crate::core::{engine::objects::gpu::Buffer, utils::model::node::Node};
+
+/// Binding point of the `Joints` uniform block of the skinned shader variants.
+pub const JOINTS_BINDING: u32 = 3;
+/// Joints of a skin the `Joints` block has room for.
+pub const MAX_JOINTS: usize = 128;
+
+/// The joints a skinned mesh follows. Its vertices are in the model's space, each joint
+/// matrix moves them from where they were bound to the joint to where the joint is now.
+#[derive(Debug)]
+pub struct Skin {
+	pub name: String,
+	/// Nodes of the joints, in the order of the vertices' joint indices.
+	pub joints: Vec<usize>,
+	/// From the model's space to the space of each joint when the mesh was bound.
+	pub inverse_binds: Vec<Matrix4<f32>>,
+	/// Joint matrices of the current pose, computed by `update`.
+	pub palette: Vec<Matrix4<f32>>,
+	/// `palette` for the shaders, created by the first `update`.
+	buffer: Option<Buffer>,
+}
+
+impl Skin {
+	pub fn new(name: &str, joints: Vec<usize>, inverse_binds: Vec<Matrix4<f32>>) -> Self {
+		Skin { name: name.to_string(), joints, inverse_binds, palette: Vec::new(), buffer: None }
+	}
+
+	/// Computes the joint matrices from the `global` transforms of `nodes` and uploads them.
+	pub fn update(&mut self, nodes: &[Node]) {
+		self.palette = self.joints.iter().zip(self.inverse_binds.iter())
+			.map(|(joint, inverse_bind)| nodes.get(*joint).map_or(Matrix4::identity(), |node| node.global) * inverse_bind)
+			.take(MAX_JOINTS)
+			.collect();
+
+		let buffer = self.buffer.get_or_insert_with(|| {
+			let buffer = Buffer::new();
+			buffer.allocate(MAX_JOINTS * std::mem::size_of::<Matrix4<f32>>(), gl::DYNAMIC_DRAW);
+			buffer.label(&format!("skin \"{}\" joints", self.name));
+			buffer
+		});
+		buffer.sub_data(0, &self.palette);
+	}
+
+	/// Makes the `Joints` block of the shaders read the skin's joint matrices.
+	pub fn bind(&self) {
+		if let Some(buffer) = &self.buffer {
+			buffer.bind_base(gl::UNIFORM_BUFFER, JOINTS_BINDING);
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,174 @@
+use nalgebra::Vector3;
+
+use crate::{core::engine::shaders::uniform::UniformBuffer, log, utils::log::manager::{LogLevel, Logger}};
+use super::shadow::{ShadowMaps, ShadowSettings};
+
+/// Binding point of the `Lights` uniform block of `resources/shaders/include/lighting.glsl`.
+pub const LIGHTS_BINDING: u32 = 1;
+/// Lights the block has room for, `MAX_LIGHTS` in the shaders.
+pub const MAX_LIGHTS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+	/// Infinitely far away, only `direction` matters.
+	Directional = 0,
+	/// Shines in every direction from `position`.
+	Point = 1,
+	/// Shines from `position` along `direction` inside a cone.
+	Spot = 2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+	pub kind: LightKind,
+	pub position: Vector3<f32>,
+	/// Where the light shines to.
+	pub direction: Vector3<f32>,
+	/// Linear RGB.
+	pub color: Vector3<f32>,
+	/// Multiplies `color`.
+	pub intensity: f32,
+	/// Distance the light fades out at, 0 for no limit.
+	pub range: f32,
+	/// Angles from the spot's axis, in radians, the light fades between.
+	pub inner_cone: f32,
+	pub outer_cone: f32,
+	/// Casts shadows when set, point lights don't.
+	pub shadow: Option<ShadowSettings>,
+}
+
+impl Light {
+	pub fn directional(direction: Vector3<f32>, color: Vector3<f32>, intensity: f32) -> Self {
+		Light {
+			kind: LightKind::Directional,
+			position: Vector3::zeros(),
+			direction,
+			color,
+			intensity,
+			range: 0.0,
+			inner_cone: 0.0,
+			outer_cone: 0.0,
+			shadow: None,
+		}
+	}
+
+	pub fn point(position: Vector3<f32>, color: Vector3<f32>, intensity: f32, range: f32) -> Self {
+		Light {
+			kind: LightKind::Point,
+			position,
+			direction: -Vector3::y(),
+			color,
+			intensity,
+			range,
+			inner_cone: 0.0,
+			outer_cone: 0.0,
+			shadow: None,
+		}
+	}
+
+	pub fn spot(position: Vector3<f32>, direction: Vector3<f32>, color: Vector3<f32>, intensity: f32, range: f32, inner_cone: f32, outer_cone: f32) -> Self {
+		Light {
+			kind: LightKind::Spot,
+			position,
+			direction,
+			color,
+			intensity,
+			range,
+			inner_cone,
+			outer_cone,
+			shadow: None,
+		}
+	}
+
+	/// The light with shadows sampled as `settings` says.
+	pub fn with_shadow(mut self, settings: ShadowSettings) -> Self {
+		self.shadow = Some(settings);
+		self
+	}
+
+	/// The std140 `Light` struct of the shaders.
+	fn block(&self, shadow: [f32; 4]) -> LightBlock {
+		let direction = self.direction.try_normalize(f32::EPSILON).unwrap_or(-Vector3::y());
+		LightBlock {
+			position: [self.position.x, self.position.y, self.position.z, self.range],
+			direction: [direction.x, direction.y, direction.z, self.kind as i32 as f32],
+			color: [self.color.x, self.color.y, self.color.z, self.intensity],
+			cone: [self.inner_cone.cos(), self.outer_cone.cos(), 0.0, 0.0],
+			shadow,
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct LightBlock {
+	position: [f32; 4],
+	direction: [f32; 4],
+	color: [f32; 4],
+	cone: [f32; 4],
+	shadow: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct LightsBlock {
+	lights: [LightBlock; MAX_LIGHTS],
+	/// Light count in `w`.
+	ambient: [f32; 4],
+	/// Exposure in `w`.
+	camera: [f32; 4],
+}
+
+/// The lights of the scene, uploaded to the `Lights` uniform block every frame.
+pub struct Lights {
+	pub lights: Vec<Light>,
+	/// Linear RGB light reaching every surface.
+	pub ambient: Vector3<f32>,
+	/// Scales the lit colour before tone mapping.
+	pub exposure: f32,
+	buffer: UniformBuffer,
+	warned: bool,
+	logger: Logger,
+}
+
+impl Lights {
+	pub fn new() -> Self {
+		Lights {
+			lights: Vec::new(),
+			ambient: Vector3::new(0.03, 0.03, 0.03),
+			exposure: 1.0,
+			buffer: UniformBuffer::new(LIGHTS_BINDING, std::mem::size_of::<LightsBlock>()),
+			warned: false,
+			logger: Logger::new("debug/lighting.log"),
+		}
+	}
+
+	pub fn add(&mut self, light: Light) {
+		self.lights.push(light);
+	}
+
+	/// Writes the lights, where their shadows are in `shadows` and the camera position specular
+	/// highlights are seen from. Lights past `MAX_LIGHTS` are left out.
+	pub fn upload(&mut self, camera_position: Vector3<f32>, shadows: &ShadowMaps) {
+		if self.lights.len() > MAX_LIGHTS && !self.warned {
+			log!(self.logger, LogLevel::Warning, "{} lights were added, only the first {} are drawn", self.lights.len(), MAX_LIGHTS);
+			self.warned = true;
+		}
+
+		let mut block = LightsBlock {
+			lights: [LightBlock::default(); MAX_LIGHTS],
+			ambient: [self.ambient.x, self.ambient.y, self.ambient.z, self.lights.len().min(MAX_LIGHTS) as f32],
+			camera: [camera_position.x, camera_position.y, camera_position.z, self.exposure],
+		};
+		for (index, (slot, light)) in block.lights.iter_mut().zip(self.lights.iter()).enumerate() {
+			*slot = light.block(shadows.light_parameters(index, light));
+		}
+		self.buffer.write(0, &block);
+	}
+}
+
+impl Default for Lights {
+	fn default() -> Self {
+		Lights::new()
+	}
+}
@@ -0,0 +1,2 @@
+pub mod manager;
+pub mod shadow;
\ No newline at end of file
@@ -0,0 +1,290 @@
+use nalgebra::{Matrix4, Orthographic3, Perspective3, Point3, Vector3, Vector4};
+
+use crate::{core::engine::{objects::gpu::{Framebuffer, Texture3D}, renderstate::manager::RenderState, shaders::{manager::Shader, permutation::ShaderFeatures, uniform::UniformBuffer}, threed::projection::ProjectionData}, log, utils::log::{manager::{LogLevel, Logger}, opengl::DebugGroup}};
+use super::manager::{Light, LightKind};
+
+/// Binding point of the `Shadows` uniform block of `resources/shaders/include/lighting.glsl`.
+pub const SHADOWS_BINDING: u32 = 2;
+/// Layers of the shadow map array, `MAX_SHADOW_MAPS` in the shaders.
+pub const MAX_SHADOW_MAPS: usize = 8;
+/// Cascades a directional light's shadow is split into, `CASCADES` in the shaders.
+pub const CASCADES: usize = 4;
+/// Texture unit of the `shadow_maps` sampler, after the material's.
+pub const SHADOW_MAP_UNIT: u32 = 5;
+/// Width and height of every layer.
+pub const SHADOW_MAP_SIZE: i32 = 2048;
+
+/// Past this distance from the camera nothing casts shadows.
+const SHADOW_DISTANCE: f32 = 100.0;
+/// Blend between logarithmic (1) and uniform (0) cascade splits.
+const SPLIT_LAMBDA: f32 = 0.75;
+/// How far behind a cascade casters are still caught.
+const CASTER_DISTANCE: f32 = 50.0;
+
+/// How a light's shadow is sampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+	/// Subtracted from the receiver's depth in the shadow map, against shadow acne.
+	pub depth_bias: f32,
+	/// World units the receiver is moved along its normal before the lookup.
+	pub normal_bias: f32,
+	/// Texels around the lookup averaged by PCF, 0 for hard shadows.
+	pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+	fn default() -> Self {
+		ShadowSettings {
+			depth_bias: 0.0005,
+			normal_bias: 0.02,
+			pcf_radius: 1,
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ShadowsBlock {
+	light_space: [[[f32; 4]; 4]; MAX_SHADOW_MAPS],
+	/// View space depth every cascade ends at.
+	cascade_splits: [f32; CASCADES],
+}
+
+/// Depth maps of the shadow casting lights, in one layer each, or `CASCADES` layers for
+/// directional lights. Fitted to the camera every frame, then rendered before the scene.
+pub struct ShadowMaps {
+	/// Light space matrix of every layer in use.
+	pub layers: Vec<Matrix4<f32>>,
+	/// First layer of every light of the `Lights` the maps were fitted to.
+	pub first_layers: Vec<Option<usize>>,
+	pub cascade_splits: [f32; CASCADES],
+	texture: Option<Texture3D>,
+	framebuffer: Option<Framebuffer>,
+	buffer: UniformBuffer,
+	warned: bool,
+	logger: Logger,
+}
+
+impl ShadowMaps {
+	pub fn new() -> Self {
+		ShadowMaps {
+			layers: Vec::new(),
+			first_layers: Vec::new(),
+			cascade_splits: [0.0; CASCADES],
+			texture: None,
+			framebuffer: None,
+			buffer: UniformBuffer::new(SHADOWS_BINDING, std::mem::size_of::<ShadowsBlock>()),
+			warned: false,
+			logger: Logger::new("debug/lighting.log"),
+		}
+	}
+
+	/// Places the layers of the shadow casting `lights`, directional cascades around the
+	/// slices of the camera frustum, and uploads their matrices.
+	pub fn fit(&mut self, lights: &[Light], projection: &ProjectionData, view: &Matrix4<f32>) {
+		self.layers.clear();
+		self.first_layers = vec![None; lights.len()];
+		self.cascade_splits = cascade_splits(projection.distance.near, projection.distance.far.min(SHADOW_DISTANCE));
+
+		let mut skipped = Vec::new();
+		for (index, light) in lights.iter().enumerate() {
+			if light.shadow.is_none() {
+				continue;
+			}
+			let needed = match light.kind {
+				LightKind::Directional => CASCADES,
+				LightKind::Spot => 1,
+				LightKind::Point => {
+					skipped.push(format!("point light {} (only directional and spot lights cast shadows)", index));
+					continue;
+				}
+			};
+			if self.layers.len() + needed > MAX_SHADOW_MAPS {
+				skipped.push(format!("light {} (all {} layers are in use)", index, MAX_SHADOW_MAPS));
+				continue;
+			}
+
+			self.first_layers[index] = Some(self.layers.len());
+			match light.kind {
+				LightKind::Directional => {
+					let mut near = projection.distance.near;
+					for far in self.cascade_splits {
+						self.layers.push(cascade_matrix(light, projection, view, near, far));
+						near = far;
+					}
+				}
+				_ => self.layers.push(spot_matrix(light)),
+			}
+		}
+
+		if !skipped.is_empty() && !self.warned {
+			log!(self.logger, LogLevel::Warning, "No shadows for {}", skipped.join(", "));
+			self.warned = true;
+		}
+
+		let mut block = ShadowsBlock {
+			light_space: [[[0.0; 4]; 4]; MAX_SHADOW_MAPS],
+			cascade_splits: self.cascade_splits,
+		};
+		for (slot, matrix) in block.light_space.iter_mut().zip(self.layers.iter()) {
+			*slot = (*matrix).into();
+		}
+		self.buffer.write(0, &block);
+	}
+
+	/// Renders the depth of every layer with `shader`. `draw` draws the casters, setting
+	/// their `model` matrix on the shader it's given and using its `SKINNED` variant for
+	/// skinned ones.
+	pub fn render(&mut self, shader: &mut Shader, mut draw: impl FnMut(&mut Shader)) {
+		if self.layers.is_empty() {
+			return;
+		}
+		self.create();
+		let Some(framebuffer) = &self.framebuffer else {
+			return;
+		};
+
+		let mut viewport = [0; 4];
+		unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+		framebuffer.bind();
+		unsafe { gl::Viewport(0, 0, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE) };
+		// Slope scaled offset on top of the per light bias
+		let previous = RenderState::current();
+		RenderState { polygon_offset: Some((2.0, 4.0)), ..RenderState::DEPTH_ONLY }.apply();
+
+		shader.use_program();
+		for (layer, matrix) in self.layers.iter().enumerate() {
+			let _group = DebugGroup::new(&format!("Shadow map {}", layer));
+			framebuffer.attach_layer(gl::DEPTH_ATTACHMENT, self.texture(), layer as i32);
+			unsafe { gl::Clear(gl::DEPTH_BUFFER_BIT) };
+			// Skinned casters switch to their variant, which needs the matrix too
+			shader.use_variant(ShaderFeatures::SKINNED);
+			shader.set_uniform_matrix4fv("light_space", matrix);
+			shader.use_program();
+			shader.set_uniform_matrix4fv("light_space", matrix);
+			draw(shader);
+		}
+
+		previous.apply();
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+			gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+		}
+	}
+
+	/// Binds the maps to `SHADOW_MAP_UNIT` for the active program of `shader`.
+	pub fn apply(&self, shader: &mut Shader) {
+		let Some(texture) = &self.texture else {
+			return;
+		};
+		if !shader.has_uniform("shadow_maps") {
+			return;
+		}
+		texture.bind(SHADOW_MAP_UNIT);
+		shader.set_uniform("shadow_maps", SHADOW_MAP_UNIT as i32);
+	}
+
+	/// The depth texture array, 0 until the maps are first rendered.
+	pub fn texture(&self) -> u32 {
+		self.texture.as_ref().map_or(0, Texture3D::id)
+	}
+
+	/// The `vec4 shadow` of a light in the `Lights` block: first layer (-1 without a shadow),
+	/// depth bias, normal bias and PCF radius.
+	pub fn light_parameters(&self, index: usize, light: &Light) -> [f32; 4] {
+		match (self.first_layers.get(index).copied().flatten(), light.shadow) {
+			(Some(layer), Some(settings)) => [layer as f32, settings.depth_bias, settings.normal_bias, settings.pcf_radius as f32],
+			_ => [-1.0, 0.0, 0.0, 0.0],
+		}
+	}
+
+	fn create(&mut self) {
+		if self.texture.is_some() {
+			return;
+		}
+		let texture = Texture3D::new(gl::TEXTURE_2D_ARRAY, gl::DEPTH_COMPONENT32F, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE, MAX_SHADOW_MAPS as i32, 1);
+		texture.label("shadow maps");
+		// Linear filtering with comparison gives 2x2 PCF per lookup for free
+		texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+		texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+		texture.parameter(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE);
+		texture.parameter(gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL);
+		// Outside of a map everything is lit
+		texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER);
+		texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER);
+		texture.parameter_floats(gl::TEXTURE_BORDER_COLOR, &[1.0; 4]);
+		self.texture = Some(texture);
+
+		let framebuffer = Framebuffer::new();
+		framebuffer.label("shadow maps");
+		framebuffer.draw_buffers(&[]);
+		self.framebuffer = Some(framebuffer);
+		log!(self.logger, LogLevel::Info, "Created {} shadow maps of {}x{}", MAX_SHADOW_MAPS, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE);
+	}
+}
+
+impl Default for ShadowMaps {
+	fn default() -> Self {
+		ShadowMaps::new()
+	}
+}
+
+/// Far distance of every cascade, closer cascades cover less of the frustum.
+fn cascade_splits(near: f32, far: f32) -> [f32; CASCADES] {
+	let mut splits = [0.0; CASCADES];
+	for (index, split) in splits.iter_mut().enumerate() {
+		let ratio = (index + 1) as f32 / CASCADES as f32;
+		let logarithmic = near * (far / near).powf(ratio);
+		let uniform = near + (far - near) * ratio;
+		*split = SPLIT_LAMBDA * logarithmic + (1.0 - SPLIT_LAMBDA) * uniform;
+	}
+	splits
+}
+
+fn up_for(direction: &Vector3<f32>) -> Vector3<f32> {
+	if direction.cross(&Vector3::y()).norm() < 0.001 { Vector3::z() } else { Vector3::y() }
+}
+
+/// Orthographic light space around the slice of the camera frustum between `near` and `far`.
+/// The bounds are a sphere and snapped to texels, so shadows don't shimmer as the camera turns.
+fn cascade_matrix(light: &Light, projection: &ProjectionData, view: &Matrix4<f32>, near: f32, far: f32) -> Matrix4<f32> {
+	let slice = Perspective3::new(projection.aspect_ratio, projection.fov, near, far).to_homogeneous();
+	let inverse = (slice * view).try_inverse().unwrap_or_else(Matrix4::identity);
+
+	let mut corners = Vec::with_capacity(8);
+	for x in [-1.0, 1.0] {
+		for y in [-1.0, 1.0] {
+			for z in [-1.0, 1.0] {
+				let corner = inverse * Vector4::new(x, y, z, 1.0);
+				corners.push(corner.xyz() / corner.w);
+			}
+		}
+	}
+	let center = corners.iter().sum::<Vector3<f32>>() / corners.len() as f32;
+	let radius = corners.iter().map(|corner| (corner - center).norm()).fold(0.0, f32::max);
+	let radius = (radius * 16.0).ceil() / 16.0;
+
+	let direction = light.direction.try_normalize(f32::EPSILON).unwrap_or(-Vector3::y());
+	let eye = center - direction * (radius + CASTER_DISTANCE);
+	let light_view = Matrix4::look_at_rh(&Point3::from(eye), &Point3::from(center), &up_for(&direction));
+	let mut light_projection = Orthographic3::new(-radius, radius, -radius, radius, 0.0, 2.0 * radius + CASTER_DISTANCE).to_homogeneous();
+
+	// Move the projection by the fraction of a texel the origin lands off the grid
+	let texels = SHADOW_MAP_SIZE as f32 / 2.0;
+	let origin = light_projection * light_view * Vector4::new(0.0, 0.0, 0.0, 1.0);
+	let offset = (origin.xy() * texels).map(f32::round) / texels - origin.xy();
+	light_projection[(0, 3)] += offset.x;
+	light_projection[(1, 3)] += offset.y;
+
+	light_projection * light_view
+}
+
+/// Perspective light space of a spot light's cone.
+fn spot_matrix(light: &Light) -> Matrix4<f32> {
+	let direction = light.direction.try_normalize(f32::EPSILON).unwrap_or(-Vector3::y());
+	let position = Point3::from(light.position);
+	let light_view = Matrix4::look_at_rh(&position, &(position + direction), &up_for(&direction));
+	let fov = (light.outer_cone * 2.0).clamp(0.1, std::f32::consts::PI - 0.1);
+	let far = if light.range > 0.0 { light.range } else { SHADOW_DISTANCE };
+	Perspective3::new(1.0, fov, 0.05, far).to_homogeneous() * light_view
+}
@@ -0,0 +1,303 @@
+use std::{collections::HashMap, path::Path};
+use image::{DynamicImage, Rgba, RgbaImage};
+use nalgebra::{Vector2, Vector3, Vector4};
+
+use crate::{core::{engine::{renderstate::manager::{CullMode, RenderState}, shaders::{manager::Shader, permutation::ShaderFeatures}}, utils::texture::{bitmap, manager::Texture}}, log, utils::log::manager::{LogLevel, Logger}};
+
+/// Texture slots of the metallic-roughness model, each sampled through its own unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureSlot {
+	Albedo,
+	Normal,
+	/// Roughness in green, metalness in blue, like glTF.
+	MetallicRoughness,
+	Occlusion,
+	Emissive,
+}
+
+impl TextureSlot {
+	pub const ALL: [TextureSlot; 5] = [
+		TextureSlot::Albedo,
+		TextureSlot::Normal,
+		TextureSlot::MetallicRoughness,
+		TextureSlot::Occlusion,
+		TextureSlot::Emissive,
+	];
+
+	pub fn unit(self) -> u32 {
+		self as u32
+	}
+
+	/// Name of the `sampler2D` uniform the slot is bound to.
+	pub fn sampler(self) -> &'static str {
+		match self {
+			TextureSlot::Albedo => "albedo_map",
+			TextureSlot::Normal => "normal_map",
+			TextureSlot::MetallicRoughness => "metallic_roughness_map",
+			TextureSlot::Occlusion => "occlusion_map",
+			TextureSlot::Emissive => "emissive_map",
+		}
+	}
+}
+
+/// A custom uniform value of a material.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaterialParameter {
+	Int(i32),
+	Bool(bool),
+	Float(f32),
+	Vec2(Vector2<f32>),
+	Vec3(Vector3<f32>),
+	Vec4(Vector4<f32>),
+}
+
+/// How a surface looks: the shader it's drawn with, its textures and the factors they're
+/// scaled by. Shaders read the factors from a `uniform Material material` struct.
+#[derive(Debug)]
+pub struct Material {
+	pub name: String,
+	/// Entry of `WindowShaders` the material is drawn with, `"default"` or `"ui"`.
+	pub shader: String,
+	pub textures: HashMap<TextureSlot, Texture>,
+
+	pub base_color: Vector4<f32>,
+	pub metallic: f32,
+	pub roughness: f32,
+	pub emissive: Vector3<f32>,
+	pub normal_scale: f32,
+	pub occlusion_strength: f32,
+	/// Fragments with less alpha are discarded, `None` for opaque materials.
+	pub alpha_cutoff: Option<f32>,
+	/// Back faces are drawn too, they're culled otherwise.
+	pub double_sided: bool,
+	/// Uniforms of the material's shader outside of the standard ones, by name.
+	pub parameters: HashMap<String, MaterialParameter>,
+
+	/// White texel bound to the slots without a texture, so the factors apply alone.
+	fallback: Option<bitmap::Texture>,
+	logger: Logger,
+}
+
+impl Material {
+	pub fn new(name: &str) -> Self {
+		Material {
+			name: name.to_string(),
+			shader: String::from("default"),
+			textures: HashMap::new(),
+
+			base_color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+			metallic: 0.0,
+			roughness: 1.0,
+			emissive: Vector3::zeros(),
+			normal_scale: 1.0,
+			occlusion_strength: 1.0,
+			alpha_cutoff: None,
+			double_sided: false,
+			parameters: HashMap::new(),
+
+			fallback: None,
+			logger: Logger::new("debug/material.log"),
+		}
+	}
+
+	/// Uses the image at `path` for `slot`. Missing files and images that can't be decoded
+	/// are logged and leave the slot empty.
+	pub fn set_texture(&mut self, slot: TextureSlot, path: &str, flip_vertical: bool) {
+		if !Path::new(path).is_file() {
+			log!(self.logger, LogLevel::Warning, "Texture \"{}\" of material \"{}\" does not exist", path, self.name);
+			return;
+		}
+		match Texture::open(path, flip_vertical) {
+			Ok(texture) => {
+				self.textures.insert(slot, texture);
+			}
+			Err(err) => {
+				log!(Logger::new("debug/model.log"), LogLevel::Error, "Failed to decode texture \"{}\" of material \"{}\": {}", path, self.name, err);
+			}
+		}
+	}
+
+	pub fn set_parameter(&mut self, name: &str, value: MaterialParameter) {
+		self.parameters.insert(name.to_string(), value);
+	}
+
+	/// Shader variant the material needs.
+	pub fn features(&self) -> ShaderFeatures {
+		ShaderFeatures::HAS_NORMAL_MAP.when(self.textures.contains_key(&TextureSlot::Normal))
+			| ShaderFeatures::ALPHA_TEST.when(self.alpha_cutoff.is_some())
+	}
+
+	/// State the material is drawn with.
+	pub fn render_state(&self) -> RenderState {
+		RenderState {
+			cull: if self.double_sided { CullMode::None } else { CullMode::Back },
+			..RenderState::DEFAULT
+		}
+	}
+
+	/// Uses the material's variant of `shader` and its `render_state`, binds the textures to
+	/// their units and sets the factors. Textures are uploaded on the first bind. Returns the
+	/// program in use.
+	pub fn bind(&mut self, shader: &mut Shader) -> u32 {
+		self.bind_variant(shader, ShaderFeatures::NONE)
+	}
+
+	/// `bind` with the features the mesh needs on top of the material's, `ShaderFeatures::SKINNED`...
+	pub fn bind_variant(&mut self, shader: &mut Shader, mesh_features: ShaderFeatures) -> u32 {
+		self.render_state().apply();
+		for texture in self.textures.values_mut() {
+			if !texture.is_initialized() {
+				texture.init();
+			}
+		}
+		let features = self.features() | mesh_features;
+		let fallback = self.fallback.get_or_insert_with(|| {
+			let mut texture = bitmap::Texture::new(RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255])), false);
+			texture.init();
+			texture
+		});
+
+		let program = shader.use_variant(features);
+		for slot in TextureSlot::ALL {
+			if !shader.has_uniform(slot.sampler()) {
+				continue;
+			}
+			match self.textures.get_mut(&slot) {
+				Some(texture) => texture.apply(slot.unit(), slot.sampler(), program),
+				None => fallback.apply(slot.unit(), slot.sampler(), program),
+			}
+		}
+
+		// Shaders only declare the factors they use.
+		let factors = [
+			("material.base_color", MaterialParameter::Vec4(self.base_color)),
+			("material.metallic", MaterialParameter::Float(self.metallic)),
+			("material.roughness", MaterialParameter::Float(self.roughness)),
+			("material.emissive", MaterialParameter::Vec3(self.emissive)),
+			("material.normal_scale", MaterialParameter::Float(self.normal_scale)),
+			("material.occlusion_strength", MaterialParameter::Float(self.occlusion_strength)),
+			("material.alpha_cutoff", MaterialParameter::Float(self.alpha_cutoff.unwrap_or(0.0))),
+		];
+		for (name, value) in factors {
+			if shader.has_uniform(name) {
+				set_parameter(shader, name, value);
+			}
+		}
+		for (name, value) in self.parameters.iter() {
+			set_parameter(shader, name, *value);
+		}
+		program
+	}
+
+	/// A material from a `newmtl` of an `.mtl` file, textures are relative to `directory`.
+	/// Reads the PBR extension (`Pr`, `Pm`, `map_Ke`, `norm`, `map_ORM`, `map_RMA`...) when present and
+	/// estimates the roughness from the shininess and specular colour otherwise.
+	pub fn from_obj(material: &tobj::Material, directory: &Path) -> Self {
+		let mut result = Material::new(&material.name);
+		let number = |key: &str| material.unknown_param.get(key).and_then(|value| value.trim().parse::<f32>().ok());
+		let texture = |name: &str| directory.join(name.split_whitespace().last().unwrap_or(name)).to_string_lossy().to_string();
+
+		let diffuse = material.diffuse.unwrap_or([1.0; 3]);
+		result.base_color = Vector4::new(diffuse[0], diffuse[1], diffuse[2], material.dissolve.unwrap_or(1.0));
+		// Materials without a specular colour (`Ks 0 0 0`) have no highlight to estimate from
+		let specular = material.specular.map_or(1.0, |specular| specular[0].max(specular[1]).max(specular[2]));
+		result.roughness = number("Pr").unwrap_or_else(|| match material.shininess {
+			Some(shininess) if specular > 0.0 => (2.0 / (shininess + 2.0)).sqrt(),
+			_ => 1.0,
+		});
+		result.metallic = number("Pm").unwrap_or(0.0);
+		if let Some(emissive) = material.emissive {
+			result.emissive = Vector3::from(emissive);
+		}
+		if result.base_color.w < 1.0 || material.dissolve_texture.is_some() {
+			result.alpha_cutoff = Some(0.5);
+		}
+
+		// OBJ texture coordinates start at the bottom left, the images are flipped to match.
+		if let Some(name) = &material.diffuse_texture {
+			result.set_texture(TextureSlot::Albedo, &texture(name), true);
+		}
+		if let Some(name) = material.normal_texture.as_ref().or(material.unknown_param.get("norm")) {
+			result.set_texture(TextureSlot::Normal, &texture(name), true);
+		}
+		if let Some(name) = material.unknown_param.get("map_ORM") {
+			result.set_texture(TextureSlot::MetallicRoughness, &texture(name), true);
+		} else if let Some(name) = material.unknown_param.get("map_RMA") {
+			result.set_texture(TextureSlot::MetallicRoughness, &texture(name), true);
+			// Roughness, metalness and occlusion in red, green and blue, moved to where glTF has them
+			if let Some(image) = result.textures.get_mut(&TextureSlot::MetallicRoughness).and_then(|texture| texture.image.as_mut()) {
+				let mut pixels = image.to_rgba8();
+				for pixel in pixels.pixels_mut() {
+					let [roughness, metallic, occlusion, alpha] = pixel.0;
+					pixel.0 = [occlusion, roughness, metallic, alpha];
+				}
+				*image = DynamicImage::ImageRgba8(pixels);
+			}
+		}
+		if let Some(name) = material.unknown_param.get("map_ao") {
+			result.set_texture(TextureSlot::Occlusion, &texture(name), true);
+		}
+		// Colour maps of the old lighting model have no place in the PBR material
+		for (map, name) in [("map_Ka", &material.ambient_texture), ("map_Ks", &material.specular_texture)] {
+			if let Some(name) = name {
+				log!(result.logger, LogLevel::Warning, "Ignoring the {} texture \"{}\" of material \"{}\", PBR materials have no use for it", map, name, result.name);
+			}
+		}
+		if let Some(name) = material.unknown_param.get("map_Ke") {
+			result.set_texture(TextureSlot::Emissive, &texture(name), true);
+			if result.emissive == Vector3::zeros() {
+				result.emissive = Vector3::new(1.0, 1.0, 1.0);
+			}
+		}
+		result
+	}
+
+	/// A material of a glTF file, `texture` gives the textures of the file by index, `None` for
+	/// the ones that can't be loaded.
+	pub fn from_gltf<E: goth_gltf::Extensions>(gltf: &goth_gltf::Gltf<E>, index: usize, mut texture: impl FnMut(usize) -> Option<Texture>) -> Self {
+		let material = &gltf.materials[index];
+		let mut result = Material::new(&material.name.clone().unwrap_or_else(|| format!("material {}", index)));
+		let pbr = &material.pbr_metallic_roughness;
+
+		result.base_color = Vector4::from(pbr.base_color_factor);
+		result.metallic = pbr.metallic_factor;
+		result.roughness = pbr.roughness_factor;
+		result.emissive = Vector3::from(material.emissive_factor);
+		result.double_sided = material.double_sided;
+		result.alpha_cutoff = match material.alpha_mode {
+			goth_gltf::AlphaMode::Mask => Some(material.alpha_cutoff),
+			_ => None,
+		};
+		if let Some(normal) = &material.normal_texture {
+			result.normal_scale = normal.scale;
+		}
+		if let Some(occlusion) = &material.occlusion_texture {
+			result.occlusion_strength = occlusion.strength;
+		}
+
+		let slots = [
+			(TextureSlot::Albedo, pbr.base_color_texture.as_ref().map(|info| info.index)),
+			(TextureSlot::Normal, material.normal_texture.as_ref().map(|info| info.index)),
+			(TextureSlot::MetallicRoughness, pbr.metallic_roughness_texture.as_ref().map(|info| info.index)),
+			(TextureSlot::Occlusion, material.occlusion_texture.as_ref().map(|info| info.index)),
+			(TextureSlot::Emissive, material.emissive_texture.as_ref().map(|info| info.index)),
+		];
+		for (slot, index) in slots {
+			if let Some(texture) = index.and_then(&mut texture) {
+				result.textures.insert(slot, texture);
+			}
+		}
+		result
+	}
+}
+
+fn set_parameter(shader: &mut Shader, name: &str, value: MaterialParameter) {
+	match value {
+		MaterialParameter::Int(value) => shader.set_uniform(name, value),
+		MaterialParameter::Bool(value) => shader.set_uniform(name, value),
+		MaterialParameter::Float(value) => shader.set_uniform(name, value),
+		MaterialParameter::Vec2(value) => shader.set_uniform(name, value),
+		MaterialParameter::Vec3(value) => shader.set_uniform(name, value),
+		MaterialParameter::Vec4(value) => shader.set_uniform(name, value),
+	}
+}
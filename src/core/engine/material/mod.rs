@@ -0,0 +1 @@
+pub mod manager;
\ No newline at end of file
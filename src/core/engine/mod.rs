@@ -1,5 +1,11 @@
+pub mod animation;
+pub mod lighting;
+pub mod material;
 pub mod objects;
 pub mod platform;
+pub mod postprocess;
+pub mod rendergraph;
+pub mod renderstate;
 pub mod ortho;
 pub mod threed;
 pub mod scene;
@@ -0,0 +1,336 @@
+use std::{num::NonZeroU32, sync::atomic::{AtomicUsize, Ordering}};
+
+use crate::{log, utils::log::{manager::{LogLevel, Logger}, opengl}};
+
+/// Kinds of GL objects the wrappers own, each has its own count of live objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+	Buffer,
+	VertexArray,
+	Texture,
+	Sampler,
+	Program,
+	Framebuffer,
+}
+
+impl ObjectKind {
+	pub const ALL: [ObjectKind; 6] = [ObjectKind::Buffer, ObjectKind::VertexArray, ObjectKind::Texture, ObjectKind::Sampler, ObjectKind::Program, ObjectKind::Framebuffer];
+
+	/// Identifier of the kind for `glObjectLabel`.
+	fn identifier(self) -> u32 {
+		match self {
+			ObjectKind::Buffer => gl::BUFFER,
+			ObjectKind::VertexArray => gl::VERTEX_ARRAY,
+			ObjectKind::Texture => gl::TEXTURE,
+			ObjectKind::Sampler => gl::SAMPLER,
+			ObjectKind::Program => gl::PROGRAM,
+			ObjectKind::Framebuffer => gl::FRAMEBUFFER,
+		}
+	}
+}
+
+static LIVE: [AtomicUsize; 6] = [const { AtomicUsize::new(0) }; 6];
+
+/// Objects of `kind` created and not dropped yet.
+pub fn live_count(kind: ObjectKind) -> usize {
+	LIVE[kind as usize].load(Ordering::Relaxed)
+}
+
+/// Logs the objects still alive to `debug/gpu.log`, for after everything should have been
+/// dropped.
+pub fn report_leaks() {
+	let mut logger = Logger::new("debug/gpu.log");
+	let leaks = ObjectKind::ALL.into_iter()
+		.map(|kind| (kind, live_count(kind)))
+		.filter(|(_, count)| *count > 0)
+		.collect::<Vec<_>>();
+	for (kind, count) in leaks.iter() {
+		log!(logger, LogLevel::Warning, "[G:LEAK] {} {:?} object(s) were never dropped", count, kind);
+	}
+	if leaks.is_empty() {
+		log!(logger, LogLevel::Info, "Every GL object was deleted");
+	}
+}
+
+/// Defines an owning wrapper of a GL object: it only exists once the object is created, is
+/// counted while alive and deletes the object when dropped.
+macro_rules! gl_object {
+	($(#[$meta:meta])* $name:ident, $kind:expr, |$id:ident| $delete:expr) => {
+		$(#[$meta])*
+		#[derive(Debug)]
+		pub struct $name {
+			id: NonZeroU32,
+		}
+
+		impl $name {
+			/// Takes ownership of an object created by the caller.
+			fn from_raw(id: u32) -> Self {
+				let id = NonZeroU32::new(id).unwrap_or_else(|| panic!("[G:CREATE] OpenGL could not create a {:?} object", $kind));
+				LIVE[$kind as usize].fetch_add(1, Ordering::Relaxed);
+				$name { id }
+			}
+
+			/// Name of the object for raw GL calls, never 0.
+			pub fn id(&self) -> u32 {
+				self.id.get()
+			}
+
+			/// Names the object in GL debug messages and graphics debuggers.
+			pub fn label(&self, name: &str) {
+				opengl::label($kind.identifier(), self.id(), name);
+			}
+		}
+
+		impl Drop for $name {
+			fn drop(&mut self) {
+				let $id = self.id();
+				unsafe { $delete };
+				LIVE[$kind as usize].fetch_sub(1, Ordering::Relaxed);
+			}
+		}
+	};
+}
+
+gl_object!(
+	/// A buffer object, for vertices, indices or blocks of the shaders.
+	Buffer, ObjectKind::Buffer, |id| gl::DeleteBuffers(1, &id)
+);
+
+impl Buffer {
+	pub fn new() -> Self {
+		let mut id = 0;
+		unsafe { gl::CreateBuffers(1, &mut id) };
+		Buffer::from_raw(id)
+	}
+
+	/// Replaces the content and size of the buffer with `data`, `usage` is `gl::STATIC_DRAW`...
+	pub fn data<T: Copy>(&self, data: &[T], usage: u32) {
+		unsafe { gl::NamedBufferData(self.id(), std::mem::size_of_val(data) as isize, data.as_ptr() as *const _, usage) };
+	}
+
+	/// Allocates `size` undefined bytes.
+	pub fn allocate(&self, size: usize, usage: u32) {
+		unsafe { gl::NamedBufferData(self.id(), size as isize, std::ptr::null(), usage) };
+	}
+
+	/// Copies `data` into the buffer at byte `offset`.
+	pub fn sub_data<T: Copy>(&self, offset: usize, data: &[T]) {
+		unsafe { gl::NamedBufferSubData(self.id(), offset as isize, std::mem::size_of_val(data) as isize, data.as_ptr() as *const _) };
+	}
+
+	/// Binds the buffer to `target`, `gl::ARRAY_BUFFER`, `gl::ELEMENT_ARRAY_BUFFER`...
+	pub fn bind(&self, target: u32) {
+		unsafe { gl::BindBuffer(target, self.id()) };
+	}
+
+	/// Binds the buffer to the indexed `binding` of `target`, `gl::UNIFORM_BUFFER` or `gl::SHADER_STORAGE_BUFFER`.
+	pub fn bind_base(&self, target: u32, binding: u32) {
+		unsafe { gl::BindBufferBase(target, binding, self.id()) };
+	}
+}
+
+impl Default for Buffer {
+	fn default() -> Self {
+		Buffer::new()
+	}
+}
+
+gl_object!(
+	/// A vertex array object, the vertex buffers, attribute formats and index buffer of a mesh.
+	VertexArray, ObjectKind::VertexArray, |id| gl::DeleteVertexArrays(1, &id)
+);
+
+impl VertexArray {
+	pub fn new() -> Self {
+		let mut id = 0;
+		unsafe { gl::CreateVertexArrays(1, &mut id) };
+		VertexArray::from_raw(id)
+	}
+
+	pub fn bind(&self) {
+		unsafe { gl::BindVertexArray(self.id()) };
+	}
+
+	pub fn unbind() {
+		unsafe { gl::BindVertexArray(0) };
+	}
+}
+
+impl Default for VertexArray {
+	fn default() -> Self {
+		VertexArray::new()
+	}
+}
+
+gl_object!(
+	/// A 2D texture with immutable storage.
+	Texture2D, ObjectKind::Texture, |id| gl::DeleteTextures(1, &id)
+);
+
+impl Texture2D {
+	/// A `width` by `height` texture of the sized `format` (`gl::RGBA8`, `gl::RGBA16F`...)
+	/// with `levels` mipmap levels.
+	pub fn new(format: u32, width: i32, height: i32, levels: i32) -> Self {
+		let mut id = 0;
+		unsafe {
+			gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id);
+			gl::TextureStorage2D(id, levels.max(1), format, width, height);
+		}
+		Texture2D::from_raw(id)
+	}
+
+	/// Mipmap levels of a full chain down to 1x1.
+	pub fn full_levels(width: i32, height: i32) -> i32 {
+		32 - (width.max(height).max(1) as u32).leading_zeros() as i32
+	}
+
+	/// Copies the whole first level from `pixels` of `format` (`gl::RGBA`...) and `kind` (`gl::UNSIGNED_BYTE`...).
+	pub fn upload<T: Copy>(&self, width: i32, height: i32, format: u32, kind: u32, pixels: &[T]) {
+		unsafe { gl::TextureSubImage2D(self.id(), 0, 0, 0, width, height, format, kind, pixels.as_ptr() as *const _) };
+	}
+
+	pub fn generate_mipmaps(&self) {
+		unsafe { gl::GenerateTextureMipmap(self.id()) };
+	}
+
+	pub fn parameter(&self, name: u32, value: u32) {
+		unsafe { gl::TextureParameteri(self.id(), name, value as i32) };
+	}
+
+	pub fn bind(&self, unit: u32) {
+		unsafe { gl::BindTextureUnit(unit, self.id()) };
+	}
+}
+
+gl_object!(
+	/// A texture with three dimensions of immutable storage, a 3D texture or an array of 2D layers.
+	Texture3D, ObjectKind::Texture, |id| gl::DeleteTextures(1, &id)
+);
+
+impl Texture3D {
+	/// A `width` by `height` by `depth` texture of `target` (`gl::TEXTURE_3D`, `gl::TEXTURE_2D_ARRAY`...)
+	/// and the sized `format`, with `levels` mipmap levels.
+	pub fn new(target: u32, format: u32, width: i32, height: i32, depth: i32, levels: i32) -> Self {
+		let mut id = 0;
+		unsafe {
+			gl::CreateTextures(target, 1, &mut id);
+			gl::TextureStorage3D(id, levels.max(1), format, width, height, depth);
+		}
+		Texture3D::from_raw(id)
+	}
+
+	/// Copies the whole first level from `pixels` of `format` (`gl::RGBA`...) and `kind` (`gl::UNSIGNED_BYTE`...).
+	pub fn upload<T: Copy>(&self, width: i32, height: i32, depth: i32, format: u32, kind: u32, pixels: &[T]) {
+		unsafe { gl::TextureSubImage3D(self.id(), 0, 0, 0, 0, width, height, depth, format, kind, pixels.as_ptr() as *const _) };
+	}
+
+	pub fn parameter(&self, name: u32, value: u32) {
+		unsafe { gl::TextureParameteri(self.id(), name, value as i32) };
+	}
+
+	pub fn parameter_floats(&self, name: u32, values: &[f32]) {
+		unsafe { gl::TextureParameterfv(self.id(), name, values.as_ptr()) };
+	}
+
+	pub fn bind(&self, unit: u32) {
+		unsafe { gl::BindTextureUnit(unit, self.id()) };
+	}
+}
+
+gl_object!(
+	/// Filtering and wrapping of the textures sampled through the unit it's bound to.
+	Sampler, ObjectKind::Sampler, |id| gl::DeleteSamplers(1, &id)
+);
+
+impl Sampler {
+	pub fn new() -> Self {
+		let mut id = 0;
+		unsafe { gl::CreateSamplers(1, &mut id) };
+		Sampler::from_raw(id)
+	}
+
+	pub fn parameter(&self, name: u32, value: u32) {
+		unsafe { gl::SamplerParameteri(self.id(), name, value as i32) };
+	}
+
+	pub fn bind(&self, unit: u32) {
+		unsafe { gl::BindSampler(unit, self.id()) };
+	}
+}
+
+impl Default for Sampler {
+	fn default() -> Self {
+		Sampler::new()
+	}
+}
+
+gl_object!(
+	/// A shader program, linked or loaded from a binary.
+	Program, ObjectKind::Program, |id| gl::DeleteProgram(id)
+);
+
+impl Program {
+	pub fn new() -> Self {
+		Program::from_raw(unsafe { gl::CreateProgram() })
+	}
+
+	pub fn use_program(&self) {
+		unsafe { gl::UseProgram(self.id()) };
+	}
+}
+
+impl Default for Program {
+	fn default() -> Self {
+		Program::new()
+	}
+}
+
+gl_object!(
+	/// A framebuffer object, the textures a pass draws into.
+	Framebuffer, ObjectKind::Framebuffer, |id| gl::DeleteFramebuffers(1, &id)
+);
+
+impl Framebuffer {
+	pub fn new() -> Self {
+		let mut id = 0;
+		unsafe { gl::CreateFramebuffers(1, &mut id) };
+		Framebuffer::from_raw(id)
+	}
+
+	/// Attaches the first level of `texture` to `attachment`, `gl::COLOR_ATTACHMENT0`, `gl::DEPTH_ATTACHMENT`...
+	pub fn attach(&self, attachment: u32, texture: u32) {
+		unsafe { gl::NamedFramebufferTexture(self.id(), attachment, texture, 0) };
+	}
+
+	/// Attaches `layer` of the first level of an array `texture`.
+	pub fn attach_layer(&self, attachment: u32, texture: u32, layer: i32) {
+		unsafe { gl::NamedFramebufferTextureLayer(self.id(), attachment, texture, 0, layer) };
+	}
+
+	/// Colour attachments the fragment outputs go to in order, none for depth only framebuffers.
+	pub fn draw_buffers(&self, attachments: &[u32]) {
+		unsafe {
+			if attachments.is_empty() {
+				gl::NamedFramebufferDrawBuffer(self.id(), gl::NONE);
+				gl::NamedFramebufferReadBuffer(self.id(), gl::NONE);
+			} else {
+				gl::NamedFramebufferDrawBuffers(self.id(), attachments.len() as i32, attachments.as_ptr());
+			}
+		}
+	}
+
+	/// Completeness status, `gl::FRAMEBUFFER_COMPLETE` when it can be drawn into.
+	pub fn status(&self) -> u32 {
+		unsafe { gl::CheckNamedFramebufferStatus(self.id(), gl::FRAMEBUFFER) }
+	}
+
+	pub fn bind(&self) {
+		unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.id()) };
+	}
+}
+
+impl Default for Framebuffer {
+	fn default() -> Self {
+		Framebuffer::new()
+	}
+}
\ No newline at end of file
@@ -1,38 +1,45 @@
-use gl::types::{GLint, GLsizei, GLuint, GLvoid};
 use image::{ImageBuffer, Rgba};
 
-#[derive(Debug, Clone, Default)]
+use super::gpu::{Buffer, Texture2D, VertexArray};
+
+/// Vertex array, vertex buffer and index buffer of a mesh, each created by its `generate_*`.
+/// They're deleted when dropped.
+#[derive(Debug, Default)]
 pub struct GraphicsObjects {
-    pub vbo: u32,
-    pub vao: u32,
-    pub ebo: u32,
+    pub vbo: Option<Buffer>,
+    pub vao: Option<VertexArray>,
+    pub ebo: Option<Buffer>,
 }
 
 impl GraphicsObjects {
     pub fn generate_vbo(&mut self) {
-        unsafe {
-            gl::GenBuffers(1, &mut self.vbo);
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-        }
+        self.vbo.get_or_insert_with(Buffer::new).bind(gl::ARRAY_BUFFER);
     }
 
     pub fn generate_vao(&mut self) {
-        unsafe {
-            gl::GenVertexArrays(1, &mut self.vao);
-            gl::BindVertexArray(self.vao);
-        }
+        self.vao.get_or_insert_with(VertexArray::new).bind();
     }
 
     pub fn generate_ebo(&mut self) {
-        unsafe {
-            gl::GenBuffers(1, &mut self.ebo);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+        self.ebo.get_or_insert_with(Buffer::new).bind(gl::ELEMENT_ARRAY_BUFFER);
+    }
+
+    /// Names the objects generated so far after `name` in GL debug messages.
+    pub fn label(&self, name: &str) {
+        if let Some(vao) = &self.vao {
+            vao.label(&format!("{} vertex array", name));
+        }
+        if let Some(vbo) = &self.vbo {
+            vbo.label(&format!("{} vertices", name));
+        }
+        if let Some(ebo) = &self.ebo {
+            ebo.label(&format!("{} indices", name));
         }
     }
 
     pub fn bind_vbo(&mut self) {
-        unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+        if let Some(vbo) = &self.vbo {
+            vbo.bind(gl::ARRAY_BUFFER);
         }
     }
 
@@ -43,20 +50,18 @@ impl GraphicsObjects {
     }
 
     pub fn bind_vao(&mut self) {
-        unsafe {
-            gl::BindVertexArray(self.vao);
+        if let Some(vao) = &self.vao {
+            vao.bind();
         }
     }
 
     pub fn unbind_vao(&mut self) {
-        unsafe {
-            gl::BindVertexArray(0);
-        }
+        VertexArray::unbind();
     }
 
     pub fn bind_ebo(&mut self) {
-        unsafe {
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+        if let Some(ebo) = &self.ebo {
+            ebo.bind(gl::ELEMENT_ARRAY_BUFFER);
         }
     }
 
@@ -68,44 +73,23 @@ impl GraphicsObjects {
 }
 
 impl GraphicsObjects {
-	pub fn create_texture(&mut self, img: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> GLuint {
-        let (width, height) = img.dimensions();
-        let raw_image = img.as_raw();
+	pub fn create_texture(&mut self, img: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Texture2D {
+        let (width, height) = (img.width() as i32, img.height() as i32);
 
-        // Generate a texture ID
-        let mut texture_id: GLuint = 0;
-        unsafe {
-            gl::GenTextures(1, &mut texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
-
-            // Set texture parameters
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-
-            // Upload texture data to GPU
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGBA as GLint,
-                width as GLsizei,
-                height as GLsizei,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                raw_image.as_ptr() as *const GLvoid,
-            );
-
-            // Generate mipmaps
-            gl::GenerateMipmap(gl::TEXTURE_2D);
-        }
+        let texture = Texture2D::new(gl::RGBA8, width, height, Texture2D::full_levels(width, height));
 
-        // Unbind the texture
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-        }
+        // Set texture parameters
+        texture.parameter(gl::TEXTURE_WRAP_S, gl::REPEAT);
+        texture.parameter(gl::TEXTURE_WRAP_T, gl::REPEAT);
+        texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+
+        // Upload texture data to GPU
+        texture.upload(width, height, gl::RGBA, gl::UNSIGNED_BYTE, img.as_raw());
+
+        // Generate mipmaps
+        texture.generate_mipmaps();
 
-        texture_id
+        texture
     }
 }
\ No newline at end of file
@@ -0,0 +1,238 @@
+use std::fmt;
+
+use crate::core::engine::shaders::reflection::{self, ProgramReflection};
+use super::gpu::VertexArray;
+
+/// What a vertex attribute holds. Every semantic has a fixed location the vertex shaders
+/// declare it at, `layout (location = 0) in vec3 aPos;` for positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Semantic {
+	Position,
+	TexCoord0,
+	Normal,
+	/// `xyz` tangent and `w` the handedness of the bitangent.
+	Tangent,
+	Color,
+	/// Indices of the joints a skinned vertex follows.
+	Joints,
+	/// How much each of `Joints` moves the vertex.
+	Weights,
+	TexCoord1,
+}
+
+impl Semantic {
+	pub const ALL: [Semantic; 8] = [Semantic::Position, Semantic::TexCoord0, Semantic::Normal, Semantic::Tangent, Semantic::Color, Semantic::Joints, Semantic::Weights, Semantic::TexCoord1];
+
+	pub fn location(self) -> u32 {
+		self as u32
+	}
+
+	/// The semantic declared at `location`.
+	pub fn at(location: u32) -> Option<Semantic> {
+		Semantic::ALL.get(location as usize).copied()
+	}
+}
+
+/// How the values of an attribute are stored in the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeFormat {
+	Float,
+	Vec2,
+	Vec3,
+	Vec4,
+	/// Four bytes, read as 0..1 floats when normalised and as integers otherwise.
+	UByte4,
+	/// Four shorts, read as 0..1 floats when normalised and as integers otherwise.
+	UShort4,
+}
+
+impl AttributeFormat {
+	pub fn components(self) -> i32 {
+		match self {
+			AttributeFormat::Float => 1,
+			AttributeFormat::Vec2 => 2,
+			AttributeFormat::Vec3 => 3,
+			AttributeFormat::Vec4 | AttributeFormat::UByte4 | AttributeFormat::UShort4 => 4,
+		}
+	}
+
+	fn gl_type(self) -> u32 {
+		match self {
+			AttributeFormat::Float | AttributeFormat::Vec2 | AttributeFormat::Vec3 | AttributeFormat::Vec4 => gl::FLOAT,
+			AttributeFormat::UByte4 => gl::UNSIGNED_BYTE,
+			AttributeFormat::UShort4 => gl::UNSIGNED_SHORT,
+		}
+	}
+
+	/// Bytes of one value.
+	pub fn size(self) -> u32 {
+		match self {
+			AttributeFormat::UByte4 => 4,
+			AttributeFormat::UShort4 => 8,
+			_ => 4 * self.components() as u32,
+		}
+	}
+
+	fn is_float(self) -> bool {
+		self.gl_type() == gl::FLOAT
+	}
+}
+
+/// One attribute of a `VertexLayout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+	pub semantic: Semantic,
+	pub format: AttributeFormat,
+	/// Bytes from the start of a vertex in its buffer.
+	pub offset: u32,
+	/// Integer formats are read as 0..1 floats.
+	pub normalized: bool,
+	/// Buffer the attribute is read from, the index of the buffer given to the `Mesh`.
+	pub binding: u32,
+}
+
+impl VertexAttribute {
+	/// Whether the shader reads the attribute as floats, not integers.
+	fn reads_float(&self) -> bool {
+		self.format.is_float() || self.normalized
+	}
+}
+
+/// Where the attributes of a mesh's vertices are in its buffers. Attributes added one after
+/// the other are interleaved in one buffer, `next_buffer` starts another.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VertexLayout {
+	pub attributes: Vec<VertexAttribute>,
+	/// Bytes from a vertex to the next in each buffer.
+	pub strides: Vec<u32>,
+}
+
+impl VertexLayout {
+	pub fn new() -> Self {
+		VertexLayout::default()
+	}
+
+	/// Every attribute interleaved in one buffer, in order.
+	pub fn interleaved(attributes: &[(Semantic, AttributeFormat)]) -> Self {
+		attributes.iter().fold(VertexLayout::new(), |layout, (semantic, format)| layout.with(*semantic, *format))
+	}
+
+	/// Every attribute in a buffer of its own, in order.
+	pub fn separate(attributes: &[(Semantic, AttributeFormat)]) -> Self {
+		attributes.iter().enumerate().fold(VertexLayout::new(), |layout, (index, (semantic, format))| {
+			let layout = if index == 0 { layout } else { layout.next_buffer() };
+			layout.with(*semantic, *format)
+		})
+	}
+
+	/// Appends a float attribute, or an integer one read as integers, to the current buffer.
+	pub fn with(self, semantic: Semantic, format: AttributeFormat) -> Self {
+		self.push(semantic, format, false)
+	}
+
+	/// Appends an integer attribute read as 0..1 floats, like 8 bit colours.
+	pub fn with_normalized(self, semantic: Semantic, format: AttributeFormat) -> Self {
+		self.push(semantic, format, true)
+	}
+
+	/// Attributes added from now on are read from the next buffer.
+	pub fn next_buffer(mut self) -> Self {
+		if self.strides.is_empty() {
+			self.strides.push(0);
+		}
+		self.strides.push(0);
+		self
+	}
+
+	fn push(mut self, semantic: Semantic, format: AttributeFormat, normalized: bool) -> Self {
+		if self.strides.is_empty() {
+			self.strides.push(0);
+		}
+		let binding = self.strides.len() as u32 - 1;
+		let stride = self.strides.last_mut().unwrap();
+		self.attributes.push(VertexAttribute { semantic, format, offset: *stride, normalized, binding });
+		*stride += format.size();
+		self
+	}
+
+	pub fn attribute(&self, semantic: Semantic) -> Option<&VertexAttribute> {
+		self.attributes.iter().find(|attribute| attribute.semantic == semantic)
+	}
+
+	/// Buffers the layout reads from.
+	pub fn buffers(&self) -> usize {
+		self.strides.len()
+	}
+
+	/// Sets up the attributes of `vertex_array`, which reads buffer `n` from binding `n`.
+	pub fn apply(&self, vertex_array: &VertexArray) {
+		let vao = vertex_array.id();
+		for attribute in self.attributes.iter() {
+			let location = attribute.semantic.location();
+			let format = attribute.format;
+			unsafe {
+				gl::EnableVertexArrayAttrib(vao, location);
+				if attribute.reads_float() {
+					let normalized = if attribute.normalized { gl::TRUE } else { gl::FALSE };
+					gl::VertexArrayAttribFormat(vao, location, format.components(), format.gl_type(), normalized, attribute.offset);
+				} else {
+					gl::VertexArrayAttribIFormat(vao, location, format.components(), format.gl_type(), attribute.offset);
+				}
+				gl::VertexArrayAttribBinding(vao, location, attribute.binding);
+			}
+		}
+	}
+
+	/// Checks the layout gives every active attribute of a program a value of the right kind.
+	pub fn validate(&self, reflection: &ProgramReflection) -> Result<(), LayoutError> {
+		let mut attributes = reflection.attributes.values().filter(|attribute| attribute.location >= 0).collect::<Vec<_>>();
+		attributes.sort_by_key(|attribute| attribute.location);
+
+		for attribute in attributes {
+			let location = attribute.location as u32;
+			let Some(provided) = Semantic::at(location).and_then(|semantic| self.attribute(semantic)) else {
+				return Err(LayoutError::Missing { name: attribute.name.clone(), location });
+			};
+			// Missing components are filled in, only floats and integers can't be mixed
+			if reads_float(attribute.kind) != provided.reads_float() {
+				return Err(LayoutError::Mismatch {
+					name: attribute.name.clone(),
+					kind: reflection::type_name(attribute.kind),
+					format: provided.format,
+					normalized: provided.normalized,
+				});
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Whether a GLSL attribute type is made of floats rather than integers.
+fn reads_float(kind: u32) -> bool {
+	!matches!(kind,
+		gl::INT | gl::INT_VEC2 | gl::INT_VEC3 | gl::INT_VEC4
+		| gl::UNSIGNED_INT | gl::UNSIGNED_INT_VEC2 | gl::UNSIGNED_INT_VEC3 | gl::UNSIGNED_INT_VEC4)
+}
+
+/// Why a `VertexLayout` can't feed a program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutError {
+	/// The program reads an attribute the layout doesn't have.
+	Missing { name: String, location: u32 },
+	/// The layout has the attribute but the program reads floats from integers or the other way around.
+	Mismatch { name: String, kind: &'static str, format: AttributeFormat, normalized: bool },
+}
+
+impl fmt::Display for LayoutError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LayoutError::Missing { name, location } => {
+				let semantic = Semantic::at(*location).map_or(String::from("no semantic"), |semantic| format!("{:?}", semantic));
+				write!(f, "[V:MISSING] The vertex layout has no attribute for \"{}\" at location {} ({})", name, location, semantic)
+			}
+			LayoutError::Mismatch { name, kind, format, normalized } => {
+				write!(f, "[V:TYPE] \"{}\" is a {} but the vertex layout has it as {:?}{}", name, kind, format, if *normalized { " normalised" } else { "" })
+			}
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,180 @@
+use crate::core::engine::shaders::manager::Shader;
+use super::{gpu::{Buffer, VertexArray}, layout::{LayoutError, VertexLayout}};
+
+/// How often the buffers of a mesh change, given to the driver as their usage hint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshUsage {
+	/// Uploaded once at load time.
+	Static,
+	/// Updated now and then, like a mesh edited in game.
+	Dynamic,
+	/// Replaced every frame or so, like UI or particles.
+	Stream,
+}
+
+impl MeshUsage {
+	fn gl(self) -> u32 {
+		match self {
+			MeshUsage::Static => gl::STATIC_DRAW,
+			MeshUsage::Dynamic => gl::DYNAMIC_DRAW,
+			MeshUsage::Stream => gl::STREAM_DRAW,
+		}
+	}
+}
+
+/// Bytes of `data`, to give vertices of any type without padding to a `Mesh`.
+pub fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+	unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+/// Geometry uploaded to the GPU once, the buffers a `VertexLayout` describes and an optional
+/// index buffer behind a vertex array that remembers their layout. Drawing only binds the
+/// vertex array, `update_buffer` and `update_indices` replace the data of dynamic meshes.
+#[derive(Debug)]
+pub struct Mesh {
+	vertex_array: VertexArray,
+	layout: VertexLayout,
+	/// Buffer of every binding of `layout`.
+	buffers: Vec<MeshBuffer>,
+	indices: Option<MeshBuffer>,
+	vertex_count: i32,
+	index_count: i32,
+	usage: MeshUsage,
+	/// Builds of the programs `validate` already checked the layout against.
+	validated: Vec<u64>,
+	/// `gl::TRIANGLES` unless set otherwise.
+	pub primitive: u32,
+}
+
+impl Mesh {
+	/// Uploads a buffer of vertices for every buffer of `layout` (see `as_bytes`) and `indices`,
+	/// the vertices are drawn in order without them.
+	pub fn new(layout: VertexLayout, buffers: &[&[u8]], indices: Option<&[u32]>, usage: MeshUsage) -> Self {
+		assert!(buffers.len() == layout.buffers(), "[V:BUFFERS] The vertex layout reads {} buffers but the mesh has {}", layout.buffers(), buffers.len());
+
+		let vertex_array = VertexArray::new();
+		layout.apply(&vertex_array);
+		let buffers = buffers.iter().zip(layout.strides.iter()).enumerate().map(|(binding, (data, stride))| {
+			let buffer = MeshBuffer::new(data, usage);
+			unsafe { gl::VertexArrayVertexBuffer(vertex_array.id(), binding as u32, buffer.buffer.id(), 0, *stride as i32) };
+			buffer
+		}).collect::<Vec<_>>();
+
+		let index_buffer = indices.map(|indices| {
+			let buffer = MeshBuffer::new(indices, usage);
+			unsafe { gl::VertexArrayElementBuffer(vertex_array.id(), buffer.buffer.id()) };
+			buffer
+		});
+
+		let mut mesh = Mesh {
+			vertex_array,
+			layout,
+			buffers,
+			indices: index_buffer,
+			vertex_count: 0,
+			index_count: indices.map_or(0, |indices| indices.len() as i32),
+			usage,
+			validated: Vec::new(),
+			primitive: gl::TRIANGLES,
+		};
+		mesh.count_vertices();
+		mesh
+	}
+
+	pub fn layout(&self) -> &VertexLayout {
+		&self.layout
+	}
+
+	/// Names the vertex array and buffers after `name` in GL debug messages.
+	pub fn label(&self, name: &str) {
+		self.vertex_array.label(&format!("{} vertex array", name));
+		for (binding, buffer) in self.buffers.iter().enumerate() {
+			buffer.buffer.label(&format!("{} vertices {}", name, binding));
+		}
+		if let Some(indices) = &self.indices {
+			indices.buffer.label(&format!("{} indices", name));
+		}
+	}
+
+	/// Replaces the vertices of the buffer `binding` of the layout.
+	pub fn update_buffer(&mut self, binding: usize, data: &[u8]) {
+		self.buffers[binding].replace(data, self.usage);
+		if binding == 0 {
+			self.count_vertices();
+		}
+	}
+
+	/// Replaces the indices, meshes made without them keep drawing their vertices in order.
+	pub fn update_indices(&mut self, indices: &[u32]) {
+		let Some(buffer) = self.indices.as_mut() else {
+			return;
+		};
+		buffer.replace(indices, self.usage);
+		self.index_count = indices.len() as i32;
+	}
+
+	/// Vertices in the first buffer.
+	pub fn vertex_count(&self) -> i32 {
+		self.vertex_count
+	}
+
+	fn count_vertices(&mut self) {
+		let stride = self.layout.strides.first().copied().unwrap_or(0).max(1);
+		self.vertex_count = self.buffers.first().map_or(0, |buffer| buffer.size as u32 / stride) as i32;
+	}
+
+	/// Checks the layout against the active program of `shader` the first time the mesh is
+	/// drawn with it, later checks of the same build of the program pass.
+	pub fn validate(&mut self, shader: &Shader) -> Result<(), LayoutError> {
+		let build = shader.active_build();
+		if self.validated.contains(&build) {
+			return Ok(());
+		}
+		self.validated.push(build);
+		match shader.reflection() {
+			Some(reflection) => self.layout.validate(reflection),
+			None => Ok(()),
+		}
+	}
+
+	/// Draws the mesh with the program in use.
+	pub fn draw(&self) {
+		self.vertex_array.bind();
+		unsafe {
+			if self.indices.is_some() {
+				gl::DrawElements(self.primitive, self.index_count, gl::UNSIGNED_INT, std::ptr::null());
+			} else {
+				gl::DrawArrays(self.primitive, 0, self.vertex_count);
+			}
+		}
+		VertexArray::unbind();
+	}
+}
+
+/// A buffer of a mesh and its size.
+#[derive(Debug)]
+struct MeshBuffer {
+	buffer: Buffer,
+	/// Bytes allocated.
+	size: usize,
+}
+
+impl MeshBuffer {
+	fn new<T: Copy>(data: &[T], usage: MeshUsage) -> Self {
+		let buffer = Buffer::new();
+		buffer.data(data, usage.gl());
+		MeshBuffer { buffer, size: std::mem::size_of_val(data) }
+	}
+
+	/// Writes `data` in place when it's as big as the old data. Streaming buffers orphan the
+	/// old storage so the GPU doesn't have to finish drawing it first.
+	fn replace<T: Copy>(&mut self, data: &[T], usage: MeshUsage) {
+		let size = std::mem::size_of_val(data);
+		if usage == MeshUsage::Stream || size != self.size {
+			self.buffer.data(data, usage.gl());
+			self.size = size;
+		} else {
+			self.buffer.sub_data(0, data);
+		}
+	}
+}
\ No newline at end of file
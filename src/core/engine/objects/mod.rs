@@ -1 +1,4 @@
-pub mod graphics;
\ No newline at end of file
+pub mod gpu;
+pub mod graphics;
+pub mod layout;
+pub mod mesh;
\ No newline at end of file
@@ -0,0 +1,188 @@
+use std::path::Path;
+
+use nalgebra::Vector3;
+
+use crate::core::{engine::script::{error::ScriptLanguage, parser::{JSParser, LuaParser}}, utils::model::manager::ModelLoader};
+use super::implementations::{SpawnedModel, Window};
+
+/// An engine command of the developer console, receives the words typed after its name.
+pub type ConsoleCommand = fn(&mut Window, &[&str]) -> Result<String, String>;
+
+pub struct RegisteredCommand {
+	pub usage: String,
+	pub description: String,
+	pub handler: ConsoleCommand,
+}
+
+impl Window {
+	/// Makes `name` callable from the developer console and the remote console.
+	pub fn register_command(&mut self, name: &str, usage: &str, description: &str, handler: ConsoleCommand) {
+		self.commands.insert(name.to_string(), RegisteredCommand {
+			usage: usage.to_string(),
+			description: description.to_string(),
+			handler,
+		});
+	}
+
+	pub(super) fn register_default_commands(&mut self) {
+		self.register_command("help", "help", "Lists the engine commands", help);
+		self.register_command("render_graph", "render_graph", "Shows the passes of the last frame and the textures they use", render_graph);
+		self.register_command("reload_shaders", "reload_shaders", "Recompiles every shader from disk, failing ones keep their previous program", reload_shaders);
+		self.register_command("set_timescale", "set_timescale <scale>", "Speeds up or slows down game time, 1 is normal speed", set_timescale);
+		self.register_command("spawn", "spawn <model> [x y z]", "Loads a model and places it in the world", spawn);
+		self.register_command("animate", "animate <spawned model> <clip> [fade seconds]", "Plays a clip of a spawned model, cross-fading from the playing ones when a fade is given", animate);
+	}
+
+	/// Runs a console line, engine commands take precedence over script code.
+	/// `lua` and `js` switch the language following lines are evaluated in.
+	pub fn run_console_line(
+		&mut self,
+		language: &mut ScriptLanguage,
+		line: &str,
+		lua_parser: &mut LuaParser,
+		js_parser: &mut JSParser
+	) -> Result<String, String> {
+		let words = line.split_whitespace().collect::<Vec<_>>();
+		match words.as_slice() {
+			["lua"] => {
+				*language = ScriptLanguage::Lua;
+				return Ok("Evaluating Lua".to_string());
+			}
+			["js"] => {
+				*language = ScriptLanguage::JavaScript;
+				return Ok("Evaluating JS".to_string());
+			}
+			[name, arguments @ ..] => {
+				if let Some(command) = self.commands.get(*name) {
+					return (command.handler)(self, arguments);
+				}
+			}
+			[] => return Ok(String::new()),
+		}
+
+		let result = match language {
+			ScriptLanguage::Lua => lua_parser.evaluate(line),
+			ScriptLanguage::JavaScript => js_parser.evaluate(line),
+		};
+		result.map_err(|error| error.to_string())
+	}
+
+	/// Completions of `path` among the engine commands and the globals of `language`.
+	pub fn complete_console_path(
+		&self,
+		language: ScriptLanguage,
+		path: &str,
+		lua_parser: &LuaParser,
+		js_parser: &JSParser
+	) -> Vec<String> {
+		let mut candidates = match language {
+			ScriptLanguage::Lua => lua_parser.complete(path),
+			ScriptLanguage::JavaScript => js_parser.complete(path),
+		};
+
+		let mut commands = self.commands.keys()
+			.filter(|name| name.starts_with(path) && !candidates.contains(name))
+			.cloned()
+			.collect::<Vec<_>>();
+		commands.sort();
+		candidates.extend(commands);
+		candidates
+	}
+}
+
+fn help(window: &mut Window, _: &[&str]) -> Result<String, String> {
+	let mut commands = window.commands.values().collect::<Vec<_>>();
+	commands.sort_by(|a, b| a.usage.cmp(&b.usage));
+
+	let mut text = String::from("lua / js - switch the language lines are evaluated in\nclear - clears the console");
+	for command in commands {
+		text.push_str(&format!("\n{} - {}", command.usage, command.description));
+	}
+	Ok(text)
+}
+
+fn reload_shaders(window: &mut Window, _: &[&str]) -> Result<String, String> {
+	let shaders = window.shaders.all();
+	let count = shaders.len();
+	let errors = shaders.into_iter()
+		.map(|shader| shader.reload())
+		.filter_map(Result::err)
+		.map(|err| err.to_string())
+		.collect::<Vec<_>>();
+	if !errors.is_empty() {
+		return Err(format!("{}\nFailing shaders keep their previous program", errors.join("\n")));
+	}
+	Ok(format!("Reloaded {} shaders", count))
+}
+
+fn render_graph(window: &mut Window, _: &[&str]) -> Result<String, String> {
+	if window.render_graph.is_empty() {
+		return Err("No frame was drawn yet".to_string());
+	}
+	Ok(window.render_graph.clone())
+}
+
+fn set_timescale(window: &mut Window, arguments: &[&str]) -> Result<String, String> {
+	let scale = match arguments {
+		[scale] => scale.parse::<f32>().map_err(|_| format!("\"{}\" is not a number", scale))?,
+		_ => return Err("Usage: set_timescale <scale>".to_string()),
+	};
+	if !scale.is_finite() || scale < 0.0 {
+		return Err("The time scale must be zero or positive".to_string());
+	}
+
+	window.timescale = scale;
+	Ok(format!("Time scale set to {}", scale))
+}
+
+fn spawn(window: &mut Window, arguments: &[&str]) -> Result<String, String> {
+	let (path, position) = match arguments {
+		[path] => (*path, [0.0; 3]),
+		[path, x, y, z] => {
+			let mut position = [0.0; 3];
+			for (axis, value) in position.iter_mut().zip([x, y, z]) {
+				*axis = value.parse::<f32>().map_err(|_| format!("\"{}\" is not a number", value))?;
+			}
+			(*path, position)
+		}
+		_ => return Err("Usage: spawn <model> [x y z]".to_string()),
+	};
+
+	if !Path::new(path).is_file() {
+		return Err(format!("\"{}\" does not exist", path));
+	}
+
+	let mut model = ModelLoader::new(path, true);
+	model.load();
+	// Animated models start playing their first clip
+	if !model.clips.is_empty() {
+		model.player.play(0, true);
+	}
+	let translation = Vector3::from(position);
+	window.spawned.push(SpawnedModel { model, translation });
+	Ok(format!("Spawned \"{}\" at ({}, {}, {})", path, translation.x, translation.y, translation.z))
+}
+
+
+fn animate(window: &mut Window, arguments: &[&str]) -> Result<String, String> {
+	let (index, clip, fade) = match arguments {
+		[index, clip] => (*index, *clip, None),
+		[index, clip, fade] => (*index, *clip, Some(fade.parse::<f32>().map_err(|_| format!("\"{}\" is not a number", fade))?)),
+		_ => return Err("Usage: animate <spawned model> <clip> [fade seconds]".to_string()),
+	};
+	let model = index.parse::<usize>().ok()
+		.and_then(|index| window.spawned.get_mut(index))
+		.map(|spawned| &mut spawned.model)
+		.ok_or_else(|| format!("There is no spawned model {}", index))?;
+
+	// Clips by name or by index
+	let Some(clip) = model.clips.iter().position(|candidate| candidate.name == clip).or_else(|| clip.parse::<usize>().ok().filter(|clip| *clip < model.clips.len())) else {
+		let names = model.clips.iter().map(|clip| clip.name.as_str()).collect::<Vec<_>>();
+		return Err(format!("\"{}\" has no clip \"{}\", its clips are: {}", model.path, clip, names.join(", ")));
+	};
+	match fade {
+		Some(duration) => model.player.cross_fade(clip, duration, true),
+		None => model.player.play(clip, true),
+	}
+	Ok(format!("Playing \"{}\" on \"{}\"", model.clips[clip].name, model.path))
+}
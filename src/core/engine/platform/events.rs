@@ -1,10 +1,20 @@
-use std::time::Instant;
+use std::{fs, time::Instant};
 
 use glfw::Context;
-use nalgebra::{Point3, Vector3};
+use nalgebra::{Matrix4, Point3, Vector3};
 
-use crate::core::{engine::{self, threed::{model::{ModelMatrix, ModelTransformData, Threed}, projection::{self, ProjectionData, ProjectionMatrix}, view::{ViewData, ViewMatrix}, ThreedSize, UseThreed}, ui::text::ext::TextComponent}, utils::{model::manager::ModelLoader, texture::manager::Texture}};
-use super::implementations::Window;
+use crate::{core::{engine::{self, material::manager::{Material, TextureSlot}, rendergraph::{manager::{Clear, RenderGraph}, resources::{TextureDesc, TextureSize, TexturePool}}, renderstate::manager::RenderState, script::{debugger::session::Debugger, error::ScriptLanguage, parser::LuaParserOptions}, shaders::permutation::ShaderFeatures, lighting::shadow::ShadowMaps, threed::{model::{ModelMatrix, ModelTransformData, Threed}, projection::{self, ProjectionData, ProjectionMatrix}, view::{ViewData, ViewMatrix}, ThreedSize, UseThreed}, ui::{console::{ConsoleInput, DeveloperConsole}, overlay::ErrorOverlay, text::ext::TextComponent}}, utils::model::manager::ModelLoader}, log, utils::log::manager::{LogLevel, Logger}};
+use super::{implementations::{Window, WindowShaders}, remote::RemoteConsole};
+
+/// What the passes of a frame draw with, the render graph hands it to each of them.
+struct Frame<'a> {
+	window: &'a mut Window,
+	cube: &'a mut ModelLoader,
+	material: &'a mut Material,
+	text: &'a mut TextComponent,
+	overlay: &'a mut ErrorOverlay,
+	console: &'a mut DeveloperConsole,
+}
 
 impl Window {
 	pub fn initialize_app(
@@ -25,26 +35,34 @@ impl Window {
 	}
 
 	pub fn use_threed_world(&mut self, data: UseThreed) {
-		let projection = ProjectionMatrix::new(ProjectionData {
+		let projection_data = ProjectionData {
 			fov: 80.0_f32.to_radians(),
 			aspect_ratio: (data.size.width as f32) / (data.size.height as f32),
 			distance: projection::Distance {
 				far: 1000.0,
 				near: 0.1,
 			}
-		});
+		};
 
+		let eye = Point3::new(0.0, 0.0, 5.0);
 		let view = ViewMatrix::new(ViewData {
-			eye: Point3::new(0.0, 0.0, 5.0),
+			eye,
 			target: Point3::default(),
 			up: Vector3::y(),
 		});
 
+		// Shadow cascades are fitted to the camera frustum before it's turned into a matrix
+		if data.shader_type == Threed::DEFAULT {
+			self.shadows.fit(&self.lights.lights, &projection_data, &view.matrix);
+		}
+		let projection = ProjectionMatrix::new(projection_data);
+
 		let model = ModelMatrix::new(data.model_transform);
 
 		if data.shader_type == Threed::DEFAULT {
-			self.shaders.default.set_uniform_matrix4fv("projection", &projection.matrix);
-			self.shaders.default.set_uniform_matrix4fv("view", &view.matrix);
+			self.shaders.camera.write(0, &projection.matrix);
+			self.shaders.camera.write(std::mem::size_of_val(&projection.matrix), &view.matrix);
+			self.lights.upload(eye.coords, &self.shadows);
 			self.shaders.default.set_uniform_matrix4fv("model", &model.matrix);
 		}
 	}
@@ -55,15 +73,18 @@ impl Window {
 	/// be called at any time, If you called `initialize_opengl()` function then you don't need
 	/// to call this function as the initialize function calls it after initializing opengl.
 	pub unsafe fn game_loop(&mut self) {
-		let mut lua_parser = engine::script::parser::LuaParser::setup();
+		let mut lua_parser = engine::script::parser::LuaParser::setup_with(LuaParserOptions {
+			sandbox: self.script_sandbox.clone(),
+			..Default::default()
+		});
 		let mut js_parser = engine::script::parser::JSParser::setup();
 
 		self.initialize_app(&mut lua_parser, &mut js_parser);
 
-		let mut texture = Texture::new("examples/models/textures/CarrotTexture.png", true);
+		let mut material = Material::new("carrot");
+		material.set_texture(TextureSlot::Albedo, "examples/models/textures/CarrotTexture.png", true);
 
 		let mut cube = ModelLoader::new("examples/models/cube.obj", true);
-		texture.init();
 		cube.load();
 
 		let font_scale = 16.0;
@@ -71,8 +92,24 @@ impl Window {
 		text.set_text("excuse me what is a kilogram :eagle: 1234");
 		text.initialize();
 
+		let mut overlay = ErrorOverlay::new("resources/fonts/default.ttf", 14.0);
+		overlay.initialize();
+
+		let mut console = DeveloperConsole::new("resources/fonts/default.ttf", 14.0);
+		console.initialize();
+		let mut remote = self.console_port.and_then(RemoteConsole::bind);
+		let mut debugger = self.debug_port.and_then(Debugger::bind);
+
+		// Textures of the render graph, reused from frame to frame
+		let mut render_targets = TexturePool::new();
+		let mut graph_logger = Logger::new("debug/rendergraph.log");
+
 		let mut last_frame_time = Instant::now();
 		let mut fps: f32 = 0.0;
+
+		// Game time, runs at `timescale` times real time
+		let mut time: f32 = 0.0;
+		let mut previous_time = self.glfw.get_time();
 		
 		while !self.should_close() {
 			let (width, height) = self.window.get_framebuffer_size();
@@ -80,11 +117,41 @@ impl Window {
 	
 			// * Handle glfw events
 			self.glfw.poll_events();
-			self.handle_events();
+			for input in self.handle_events(&mut console) {
+				match input {
+					ConsoleInput::Submit(line) => {
+						match self.run_console_line(&mut console.language, &line, &mut lua_parser, &mut js_parser) {
+							Ok(output) => console.print(&output),
+							Err(error) => console.print(&error),
+						}
+					}
+					ConsoleInput::Complete(path) => {
+						let candidates = self.complete_console_path(console.language, &path, &lua_parser, &js_parser);
+						console.complete(&path, &candidates);
+					}
+				}
+			}
 
-			self.shaders.default.use_program();
+			if let Some(remote) = remote.as_mut() {
+				for request in remote.poll() {
+					let language = remote.sessions.entry(request.client).or_insert(ScriptLanguage::Lua);
+					let output = match self.run_console_line(language, &request.line, &mut lua_parser, &mut js_parser) {
+						Ok(output) => output,
+						Err(error) => error,
+					};
+					request.respond(&output);
+				}
+			}
 
-			let time = self.glfw.get_time() as f32;
+			// Shaders edited on disk are recompiled, broken edits keep the last good program
+			for result in self.shaders.all().into_iter().map(|shader| shader.hot_reload()) {
+				if let Err(err) = result {
+					console.print(&err.to_string());
+				}
+			}
+			let current_time = self.glfw.get_time();
+			time += ((current_time - previous_time) * self.timescale as f64) as f32;
+			previous_time = current_time;
 			
 			// * Setup & use projection, model and view matrix
 			self.use_threed_world(UseThreed { 
@@ -98,27 +165,23 @@ impl Window {
 			});
 
 			// * Clear window color
+			if let Some(debugger) = debugger.as_mut() {
+				debugger.poll(&mut lua_parser, &mut js_parser);
+			}
 			lua_parser.load();
 			js_parser.load();
-			gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
-			texture.apply(3, "texture1", self.shaders.default.program_id);
-			cube.draw();
-
-			self.shaders.ui.use_program();
-
-			// Set up the projection matrix where (0, 0) is top-left and (width, height) is bottom-right
-			let projection_matrix = nalgebra::Matrix4::new_orthographic(
-				0.0, width as f32,    // Left, right
-				height as f32, 0.0,   // Bottom, top (flipped to place (0, 0) at top-left)
-				-1.0, 1.0             // Near, far
-			);
-			
-			
-			self.shaders.ui.set_uniform_matrix4fv("projection", &projection_matrix);
-
-			let binding = text.text.clone();
-			let chars = binding.chars();
+			let cube_model = ModelMatrix::new(ModelTransformData {
+				translation: Vector3::default(),
+				rotation: Vector3::new(time, time, 0.0),
+				scale: Vector3::new(1.0, 1.0, 1.0),
+			});
+			let spawned_models = self.spawned.iter()
+				.map(|spawned| ModelMatrix::new(ModelTransformData {
+					translation: spawned.translation,
+					rotation: Vector3::new(time, time, 0.0),
+					scale: Vector3::new(1.0, 1.0, 1.0),
+				}))
+				.collect::<Vec<_>>();
 
 			// 1. Calculate time since the last frame
 			let now = Instant::now();
@@ -131,47 +194,116 @@ impl Window {
 				fps = 1.0 / frame_time_seconds;
 			}
 
+			// 3. Pose the animated models for the frame, in game time
+			let animation_delta = frame_time_seconds * self.timescale;
+			cube.animate(animation_delta);
+			for spawned in self.spawned.iter_mut() {
+				spawned.model.animate(animation_delta);
+			}
+
+			if self.error_overlay {
+				overlay.update(lua_parser.errors.values().chain(js_parser.errors.values()));
+			}
 
-			let mut last_img_width = font_scale / 2.0;
-			let mut last_img_height = 0.0;
-			let space_width = font_scale / 1.5;  // Adjust this value for the space character
-
-			// Find the maximum height of all glyphs (to set a common baseline)
-			let max_height = text.glyphs_image.values()
-				.map(|img| img.dimensions.1 as f32)
-				.max_by(|a, b| a.partial_cmp(b).unwrap())
-				.unwrap_or(0.0);  // Default to 0.0 if no glyphs are available
-
-			// Loop through each character in the string
-			chars.for_each(|char| {
-				if let Some(img) = text.glyphs_image.clone().get_mut(&char) {
-					// Character found in glyphs_image
-					last_img_height = img.dimensions.1 as f32;
-					// Calculate the y offset to align all glyphs on the same baseline
-					let vertical_offset = max_height - img.dimensions.1 as f32;
-
-					// Adjust translation: apply only the vertical_offset without adding it to glyph height
-					let model = ModelMatrix::new(ModelTransformData {
-						translation: Vector3::new(last_img_width, last_img_height + vertical_offset + (font_scale / 2.0), 0.0),
-						rotation: Vector3::default(),
-						scale: Vector3::new(img.dimensions.0 as f32, img.dimensions.1 as f32, 1.0),
-					});
-
-					// Set the uniform matrix for the shader
-					self.shaders.ui.set_uniform_matrix4fv("model", &model.matrix);
-
-					// Apply the texture and draw the character
-					img.apply(0, "texture1", self.shaders.ui.program_id);
-					text.set_vertex();
-
-					// Increment the width based on the character's width and a small adjustment for spacing
-					last_img_width += img.dimensions.0 as f32 + font_scale * 0.1;  // Adjust horizontal spacing
-				} else {
-					// If character is not found (or is a space), increment by the space_width
-					last_img_width += space_width;  // Use appropriate space width
+			// * Declare the passes of the frame, the graph orders them and sets their targets and state
+			let mut graph = RenderGraph::new(width, height);
+			let backbuffer = graph.backbuffer();
+			let shadow_maps = graph.import("shadow maps", self.shadows.texture());
+			// The post-process chain tone maps an HDR copy of the scene into the window
+			let hdr = self.post_process.is_some().then(|| (
+				graph.create("hdr colour", TextureDesc::new(gl::RGBA16F, TextureSize::Window)),
+				graph.create("scene depth", TextureDesc::new(gl::DEPTH_COMPONENT24, TextureSize::Window)),
+			));
+
+			graph.pass("shadows").write(shadow_maps).state(RenderState::DEPTH_ONLY).execute(|frame: &mut Frame, _| {
+				let window = &mut *frame.window;
+				let spawned = &mut window.spawned;
+				window.shadows.render(&mut window.shaders.shadow, |shader| {
+					frame.cube.draw(shader, &cube_model.matrix);
+					for (spawned, model) in spawned.iter().zip(spawned_models.iter()) {
+						spawned.model.draw(shader, &model.matrix);
+					}
+				});
+			});
+
+			let scene = graph.pass("scene").read(shadow_maps).clear(Clear { color: Some([0.0, 0.0, 0.0, 1.0]), depth: true, stencil: None });
+			let scene = match hdr {
+				Some((color, depth)) => scene.write(color).depth(depth),
+				None => scene.write(backbuffer),
+			};
+			scene.execute(|frame: &mut Frame, _| {
+				// Meshes without materials of their own are drawn with the carrot
+				let window = &mut *frame.window;
+				draw_model(frame.cube, &cube_model.matrix, frame.material, &mut window.shaders, &window.shadows, frame.console);
+				for (spawned, model) in window.spawned.iter_mut().zip(spawned_models.iter()) {
+					draw_model(&mut spawned.model, &model.matrix, frame.material, &mut window.shaders, &window.shadows, frame.console);
 				}
 			});
 
+			if let Some((color, _)) = hdr {
+				graph.pass("post-process").read(color).write(backbuffer).state(RenderState::FULLSCREEN).execute(move |frame: &mut Frame, resources| {
+					let window = &mut *frame.window;
+					let (width, height) = resources.size();
+					if let Some(post_process) = window.post_process.as_mut() {
+						post_process.run(resources.texture(color), width, height, &mut window.shaders.post);
+					}
+				});
+			}
+
+			graph.pass("ui").write(backbuffer).state(RenderState::OVERLAY).execute(|frame: &mut Frame, resources| {
+				let window = &mut *frame.window;
+				let (width, height) = resources.size();
+
+				// Glyphs are always transparent around the character
+				let ui_program = window.shaders.ui.use_variant(ShaderFeatures::ALPHA_TEST);
+
+				// Set up the projection matrix where (0, 0) is top-left and (width, height) is bottom-right
+				let projection_matrix = nalgebra::Matrix4::new_orthographic(
+					0.0, width as f32,    // Left, right
+					height as f32, 0.0,   // Bottom, top (flipped to place (0, 0) at top-left)
+					-1.0, 1.0             // Near, far
+				);
+				window.shaders.ui.set_uniform_matrix4fv("projection", &projection_matrix);
+
+				frame.text.draw(ui_program);
+				if window.error_overlay {
+					frame.overlay.draw(ui_program);
+				}
+				frame.console.draw(ui_program, width);
+			});
+
+			// The graph borrows the window until it's dropped
+			let previous = std::mem::take(&mut self.render_graph);
+			let result = graph.execute(&mut render_targets, &mut Frame {
+				window: self,
+				cube: &mut cube,
+				material: &mut material,
+				text: &mut text,
+				overlay: &mut overlay,
+				console: &mut console,
+			});
+			let dump = match &result {
+				Ok(()) => graph.dump(),
+				Err(err) => err.to_string(),
+			};
+			let dot = (result.is_ok() && dump != previous).then(|| graph.dot());
+			drop(graph);
+
+			// The graph is logged whenever it changes, with a Graphviz copy next to the log
+			if dump != previous {
+				match dot {
+					Some(dot) => {
+						log!(graph_logger, LogLevel::Info, "{}", dump);
+						let _ = fs::write("debug/rendergraph.dot", dot);
+					}
+					None => {
+						log!(graph_logger, LogLevel::Error, "{}", dump);
+						console.print(&dump);
+					}
+				}
+			}
+			self.render_graph = dump;
+
 			// * Swap window's buffers :)
 			self.window.swap_buffers();
 		}
@@ -180,8 +312,18 @@ impl Window {
 	/// # Safety
 	///
 	/// This function should not be called by you, the programmer/coder/user. This is automatically called!
-	pub unsafe fn handle_events(&mut self) {
+	pub unsafe fn handle_events(&mut self, console: &mut DeveloperConsole) -> Vec<ConsoleInput> {
+		let mut inputs = Vec::new();
 		for (_, event) in glfw::flush_messages(&self.events) {
+			// An open console takes the keyboard, Escape closes it instead of the window
+			let console_was_open = console.open;
+			if let Some(input) = console.handle_event(&event) {
+				inputs.push(input);
+			}
+			if console_was_open || console.open {
+				continue;
+			}
+
 			#[allow(clippy::single_match)]
 			match event {
 				glfw::WindowEvent::Key(glfw::Key::Escape, _, glfw::Action::Release, _) => {
@@ -193,5 +335,30 @@ impl Window {
 				// }
 			}
 		}
+		inputs
 	}
+}
+
+/// Draws every mesh of `model` with its own material, `fallback` for the ones without. The
+/// material picks the program the matrices are set on.
+fn draw_model(model: &mut ModelLoader, matrix: &Matrix4<f32>, fallback: &mut Material, shaders: &mut WindowShaders, shadows: &ShadowMaps, console: &mut DeveloperConsole) {
+	let path = model.path.clone();
+	model.each_mesh(|mesh, material, skins| {
+		let material = material.unwrap_or(&mut *fallback);
+		for instance in mesh.instances.iter() {
+			let skin = instance.skin.and_then(|skin| skins.get(skin));
+			let shader = shaders.by_name(&material.shader.clone());
+			material.bind_variant(shader, ShaderFeatures::SKINNED.when(skin.is_some()));
+			shadows.apply(shader);
+			if let Some(skin) = skin {
+				skin.bind();
+			}
+			// A layout missing attributes the program reads is reported once per program
+			if let Err(err) = mesh.mesh.validate(shader) {
+				console.print(&format!("\"{}\" ({}): {}", path, mesh.data.name, err));
+			}
+			shader.set_uniform_matrix4fv("model", &(matrix * instance.transform));
+			mesh.mesh.draw();
+		}
+	});
 }
\ No newline at end of file
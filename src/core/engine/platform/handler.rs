@@ -1,6 +1,10 @@
-use crate::core::engine::shaders::manager::{Shader, ShaderSources};
+use std::collections::HashMap;
 
-use super::implementations::{Window, WindowProperties, WindowShaders};
+use nalgebra::{Matrix4, Vector3};
+
+use crate::{core::engine::{animation::skin::JOINTS_BINDING, lighting::{manager::{Light, Lights, LIGHTS_BINDING}, shadow::{ShadowMaps, ShadowSettings, SHADOWS_BINDING}}, postprocess::manager::{PostProcess, PostSettings, PostShaders}, renderstate::manager::RenderState, shaders::{manager::{Shader, ShaderSources}, uniform::UniformBuffer}}, utils::log::opengl};
+
+use super::implementations::{Window, WindowProperties, WindowShaders, CAMERA_BINDING};
 
 impl Window {
 	pub fn new(properties: WindowProperties, scripts: Vec<String>) -> Self {
@@ -10,6 +14,8 @@ impl Window {
 		glfw.window_hint(glfw::WindowHint::ContextVersion(4, 4));
 		glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
 		glfw.window_hint(glfw::WindowHint::DoubleBuffer(true));
+		// Debug contexts validate every call, release builds skip that
+		glfw.window_hint(glfw::WindowHint::OpenGlDebugContext(cfg!(debug_assertions)));
 
 		let (mut window, events) = glfw.clone()
 		.with_primary_monitor(|_, mut m| {
@@ -47,19 +53,49 @@ impl Window {
 		window.set_key_polling(true);
 		window.set_mouse_button_polling(true);
 		window.set_cursor_pos_polling(true);
+		window.set_char_polling(true);
 
-		let default_shader = Shader::new(ShaderSources {
+		let mut default_shader = Shader::new(ShaderSources {
 			vertex: String::from("resources/shaders/vertex.glsl"),
 			fragment: String::from("resources/shaders/fragment.glsl"),
+			..Default::default()
+		});
+		default_shader.bind_uniform_block("Camera", CAMERA_BINDING);
+		default_shader.bind_uniform_block("Lights", LIGHTS_BINDING);
+		default_shader.bind_uniform_block("Shadows", SHADOWS_BINDING);
+		default_shader.bind_uniform_block("Joints", JOINTS_BINDING);
+
+		// The post-process chain tone maps the scene, so it's written out in HDR
+		let post_process = properties.window_options.post_processing.then(|| PostProcess::new(PostSettings::default()));
+		if post_process.is_some() {
+			default_shader.defines.push((String::from("HDR_OUTPUT"), String::new()));
+		}
+
+		let mut shadow_shader = Shader::new(ShaderSources {
+			vertex: String::from("resources/shaders/shadow/vertex.glsl"),
+			fragment: String::from("resources/shaders/shadow/fragment.glsl"),
+			..Default::default()
 		});
+		shadow_shader.bind_uniform_block("Joints", JOINTS_BINDING);
+
+		// A warm sun from the top right and a cool fill light from the left
+		let mut lights = Lights::new();
+		lights.add(Light::directional(Vector3::new(-0.5, -1.0, -0.6), Vector3::new(1.0, 0.95, 0.85), 3.0).with_shadow(ShadowSettings::default()));
+		lights.add(Light::point(Vector3::new(-3.0, 1.0, 2.0), Vector3::new(0.6, 0.7, 1.0), 15.0, 20.0));
+
+		let error_overlay = properties.window_options.error_overlay;
+		let console_port = properties.window_options.console_port;
+		let debug_port = properties.window_options.debug_port;
+		let script_sandbox = properties.window_options.script_sandbox.clone();
 
 		let ui_shader = Shader::new(ShaderSources {
 			vertex: String::from("resources/shaders/ui/text/vertex.glsl"),
 			fragment: String::from("resources/shaders/ui/text/fragment.glsl"),
+			..Default::default()
 		});
 
 
-		Window {
+		let mut platform = Window {
 			glfw,
 			window,
 			events,
@@ -67,9 +103,26 @@ impl Window {
 			scripts,
 			shaders: WindowShaders {
 				default: default_shader,
-				ui: ui_shader
-			}
-		}
+				ui: ui_shader,
+				shadow: shadow_shader,
+				post: PostShaders::new(),
+				camera: UniformBuffer::new(CAMERA_BINDING, 2 * std::mem::size_of::<Matrix4<f32>>()),
+			},
+			lights,
+			shadows: ShadowMaps::new(),
+			post_process,
+			render_graph: String::new(),
+			error_overlay,
+			console_port,
+			debug_port,
+			script_sandbox,
+
+			commands: HashMap::new(),
+			timescale: 1.0,
+			spawned: Vec::new(),
+		};
+		platform.register_default_commands();
+		platform
 	}
 
 	pub fn should_close(&mut self) -> bool {
@@ -77,21 +130,21 @@ impl Window {
 	}
 
 	pub fn enable_gl_flags(&mut self) {
-		unsafe {
-			gl::Enable(gl::DEPTH_TEST);
-			gl::Enable(gl::DEBUG_OUTPUT);
-			
-			gl::Enable(gl::BLEND);
-			gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-		}
+		opengl::install_debug_callback(false);
+		// Passes and draws apply their own state from here on
+		RenderState::DEFAULT.apply();
 	} 
 
 	pub fn initialize_opengl(&mut self) {
 		self.glfw.make_context_current(Some(&self.window));
 		gl::load_with(|s| self.glfw.get_proc_address_raw(s));
 
-		self.shaders.default.setup();
-		self.shaders.ui.setup();
+		// There is no previous program to fall back to yet.
+		for shader in self.shaders.all() {
+			if let Err(err) = shader.setup() {
+				panic!("{}", err);
+			}
+		}
 
 		self.enable_gl_flags();
 	}
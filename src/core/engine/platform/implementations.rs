@@ -1,7 +1,9 @@
 use std::collections;
 
 use glfw::{self, GlfwReceiver, WindowEvent};
-use crate::core::engine::shaders::manager::Shader;
+use nalgebra::Vector3;
+use crate::core::{engine::{lighting::{manager::Lights, shadow::ShadowMaps}, postprocess::manager::{PostProcess, PostShaders}, script::parser::LuaSandbox, shaders::{manager::Shader, uniform::UniformBuffer}}, utils::model::manager::ModelLoader};
+use super::commands::RegisteredCommand;
 
 #[derive(Clone)]
 pub struct Size {
@@ -25,13 +27,30 @@ pub struct DisplayOptions {
 pub struct WindowOptions {
 	pub title: String,
 	pub size: Size,
+	/// Draw failing scripts on screen, on by default in debug builds.
+	pub error_overlay: bool,
+	/// Localhost port the remote console listens on, `None` disables it. Off by default, clients
+	/// have to send the token written to `remote::TOKEN_FILE` first.
+	pub console_port: Option<u16>,
+	/// Localhost port the script debugger (Debug Adapter Protocol) listens on, `None` disables it.
+	/// Off by default, clients have to send the token written to `session::TOKEN_FILE` first.
+	pub debug_port: Option<u16>,
+	/// Draw the scene into an HDR target and run the post-process chain over it, on by default.
+	pub post_processing: bool,
+	/// Runs the Lua scripts in a sandbox with these limits, `None` trusts them.
+	pub script_sandbox: Option<LuaSandbox>,
 }
 
 impl Default for WindowOptions {
 	fn default() -> Self {
 		WindowOptions {
 			title: String::new(),
-			size: Size::from(800, 400)
+			size: Size::from(800, 400),
+			error_overlay: cfg!(debug_assertions),
+			console_port: None,
+			debug_port: None,
+			post_processing: true,
+			script_sandbox: None,
 		}
 	}
 }
@@ -41,9 +60,36 @@ pub struct WindowProperties {
 	pub display_options: Option<DisplayOptions>
 }
 
+/// Binding point of the `Camera` uniform block, `mat4 projection` followed by `mat4 view`.
+pub const CAMERA_BINDING: u32 = 0;
+
 pub struct WindowShaders {
 	pub default: Shader,
 	pub ui: Shader,
+	/// Depth only pass of the shadow maps.
+	pub shadow: Shader,
+	/// Passes of the post-process chain.
+	pub post: PostShaders,
+	/// Buffer of the `Camera` block the 3D shaders share.
+	pub camera: UniformBuffer,
+}
+
+impl WindowShaders {
+	/// Every shader, to set up or reload them all.
+	pub fn all(&mut self) -> Vec<&mut Shader> {
+		let mut shaders = vec![&mut self.default, &mut self.ui, &mut self.shadow];
+		shaders.extend(self.post.all());
+		shaders
+	}
+
+	/// The shader a `Material::shader` names, the default one for unknown names.
+	pub fn by_name(&mut self, name: &str) -> &mut Shader {
+		match name {
+			"ui" => &mut self.ui,
+			"shadow" => &mut self.shadow,
+			_ => &mut self.default,
+		}
+	}
 }
 
 pub struct UIStates {
@@ -51,11 +97,34 @@ pub struct UIStates {
 	pub string: collections::HashMap<String, String>,
 }
 
+/// A model placed into the world with the `spawn` console command.
+pub struct SpawnedModel {
+	pub model: ModelLoader,
+	pub translation: Vector3<f32>,
+}
+
 pub struct Window {
+	pub scripts: Vec<String>,
+	pub shaders: WindowShaders,
+	pub lights: Lights,
+	pub shadows: ShadowMaps,
+	/// Chain the scene goes through, `None` draws it straight to the window.
+	pub post_process: Option<PostProcess>,
+	/// Dump of the last frame's render graph, or why it couldn't run.
+	pub render_graph: String,
+	pub error_overlay: bool,
+	pub console_port: Option<u16>,
+	pub debug_port: Option<u16>,
+	pub script_sandbox: Option<LuaSandbox>,
+
+	/// Engine commands of the developer console, by name.
+	pub commands: collections::HashMap<String, RegisteredCommand>,
+	/// How fast game time passes compared to real time.
+	pub timescale: f32,
+	pub spawned: Vec<SpawnedModel>,
+
+	// Fields drop in order, the GL objects above are deleted before the context goes away
 	pub glfw: glfw::Glfw,
 	pub window: glfw::PWindow,
 	pub events: GlfwReceiver<(f64, WindowEvent)>,
-
-	pub scripts: Vec<String>,
-	pub shaders: WindowShaders
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
 pub mod handler;
 pub mod events;
-pub mod implementations;
\ No newline at end of file
+pub mod implementations;
+pub mod commands;
+pub mod remote;
\ No newline at end of file
@@ -0,0 +1,143 @@
+use std::{
+	collections::{hash_map::RandomState, HashMap},
+	fs,
+	hash::BuildHasher,
+	io::{BufRead, BufReader, Write},
+	net::{TcpListener, TcpStream},
+	sync::{mpsc::{self, Receiver, Sender}, Arc},
+	thread,
+	time::SystemTime,
+};
+
+use crate::{core::engine::script::error::ScriptLanguage, log, utils::log::manager::{LogLevel, Logger}};
+
+/// Port the remote console listens on when nothing else is configured.
+pub const DEFAULT_CONSOLE_PORT: u16 = 7878;
+/// File the token of the running remote console is written to.
+pub const TOKEN_FILE: &str = "debug/console.token";
+
+/// A line sent by a remote console client, answered with `respond`.
+pub struct RemoteRequest {
+	pub client: usize,
+	pub line: String,
+	reply: Sender<String>,
+}
+
+impl RemoteRequest {
+	pub fn respond(self, text: &str) {
+		// The client may already be gone, there is nobody left to tell.
+		let _ = self.reply.send(text.to_string());
+	}
+}
+
+/// Accepts developer console connections on localhost so tools can drive a running game.
+/// A client first sends the token of the run (see `TOKEN_FILE`), then every line it sends
+/// is evaluated like a line typed into the in-game console, the result is sent back
+/// followed by a `"> "` prompt.
+pub struct RemoteConsole {
+	requests: Receiver<RemoteRequest>,
+	/// Language every connected client currently evaluates in.
+	pub sessions: HashMap<usize, ScriptLanguage>,
+}
+
+impl RemoteConsole {
+	pub fn bind(port: u16) -> Option<Self> {
+		let mut logger = Logger::new("debug/console.log");
+		let listener = match TcpListener::bind(("127.0.0.1", port)) {
+			Ok(listener) => listener,
+			Err(err) => {
+				log!(logger, LogLevel::Error, "Failed to start the remote console on port \"{}\": {}", port, err);
+				return None;
+			}
+		};
+
+		// Any local process (or a web page, through the browser) can reach the port, only
+		// clients that can read the token get to run code
+		let token = Arc::new(generate_token());
+		if let Err(err) = fs::write(TOKEN_FILE, token.as_str()) {
+			log!(logger, LogLevel::Warning, "Failed to write the remote console token to \"{}\": {}", TOKEN_FILE, err);
+		}
+		log!(logger, LogLevel::Info, "Remote console listening on \"127.0.0.1:{}\" with the token \"{}\"", port, token);
+
+		let (sender, requests) = mpsc::channel();
+		thread::spawn(move || {
+			for (client, stream) in listener.incoming().enumerate() {
+				let Ok(stream) = stream else {
+					continue;
+				};
+				let sender = sender.clone();
+				let token = token.clone();
+				thread::spawn(move || serve_client(client, stream, &token, sender));
+			}
+		});
+
+		Some(RemoteConsole {
+			requests,
+			sessions: HashMap::new(),
+		})
+	}
+
+	/// Lines received since the last call, to be evaluated on the main thread.
+	pub fn poll(&self) -> Vec<RemoteRequest> {
+		self.requests.try_iter().collect()
+	}
+}
+
+/// A token nobody can guess, new every run.
+pub fn generate_token() -> String {
+	let state = RandomState::new();
+	(0..2).map(|part| format!("{:016x}", state.hash_one((part, SystemTime::now())))).collect()
+}
+
+/// Whether `line` is the request line of an HTTP request, like a browser sends.
+pub fn is_http_request(line: &str) -> bool {
+	let mut parts = line.split_whitespace();
+	let method = parts.next().unwrap_or_default();
+	let version = parts.nth(1).unwrap_or_default();
+	version.starts_with("HTTP/") || matches!(method, "GET" | "HEAD" | "POST" | "PUT" | "DELETE" | "CONNECT" | "OPTIONS" | "TRACE" | "PATCH")
+}
+
+fn serve_client(client: usize, stream: TcpStream, token: &str, requests: Sender<RemoteRequest>) {
+	let Ok(reader) = stream.try_clone() else {
+		return;
+	};
+	let mut writer = stream;
+	let mut lines = BufReader::new(reader).lines();
+
+	// The first line has to be the token, browsers are dropped without an answer
+	let Some(Ok(first)) = lines.next() else {
+		return;
+	};
+	if is_http_request(&first) {
+		return;
+	}
+	if first.trim() != token {
+		let _ = writer.write_all(b"invalid token\n");
+		return;
+	}
+
+	if writer.write_all(b"nengine console, `help` lists the engine commands\n> ").is_err() {
+		return;
+	}
+
+	for line in lines {
+		let Ok(line) = line else {
+			break;
+		};
+
+		let (reply, response) = mpsc::channel();
+		let request = RemoteRequest { client, line: line.trim().to_string(), reply };
+		// The game has shut down once the channel is closed.
+		if requests.send(request).is_err() {
+			break;
+		}
+		let Ok(text) = response.recv() else {
+			break;
+		};
+
+		let text = if text.is_empty() { text } else { format!("{}\n", text) };
+		if writer.write_all(format!("{}> ", text).as_bytes()).is_err() {
+			break;
+		}
+	}
+}
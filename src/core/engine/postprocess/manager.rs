@@ -0,0 +1,295 @@
+use nalgebra::Vector2;
+
+use crate::{core::engine::{objects::gpu::{Texture3D, VertexArray}, shaders::manager::{Shader, ShaderSources}}, log, utils::log::{manager::{LogLevel, Logger}, opengl::DebugGroup}};
+use super::target::RenderTarget;
+
+/// Entries of a colour grading lookup table per channel, the strip is `LUT_SIZE²` by `LUT_SIZE`.
+pub const LUT_SIZE: u32 = 16;
+
+/// Which passes of the chain run and how. Changes apply on the next frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostSettings {
+	/// Blurs the colour above `bloom_threshold` over its surroundings.
+	pub bloom: bool,
+	/// Brightness, after exposure, colours start to glow from.
+	pub bloom_threshold: f32,
+	/// Multiplies the blurred colour added back to the scene.
+	pub bloom_intensity: f32,
+	/// Horizontal and vertical blurs of the half size bloom image, more spread it further.
+	pub bloom_iterations: u32,
+	/// Maps the scene with the ACES curve, clamps it otherwise.
+	pub aces: bool,
+	/// Path of a `LUT_SIZE²` by `LUT_SIZE` strip of a colour grading lookup table, red along
+	/// each square, green down and blue from square to square. `None` skips grading.
+	pub color_grading: Option<String>,
+	/// Blend between the ungraded (0) and graded (1) colour.
+	pub color_grading_strength: f32,
+	/// How dark the corners get, 0 skips the pass.
+	pub vignette: f32,
+	/// Smooths the edges of the final image.
+	pub fxaa: bool,
+}
+
+impl Default for PostSettings {
+	fn default() -> Self {
+		PostSettings {
+			bloom: true,
+			bloom_threshold: 1.0,
+			bloom_intensity: 0.6,
+			bloom_iterations: 4,
+			aces: true,
+			color_grading: None,
+			color_grading_strength: 1.0,
+			vignette: 0.25,
+			fxaa: true,
+		}
+	}
+}
+
+/// A shader of every pass, all drawn over `resources/shaders/post/fullscreen.glsl`.
+pub struct PostShaders {
+	pub bloom_extract: Shader,
+	pub blur: Shader,
+	pub tonemap: Shader,
+	pub grade: Shader,
+	pub vignette: Shader,
+	pub fxaa: Shader,
+}
+
+impl PostShaders {
+	pub fn new() -> Self {
+		let pass = |name: &str| Shader::new(ShaderSources {
+			vertex: String::from("resources/shaders/post/fullscreen.glsl"),
+			fragment: format!("resources/shaders/post/{}.glsl", name),
+			..Default::default()
+		});
+		PostShaders {
+			bloom_extract: pass("bloom_extract"),
+			blur: pass("blur"),
+			tonemap: pass("tonemap"),
+			grade: pass("grade"),
+			vignette: pass("vignette"),
+			fxaa: pass("fxaa"),
+		}
+	}
+
+	pub fn all(&mut self) -> [&mut Shader; 6] {
+		[&mut self.bloom_extract, &mut self.blur, &mut self.tonemap, &mut self.grade, &mut self.vignette, &mut self.fxaa]
+	}
+}
+
+impl Default for PostShaders {
+	fn default() -> Self {
+		PostShaders::new()
+	}
+}
+
+/// Passes after tone mapping, each reads the output of the one before.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pass {
+	Tonemap,
+	Grade,
+	Vignette,
+	Fxaa,
+}
+
+/// Takes the HDR scene through bloom, tone mapping, colour grading, vignette and FXAA on its
+/// way to the window. Runs as a pass of the frame's `RenderGraph`, which sets the state.
+pub struct PostProcess {
+	pub settings: PostSettings,
+	/// Half size targets the bloom is blurred back and forth between.
+	bloom: [RenderTarget; 2],
+	/// Targets the passes after tone mapping alternate between, the last pass draws to the window.
+	ldr: [RenderTarget; 2],
+	/// Vertex array of the fullscreen triangle, which has no attributes.
+	vertex_array: Option<VertexArray>,
+	/// Size of the window the last pass draws to.
+	width: i32,
+	height: i32,
+	lut: Option<Texture3D>,
+	/// Strip `lut` was loaded from.
+	lut_path: Option<String>,
+	logger: Logger,
+}
+
+impl PostProcess {
+	pub fn new(settings: PostSettings) -> Self {
+		PostProcess {
+			settings,
+			bloom: [RenderTarget::new("bloom 0", &[gl::RGBA16F], None), RenderTarget::new("bloom 1", &[gl::RGBA16F], None)],
+			ldr: [RenderTarget::new("post-process 0", &[gl::RGBA8], None), RenderTarget::new("post-process 1", &[gl::RGBA8], None)],
+			vertex_array: None,
+			width: 0,
+			height: 0,
+			lut: None,
+			lut_path: None,
+			logger: Logger::new("debug/postprocess.log"),
+		}
+	}
+
+	/// Runs the passes over the `scene` texture and draws the result to the `width` by
+	/// `height` window, whose framebuffer is left bound.
+	pub fn run(&mut self, scene: u32, width: i32, height: i32, shaders: &mut PostShaders) {
+		if (self.width, self.height) != (width, height) {
+			log!(self.logger, LogLevel::Info, "Resizing the render targets to {}x{}", width, height);
+			self.width = width;
+			self.height = height;
+		}
+		for target in self.bloom.iter_mut() {
+			target.resize(width / 2, height / 2);
+		}
+		for target in self.ldr.iter_mut() {
+			target.resize(width, height);
+		}
+		self.vertex_array.get_or_insert_with(|| {
+			let vertex_array = VertexArray::new();
+			vertex_array.label("fullscreen triangle");
+			vertex_array
+		}).bind();
+		self.load_lut();
+
+		let bloom = self.settings.bloom && self.settings.bloom_intensity > 0.0;
+		if bloom {
+			self.bloom(scene, shaders);
+		}
+
+		let mut passes = vec![Pass::Tonemap];
+		if self.lut.is_some() {
+			passes.push(Pass::Grade);
+		}
+		if self.settings.vignette > 0.0 {
+			passes.push(Pass::Vignette);
+		}
+		if self.settings.fxaa {
+			passes.push(Pass::Fxaa);
+		}
+
+		let mut input = scene;
+		for (index, pass) in passes.iter().enumerate() {
+			let output = (index + 1 < passes.len()).then_some(&self.ldr[index % 2]);
+			let _group = DebugGroup::new(&format!("{:?}", pass));
+			match pass {
+				Pass::Tonemap => {
+					let bloom_texture = if bloom { self.bloom[0].color(0) } else { input };
+					let (intensity, aces) = (if bloom { self.settings.bloom_intensity } else { 0.0 }, self.settings.aces);
+					self.draw(&mut shaders.tonemap, output, &[("image", input), ("bloom", bloom_texture)], |shader| {
+						shader.set_uniform("bloom_intensity", intensity);
+						shader.set_uniform("use_aces", aces);
+					});
+				}
+				Pass::Grade => {
+					let (strength, lut) = (self.settings.color_grading_strength, self.lut.as_ref().map_or(0, Texture3D::id));
+					self.draw(&mut shaders.grade, output, &[("image", input), ("lut", lut)], |shader| {
+						shader.set_uniform("strength", strength);
+					});
+				}
+				Pass::Vignette => {
+					let strength = self.settings.vignette;
+					self.draw(&mut shaders.vignette, output, &[("image", input)], |shader| {
+						shader.set_uniform("strength", strength);
+					});
+				}
+				Pass::Fxaa => self.draw(&mut shaders.fxaa, output, &[("image", input)], |_| {}),
+			}
+			if let Some(output) = output {
+				input = output.color(0);
+			}
+		}
+
+		VertexArray::unbind();
+	}
+
+	/// Extracts the bright colour into `bloom[0]` and blurs it there.
+	fn bloom(&self, scene: u32, shaders: &mut PostShaders) {
+		let _group = DebugGroup::new("Bloom");
+		let threshold = self.settings.bloom_threshold;
+		self.draw(&mut shaders.bloom_extract, Some(&self.bloom[0]), &[("image", scene)], |shader| {
+			shader.set_uniform("threshold", threshold);
+		});
+
+		let directions = [(0, 1, Vector2::new(1.0, 0.0)), (1, 0, Vector2::new(0.0, 1.0))];
+		for _ in 0..self.settings.bloom_iterations {
+			for (from, to, direction) in directions {
+				self.draw(&mut shaders.blur, Some(&self.bloom[to]), &[("image", self.bloom[from].color(0))], |shader| {
+					shader.set_uniform("direction", direction);
+				});
+			}
+		}
+	}
+
+	/// Draws the fullscreen triangle with `shader` into `target`, the window without one.
+	/// `textures` are bound to the units in order and their samplers set, `uniforms` sets the rest.
+	fn draw(&self, shader: &mut Shader, target: Option<&RenderTarget>, textures: &[(&str, u32)], uniforms: impl FnOnce(&mut Shader)) {
+		unsafe {
+			match target {
+				Some(target) => target.bind(),
+				None => {
+					gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+					gl::Viewport(0, 0, self.width, self.height);
+				}
+			}
+		}
+
+		shader.use_program();
+		for (unit, (sampler, texture)) in textures.iter().enumerate() {
+			unsafe { gl::BindTextureUnit(unit as u32, *texture) };
+			shader.set_uniform(sampler, unit as i32);
+		}
+		uniforms(shader);
+
+		unsafe { gl::DrawArrays(gl::TRIANGLES, 0, 3) };
+	}
+
+	/// Loads the lookup table of `settings.color_grading` when it changed into a 3D texture.
+	/// A strip that can't be read or has the wrong size is logged and grading is skipped.
+	fn load_lut(&mut self) {
+		if self.lut_path == self.settings.color_grading {
+			return;
+		}
+		self.lut_path = self.settings.color_grading.clone();
+		self.lut = None;
+
+		let Some(path) = self.lut_path.clone() else {
+			return;
+		};
+		let strip = match image::open(&path) {
+			Ok(strip) => strip.to_rgba8(),
+			Err(err) => {
+				log!(self.logger, LogLevel::Error, "Could not read the colour grading table \"{}\": {}", path, err);
+				return;
+			}
+		};
+		if strip.dimensions() != (LUT_SIZE * LUT_SIZE, LUT_SIZE) {
+			log!(self.logger, LogLevel::Error, "Colour grading table \"{}\" is {}x{}, it should be {}x{}", path, strip.width(), strip.height(), LUT_SIZE * LUT_SIZE, LUT_SIZE);
+			return;
+		}
+
+		// Every square of the strip is a slice of the table along blue
+		let mut texels = Vec::with_capacity((LUT_SIZE * LUT_SIZE * LUT_SIZE * 4) as usize);
+		for blue in 0..LUT_SIZE {
+			for green in 0..LUT_SIZE {
+				for red in 0..LUT_SIZE {
+					texels.extend_from_slice(&strip.get_pixel(blue * LUT_SIZE + red, green).0);
+				}
+			}
+		}
+
+		let size = LUT_SIZE as i32;
+		let lut = Texture3D::new(gl::TEXTURE_3D, gl::RGBA8, size, size, size, 1);
+		lut.label(&path);
+		lut.upload(size, size, size, gl::RGBA, gl::UNSIGNED_BYTE, &texels);
+		lut.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+		lut.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+		for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+			lut.parameter(wrap, gl::CLAMP_TO_EDGE);
+		}
+		self.lut = Some(lut);
+		log!(self.logger, LogLevel::Info, "Loaded the colour grading table \"{}\"", path);
+	}
+}
+
+impl Default for PostProcess {
+	fn default() -> Self {
+		PostProcess::new(PostSettings::default())
+	}
+}
\ No newline at end of file
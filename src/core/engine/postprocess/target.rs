@@ -0,0 +1,94 @@
+use crate::core::engine::objects::gpu::{Framebuffer, Texture2D};
+
+/// A framebuffer with colour attachments of the given formats and an optional depth texture.
+/// The textures are created by the first `resize`, so a target can be made before OpenGL is
+/// loaded, and recreated whenever the size changes.
+pub struct RenderTarget {
+	/// `None` before the first `resize`.
+	pub framebuffer: Option<Framebuffer>,
+	/// Textures of the colour attachments, in attachment order.
+	pub colors: Vec<Texture2D>,
+	pub depth: Option<Texture2D>,
+	pub width: i32,
+	pub height: i32,
+	/// Prefix of the labels of the GL objects.
+	name: String,
+	formats: Vec<u32>,
+	depth_format: Option<u32>,
+}
+
+impl RenderTarget {
+	/// A target named `name` in GL debug messages, with a colour attachment for each of
+	/// `formats` (`gl::RGBA16F`, `gl::RGBA8`...) and a depth attachment of `depth_format`.
+	pub fn new(name: &str, formats: &[u32], depth_format: Option<u32>) -> Self {
+		RenderTarget {
+			framebuffer: None,
+			colors: Vec::new(),
+			depth: None,
+			width: 0,
+			height: 0,
+			name: name.to_string(),
+			formats: formats.to_vec(),
+			depth_format,
+		}
+	}
+
+	/// Makes the attachments `width` by `height`, returns whether they were (re)created.
+	/// Their content is undefined afterwards.
+	pub fn resize(&mut self, width: i32, height: i32) -> bool {
+		let (width, height) = (width.max(1), height.max(1));
+		if self.framebuffer.is_some() && self.width == width && self.height == height {
+			return false;
+		}
+		self.width = width;
+		self.height = height;
+
+		// The old attachments are deleted as they're replaced
+		let framebuffer = Framebuffer::new();
+		framebuffer.label(&self.name);
+
+		let mut attachments = Vec::new();
+		self.colors.clear();
+		for (index, format) in self.formats.iter().enumerate() {
+			let texture = Texture2D::new(*format, width, height, 1);
+			texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+			texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+			// Taps past the edge of the screen repeat the edge, not the other side
+			texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+			texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+
+			texture.label(&format!("{} colour {}", self.name, index));
+
+			let attachment = gl::COLOR_ATTACHMENT0 + index as u32;
+			framebuffer.attach(attachment, texture.id());
+			attachments.push(attachment);
+			self.colors.push(texture);
+		}
+		framebuffer.draw_buffers(&attachments);
+
+		self.depth = self.depth_format.map(|format| {
+			let depth = Texture2D::new(format, width, height, 1);
+			depth.label(&format!("{} depth", self.name));
+			framebuffer.attach(gl::DEPTH_ATTACHMENT, depth.id());
+			depth
+		});
+
+		let status = framebuffer.status();
+		assert!(status == gl::FRAMEBUFFER_COMPLETE, "[P:FBO] Render target of {}x{} is incomplete (status 0x{:x})", width, height, status);
+		self.framebuffer = Some(framebuffer);
+		true
+	}
+
+	/// Draws into the target from now on, over all of it.
+	pub fn bind(&self) {
+		if let Some(framebuffer) = &self.framebuffer {
+			framebuffer.bind();
+			unsafe { gl::Viewport(0, 0, self.width, self.height) };
+		}
+	}
+
+	/// Texture of the colour attachment `index`.
+	pub fn color(&self, index: usize) -> u32 {
+		self.colors[index].id()
+	}
+}
\ No newline at end of file
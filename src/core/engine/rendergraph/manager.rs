@@ -0,0 +1,486 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{core::engine::renderstate::manager::RenderState, utils::log::opengl::DebugGroup};
+use super::resources::{TextureDesc, TexturePool};
+
+/// A texture of a `RenderGraph`, handed out by `create`, `import` and `backbuffer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceId(usize);
+
+#[derive(Debug, Clone, Copy)]
+enum ResourceKind {
+	/// Allocated from the `TexturePool` for the passes between its first and last use.
+	Transient(TextureDesc),
+	/// Owned outside of the graph (the shadow maps...), passes writing it bind their own framebuffer.
+	Imported(u32),
+	/// The window's framebuffer.
+	Backbuffer,
+}
+
+struct Resource {
+	name: String,
+	kind: ResourceKind,
+}
+
+/// What a pass clears its outputs to before it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Clear {
+	pub color: Option<[f32; 4]>,
+	pub depth: bool,
+	pub stencil: Option<i32>,
+}
+
+impl Clear {
+	/// Clears inside the pass' scissor, with depth and stencil writes unmasked for it.
+	fn apply(&self, state: &RenderState) {
+		let mut mask = 0;
+		unsafe {
+			if let Some([r, g, b, a]) = self.color {
+				gl::ClearColor(r, g, b, a);
+				mask |= gl::COLOR_BUFFER_BIT;
+			}
+			if self.depth {
+				mask |= gl::DEPTH_BUFFER_BIT;
+			}
+			if let Some(stencil) = self.stencil {
+				gl::ClearStencil(stencil);
+				gl::StencilMask(0xff);
+				mask |= gl::STENCIL_BUFFER_BIT;
+			}
+		}
+		if mask == 0 {
+			return;
+		}
+
+		RenderState { depth_write: state.depth_write || self.depth, ..*state }.apply();
+		unsafe { gl::Clear(mask) };
+		// The stencil mask was changed behind the cache's back
+		if self.stencil.is_some() {
+			RenderState::invalidate();
+		}
+		state.apply();
+	}
+}
+
+/// Textures and size a pass runs with.
+pub struct PassResources<'r> {
+	textures: &'r HashMap<ResourceId, u32>,
+	width: i32,
+	height: i32,
+}
+
+impl PassResources<'_> {
+	/// GL texture of `resource`, 0 for the backbuffer and resources of culled passes.
+	pub fn texture(&self, resource: ResourceId) -> u32 {
+		self.textures.get(&resource).copied().unwrap_or(0)
+	}
+
+	/// Size of the pass' outputs, the viewport the graph set.
+	pub fn size(&self) -> (i32, i32) {
+		(self.width, self.height)
+	}
+}
+
+type PassFunction<'a, C> = Box<dyn FnMut(&mut C, &PassResources) + 'a>;
+
+struct Pass<'a, C> {
+	name: String,
+	reads: Vec<ResourceId>,
+	/// Colour outputs in attachment order, or the backbuffer or imported resources.
+	writes: Vec<ResourceId>,
+	depth: Option<ResourceId>,
+	state: RenderState,
+	clear: Clear,
+	/// Kept even when nothing reads its outputs.
+	side_effect: bool,
+	run: Option<PassFunction<'a, C>>,
+}
+
+/// Declares the resources of a pass, returned by `RenderGraph::pass`.
+pub struct PassBuilder<'g, 'a, C> {
+	graph: &'g mut RenderGraph<'a, C>,
+	index: usize,
+}
+
+impl<'a, C> PassBuilder<'_, 'a, C> {
+	/// The pass samples `resource`, it runs after every pass writing it.
+	pub fn read(self, resource: ResourceId) -> Self {
+		self.graph.passes[self.index].reads.push(resource);
+		self
+	}
+
+	/// The pass draws into `resource`, as the next colour attachment for transient textures.
+	pub fn write(self, resource: ResourceId) -> Self {
+		self.graph.passes[self.index].writes.push(resource);
+		self
+	}
+
+	/// The pass tests against and writes the depth texture `resource`.
+	pub fn depth(self, resource: ResourceId) -> Self {
+		self.graph.passes[self.index].depth = Some(resource);
+		self
+	}
+
+	/// Fixed function state the pass starts with, `RenderState::DEFAULT` unless set.
+	pub fn state(self, state: RenderState) -> Self {
+		self.graph.passes[self.index].state = state;
+		self
+	}
+
+	/// Clears the outputs before the pass runs.
+	pub fn clear(self, clear: Clear) -> Self {
+		self.graph.passes[self.index].clear = clear;
+		self
+	}
+
+	/// Keeps the pass when nothing reads what it writes.
+	pub fn side_effect(self) -> Self {
+		self.graph.passes[self.index].side_effect = true;
+		self
+	}
+
+	/// What the pass draws, given the graph's context and the pass' textures.
+	pub fn execute(self, run: impl FnMut(&mut C, &PassResources) + 'a) {
+		self.graph.passes[self.index].run = Some(Box::new(run));
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderGraphError {
+	/// The passes depend on each other in a loop.
+	Cycle(Vec<String>),
+	/// A pass reads a resource it writes.
+	Feedback { pass: String, resource: String },
+	/// A pass writes the backbuffer and transient textures at once.
+	MixedTargets { pass: String },
+	/// The outputs of a pass don't have the same size.
+	SizeMismatch { pass: String },
+	/// A colour texture is bound as depth or the other way around.
+	WrongAttachment { pass: String, resource: String },
+}
+
+impl fmt::Display for RenderGraphError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RenderGraphError::Cycle(passes) => write!(f, "[R:CYCLE] Passes \"{}\" depend on each other", passes.join("\", \"")),
+			RenderGraphError::Feedback { pass, resource } => write!(f, "[R:FEEDBACK] Pass \"{}\" reads \"{}\" while writing it", pass, resource),
+			RenderGraphError::MixedTargets { pass } => write!(f, "[R:TARGETS] Pass \"{}\" writes the backbuffer and textures at once", pass),
+			RenderGraphError::SizeMismatch { pass } => write!(f, "[R:SIZE] Outputs of pass \"{}\" have different sizes", pass),
+			RenderGraphError::WrongAttachment { pass, resource } => write!(f, "[R:ATTACHMENT] Pass \"{}\" attaches \"{}\" as the wrong kind of output", pass, resource),
+		}
+	}
+}
+
+impl std::error::Error for RenderGraphError {}
+
+/// Passes of a frame and the textures between them. The graph orders the passes by what they
+/// read and write, culls the ones nothing needs, allocates the transient textures (sharing
+/// them between resources that are never alive at once) and sets the framebuffer, viewport and
+/// `RenderState` of every pass. Built again every frame, the textures live in a `TexturePool`.
+pub struct RenderGraph<'a, C> {
+	passes: Vec<Pass<'a, C>>,
+	resources: Vec<Resource>,
+	width: i32,
+	height: i32,
+
+	/// Passes in execution order, filled by `execute`.
+	order: Vec<usize>,
+	/// Textures of the resources for the frame, from the pool or imported.
+	textures: HashMap<ResourceId, u32>,
+	/// Pool texture of every transient resource and its first and last pass in `order`.
+	lifetimes: HashMap<ResourceId, (usize, usize, usize)>,
+}
+
+impl<'a, C> RenderGraph<'a, C> {
+	/// A graph drawing to a `width` by `height` window.
+	pub fn new(width: i32, height: i32) -> Self {
+		RenderGraph {
+			passes: Vec::new(),
+			resources: vec![Resource { name: String::from("backbuffer"), kind: ResourceKind::Backbuffer }],
+			width: width.max(1),
+			height: height.max(1),
+
+			order: Vec::new(),
+			textures: HashMap::new(),
+			lifetimes: HashMap::new(),
+		}
+	}
+
+	/// The window's framebuffer, passes writing it are never culled.
+	pub fn backbuffer(&self) -> ResourceId {
+		ResourceId(0)
+	}
+
+	/// A texture only alive while passes use it.
+	pub fn create(&mut self, name: &str, desc: TextureDesc) -> ResourceId {
+		self.add_resource(name, ResourceKind::Transient(desc))
+	}
+
+	/// A texture owned outside of the graph, for ordering the passes that write and read it.
+	pub fn import(&mut self, name: &str, texture: u32) -> ResourceId {
+		self.add_resource(name, ResourceKind::Imported(texture))
+	}
+
+	fn add_resource(&mut self, name: &str, kind: ResourceKind) -> ResourceId {
+		self.resources.push(Resource { name: name.to_string(), kind });
+		ResourceId(self.resources.len() - 1)
+	}
+
+	/// Adds a pass, declare what it reads and writes on the builder and finish with `execute`.
+	/// Passes writing the same resource run in the order they were added.
+	pub fn pass(&mut self, name: &str) -> PassBuilder<'_, 'a, C> {
+		self.passes.push(Pass {
+			name: name.to_string(),
+			reads: Vec::new(),
+			writes: Vec::new(),
+			depth: None,
+			state: RenderState::DEFAULT,
+			clear: Clear::default(),
+			side_effect: false,
+			run: None,
+		});
+		let index = self.passes.len() - 1;
+		PassBuilder { graph: self, index }
+	}
+
+	/// Orders and runs the passes with `context`. Nothing runs when the graph is invalid.
+	pub fn execute(&mut self, pool: &mut TexturePool, context: &mut C) -> Result<(), RenderGraphError> {
+		self.validate()?;
+		self.order = self.sort()?;
+		self.allocate(pool);
+
+		for position in 0..self.order.len() {
+			let index = self.order[position];
+			let group = DebugGroup::new(&self.passes[index].name);
+			let (width, height) = self.bind(index, pool);
+			let pass = &self.passes[index];
+			pass.state.apply();
+			pass.clear.apply(&pass.state);
+
+			let resources = PassResources { textures: &self.textures, width, height };
+			if let Some(run) = self.passes[index].run.as_mut() {
+				run(context, &resources);
+			}
+			drop(group);
+		}
+
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+			gl::Viewport(0, 0, self.width, self.height);
+		}
+		pool.collect();
+		Ok(())
+	}
+
+	fn validate(&self) -> Result<(), RenderGraphError> {
+		for pass in self.passes.iter() {
+			if let Some(resource) = pass.reads.iter().find(|resource| pass.writes.contains(resource) || pass.depth == Some(**resource)) {
+				return Err(RenderGraphError::Feedback { pass: pass.name.clone(), resource: self.resources[resource.0].name.clone() });
+			}
+
+			let backbuffer = pass.writes.contains(&self.backbuffer());
+			let mut sizes = Vec::new();
+			for resource in pass.writes.iter().chain(pass.depth.iter()) {
+				if let ResourceKind::Transient(desc) = self.resources[resource.0].kind {
+					if backbuffer {
+						return Err(RenderGraphError::MixedTargets { pass: pass.name.clone() });
+					}
+					if desc.is_depth() != (pass.depth == Some(*resource)) {
+						return Err(RenderGraphError::WrongAttachment { pass: pass.name.clone(), resource: self.resources[resource.0].name.clone() });
+					}
+					sizes.push(desc.size.resolve(self.width, self.height));
+				}
+			}
+			if sizes.windows(2).any(|pair| pair[0] != pair[1]) {
+				return Err(RenderGraphError::SizeMismatch { pass: pass.name.clone() });
+			}
+		}
+		Ok(())
+	}
+
+	fn outputs(&self, pass: usize) -> impl Iterator<Item = &ResourceId> {
+		self.passes[pass].writes.iter().chain(self.passes[pass].depth.iter())
+	}
+
+	/// Passes `pass` has to run after: the writers of what it reads and the writers of what
+	/// it writes that were added before it.
+	fn dependencies(&self, pass: usize) -> Vec<usize> {
+		let mut dependencies = Vec::new();
+		for (other, _) in self.passes.iter().enumerate().filter(|(other, _)| *other != pass) {
+			let writes = |resource: &ResourceId| self.outputs(other).any(|output| output == resource);
+			let reads = self.passes[pass].reads.iter().any(writes);
+			let earlier_writer = other < pass && self.outputs(pass).any(writes);
+			if reads || earlier_writer {
+				dependencies.push(other);
+			}
+		}
+		dependencies
+	}
+
+	/// The passes leading to the backbuffer or a side effect, dependencies first and in the
+	/// order they were added otherwise.
+	fn sort(&self) -> Result<Vec<usize>, RenderGraphError> {
+		let dependencies = (0..self.passes.len()).map(|pass| self.dependencies(pass)).collect::<Vec<_>>();
+
+		let mut needed = vec![false; self.passes.len()];
+		let mut stack = (0..self.passes.len())
+			.filter(|pass| self.passes[*pass].side_effect || self.passes[*pass].writes.contains(&self.backbuffer()))
+			.collect::<Vec<_>>();
+		while let Some(pass) = stack.pop() {
+			if !needed[pass] {
+				needed[pass] = true;
+				stack.extend(dependencies[pass].iter().copied());
+			}
+		}
+
+		let mut order = Vec::new();
+		let mut done = vec![false; self.passes.len()];
+		while order.len() < needed.iter().filter(|needed| **needed).count() {
+			let ready = (0..self.passes.len())
+				.find(|pass| needed[*pass] && !done[*pass] && dependencies[*pass].iter().all(|dependency| done[*dependency]));
+			match ready {
+				Some(pass) => {
+					done[pass] = true;
+					order.push(pass);
+				}
+				None => {
+					let stuck = (0..self.passes.len())
+						.filter(|pass| needed[*pass] && !done[*pass])
+						.map(|pass| self.passes[pass].name.clone())
+						.collect();
+					return Err(RenderGraphError::Cycle(stuck));
+				}
+			}
+		}
+		Ok(order)
+	}
+
+	/// Gives every transient resource a pool texture for the passes between its first and last
+	/// use, reusing the textures of resources that are no longer used.
+	fn allocate(&mut self, pool: &mut TexturePool) {
+		self.textures.clear();
+		self.lifetimes.clear();
+
+		let mut spans: HashMap<ResourceId, (usize, usize)> = HashMap::new();
+		for (position, pass) in self.order.iter().enumerate() {
+			let pass = &self.passes[*pass];
+			for resource in pass.reads.iter().chain(pass.writes.iter()).chain(pass.depth.iter()) {
+				match self.resources[resource.0].kind {
+					ResourceKind::Transient(_) => {
+						let span = spans.entry(*resource).or_insert((position, position));
+						span.1 = position;
+					}
+					ResourceKind::Imported(texture) => {
+						self.textures.insert(*resource, texture);
+					}
+					ResourceKind::Backbuffer => {}
+				}
+			}
+		}
+
+		let mut spans = spans.into_iter().collect::<Vec<_>>();
+		spans.sort_by_key(|(resource, (first, _))| (*first, resource.0));
+		let mut live: Vec<(usize, usize)> = Vec::new();
+		for (resource, (first, last)) in spans {
+			// Textures of resources whose last pass came before this one are free again
+			live.retain(|(_, end)| *end >= first);
+			let ResourceKind::Transient(desc) = self.resources[resource.0].kind else {
+				continue;
+			};
+			let (width, height) = desc.size.resolve(self.width, self.height);
+			let taken = live.iter().map(|(texture, _)| *texture).collect::<Vec<_>>();
+			let texture = pool.acquire(desc.format, width, height, &taken);
+			live.push((texture, last));
+			self.textures.insert(resource, pool.texture(texture));
+			self.lifetimes.insert(resource, (texture, first, last));
+		}
+	}
+
+	/// Binds the framebuffer and viewport of a pass and returns the viewport size. Passes
+	/// without transient or backbuffer outputs bind their own.
+	fn bind(&self, pass: usize, pool: &mut TexturePool) -> (i32, i32) {
+		let pass = &self.passes[pass];
+		if pass.writes.contains(&self.backbuffer()) {
+			unsafe {
+				gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+				gl::Viewport(0, 0, self.width, self.height);
+			}
+			return (self.width, self.height);
+		}
+
+		let transient = |resource: &ResourceId| match self.resources[resource.0].kind {
+			ResourceKind::Transient(desc) => Some(desc.size.resolve(self.width, self.height)),
+			_ => None,
+		};
+		let colors = pass.writes.iter().filter(|resource| transient(resource).is_some()).map(|resource| self.textures[resource]).collect::<Vec<_>>();
+		let depth = pass.depth.filter(|resource| transient(resource).is_some()).map(|resource| self.textures[&resource]);
+		let Some((width, height)) = pass.writes.iter().chain(pass.depth.iter()).find_map(transient) else {
+			return (self.width, self.height);
+		};
+
+		let framebuffer = pool.framebuffer(&colors, depth);
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+			gl::Viewport(0, 0, width, height);
+		}
+		(width, height)
+	}
+
+	/// The passes in the order they last ran, the ones culled and the texture every transient
+	/// resource got, for `debug/rendergraph.log`.
+	pub fn dump(&self) -> String {
+		let name = |resource: &ResourceId| self.resources[resource.0].name.clone();
+		let names = |resources: &[ResourceId]| resources.iter().map(name).collect::<Vec<_>>().join(", ");
+
+		let mut text = format!("Render graph of {}x{}, {} of {} passes run", self.width, self.height, self.order.len(), self.passes.len());
+		for (position, index) in self.order.iter().enumerate() {
+			let pass = &self.passes[*index];
+			text.push_str(&format!("\n  {}. {}", position + 1, pass.name));
+			if !pass.reads.is_empty() {
+				text.push_str(&format!(" reads [{}]", names(&pass.reads)));
+			}
+			if !pass.writes.is_empty() {
+				text.push_str(&format!(" writes [{}]", names(&pass.writes)));
+			}
+			if let Some(depth) = &pass.depth {
+				text.push_str(&format!(" depth [{}]", name(depth)));
+			}
+		}
+
+		let culled = (0..self.passes.len()).filter(|pass| !self.order.contains(pass)).map(|pass| self.passes[pass].name.clone()).collect::<Vec<_>>();
+		if !culled.is_empty() {
+			text.push_str(&format!("\n  culled: {}", culled.join(", ")));
+		}
+
+		let mut lifetimes = self.lifetimes.iter().collect::<Vec<_>>();
+		lifetimes.sort_by_key(|(resource, (texture, ..))| (*texture, resource.0));
+		for (resource, (texture, first, last)) in lifetimes {
+			let ResourceKind::Transient(desc) = self.resources[resource.0].kind else {
+				continue;
+			};
+			let (width, height) = desc.size.resolve(self.width, self.height);
+			text.push_str(&format!("\n  {} ({}x{}, format 0x{:x}) in texture #{}, passes {} to {}", name(resource), width, height, desc.format, texture, first + 1, last + 1));
+		}
+		text
+	}
+
+	/// The graph in Graphviz' dot language, passes as boxes and resources as ellipses.
+	pub fn dot(&self) -> String {
+		let mut text = String::from("digraph render_graph {\n");
+		for (index, resource) in self.resources.iter().enumerate() {
+			text.push_str(&format!("\tr{} [label=\"{}\" shape=ellipse];\n", index, resource.name));
+		}
+		for (index, pass) in self.passes.iter().enumerate() {
+			let style = if self.order.contains(&index) { "solid" } else { "dashed" };
+			text.push_str(&format!("\tp{} [label=\"{}\" shape=box style={}];\n", index, pass.name, style));
+			for resource in pass.reads.iter() {
+				text.push_str(&format!("\tr{} -> p{};\n", resource.0, index));
+			}
+			for resource in pass.writes.iter().chain(pass.depth.iter()) {
+				text.push_str(&format!("\tp{} -> r{};\n", index, resource.0));
+			}
+		}
+		text.push('}');
+		text
+	}
+}
\ No newline at end of file
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::core::engine::objects::gpu::{Framebuffer, Texture2D};
+
+/// How big a transient texture is, resolved against the window every frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureSize {
+	/// The size of the window.
+	Window,
+	/// The size of the window divided by the factor, rounded down.
+	Divided(u32),
+	Fixed(i32, i32),
+}
+
+impl TextureSize {
+	pub fn resolve(self, width: i32, height: i32) -> (i32, i32) {
+		let (width, height) = match self {
+			TextureSize::Window => (width, height),
+			TextureSize::Divided(factor) => (width / factor.max(1) as i32, height / factor.max(1) as i32),
+			TextureSize::Fixed(width, height) => (width, height),
+		};
+		(width.max(1), height.max(1))
+	}
+}
+
+/// A texture a pass draws into and later passes read, only alive for the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureDesc {
+	/// Sized internal format, `gl::RGBA16F`, `gl::DEPTH_COMPONENT24`...
+	pub format: u32,
+	pub size: TextureSize,
+}
+
+impl TextureDesc {
+	pub fn new(format: u32, size: TextureSize) -> Self {
+		TextureDesc { format, size }
+	}
+
+	/// Whether the texture is attached as depth rather than colour.
+	pub fn is_depth(&self) -> bool {
+		matches!(self.format, gl::DEPTH_COMPONENT16 | gl::DEPTH_COMPONENT24 | gl::DEPTH_COMPONENT32 | gl::DEPTH_COMPONENT32F | gl::DEPTH24_STENCIL8 | gl::DEPTH32F_STENCIL8)
+	}
+}
+
+/// Frames a pooled texture may go unused before it's deleted, after a resize for example.
+const UNUSED_FRAMES: u64 = 3;
+
+struct PooledTexture {
+	texture: Texture2D,
+	format: u32,
+	width: i32,
+	height: i32,
+	last_used: u64,
+}
+
+/// The GL textures and framebuffers behind the transient resources, kept from frame to
+/// frame. Resources whose lifetimes don't overlap share a texture.
+pub struct TexturePool {
+	textures: Vec<PooledTexture>,
+	/// Framebuffers by their colour attachments followed by the depth one (0 without).
+	framebuffers: HashMap<Vec<u32>, Framebuffer>,
+	frame: u64,
+}
+
+impl TexturePool {
+	pub fn new() -> Self {
+		TexturePool {
+			textures: Vec::new(),
+			framebuffers: HashMap::new(),
+			frame: 0,
+		}
+	}
+
+	/// Index of a texture of `format` and size that isn't one of `live`, created when there is none.
+	pub(super) fn acquire(&mut self, format: u32, width: i32, height: i32, live: &[usize]) -> usize {
+		let found = self.textures.iter().enumerate().position(|(index, texture)| {
+			texture.format == format && texture.width == width && texture.height == height && !live.contains(&index)
+		});
+		let index = found.unwrap_or_else(|| {
+			let texture = Texture2D::new(format, width, height, 1);
+			texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+			texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+			texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+			texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+			texture.label(&format!("render graph texture {} ({}x{}, format 0x{:x})", self.textures.len(), width, height, format));
+			self.textures.push(PooledTexture { texture, format, width, height, last_used: 0 });
+			self.textures.len() - 1
+		});
+		self.textures[index].last_used = self.frame;
+		index
+	}
+
+	pub(super) fn texture(&self, index: usize) -> u32 {
+		self.textures[index].texture.id()
+	}
+
+	/// A framebuffer drawing into `colors` and `depth`, created the first time they're asked for.
+	pub(super) fn framebuffer(&mut self, colors: &[u32], depth: Option<u32>) -> u32 {
+		let mut key = colors.to_vec();
+		key.push(depth.unwrap_or(0));
+		let count = self.framebuffers.len();
+		self.framebuffers.entry(key).or_insert_with(|| {
+			let framebuffer = Framebuffer::new();
+			framebuffer.label(&format!("render graph framebuffer {}", count));
+			let mut attachments = Vec::new();
+			for (index, texture) in colors.iter().enumerate() {
+				let attachment = gl::COLOR_ATTACHMENT0 + index as u32;
+				framebuffer.attach(attachment, *texture);
+				attachments.push(attachment);
+			}
+			framebuffer.draw_buffers(&attachments);
+			if let Some(depth) = depth {
+				framebuffer.attach(gl::DEPTH_ATTACHMENT, depth);
+			}
+
+			let status = framebuffer.status();
+			assert!(status == gl::FRAMEBUFFER_COMPLETE, "[R:FBO] Framebuffer of the render graph is incomplete (status 0x{:x})", status);
+			framebuffer
+		}).id()
+	}
+
+	/// Ends a frame, deleting the textures that went unused for a few frames and the
+	/// framebuffers they were attached to.
+	pub(super) fn collect(&mut self) {
+		let frame = self.frame;
+		let (kept, stale): (Vec<_>, Vec<_>) = self.textures.drain(..).partition(|texture| frame - texture.last_used < UNUSED_FRAMES);
+		self.textures = kept;
+		self.frame += 1;
+		if stale.is_empty() {
+			return;
+		}
+
+		// Dropping the framebuffers and textures deletes them
+		let stale = stale.iter().map(|texture| texture.texture.id()).collect::<Vec<_>>();
+		self.framebuffers.retain(|attachments, _| !attachments.iter().any(|texture| stale.contains(texture)));
+	}
+}
+
+impl Default for TexturePool {
+	fn default() -> Self {
+		TexturePool::new()
+	}
+}
\ No newline at end of file
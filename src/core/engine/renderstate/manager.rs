@@ -0,0 +1,278 @@
+use std::cell::Cell;
+
+/// How a value is compared with the one already in the depth or stencil buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareFunc {
+	Never,
+	Less,
+	Equal,
+	LessEqual,
+	Greater,
+	NotEqual,
+	GreaterEqual,
+	Always,
+}
+
+impl CompareFunc {
+	fn gl(self) -> u32 {
+		match self {
+			CompareFunc::Never => gl::NEVER,
+			CompareFunc::Less => gl::LESS,
+			CompareFunc::Equal => gl::EQUAL,
+			CompareFunc::LessEqual => gl::LEQUAL,
+			CompareFunc::Greater => gl::GREATER,
+			CompareFunc::NotEqual => gl::NOTEQUAL,
+			CompareFunc::GreaterEqual => gl::GEQUAL,
+			CompareFunc::Always => gl::ALWAYS,
+		}
+	}
+}
+
+/// How drawn colour is combined with the colour already there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+	/// Replaces it.
+	Opaque,
+	/// Source alpha over one minus source alpha.
+	Alpha,
+	/// Colour already multiplied by its alpha over one minus source alpha.
+	Premultiplied,
+	/// Adds the colour scaled by its alpha, for glows and particles.
+	Additive,
+	/// Multiplies it.
+	Multiply,
+}
+
+impl BlendMode {
+	/// Source and destination factors, `None` without blending.
+	fn factors(self) -> Option<(u32, u32)> {
+		match self {
+			BlendMode::Opaque => None,
+			BlendMode::Alpha => Some((gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA)),
+			BlendMode::Premultiplied => Some((gl::ONE, gl::ONE_MINUS_SRC_ALPHA)),
+			BlendMode::Additive => Some((gl::SRC_ALPHA, gl::ONE)),
+			BlendMode::Multiply => Some((gl::DST_COLOR, gl::ZERO)),
+		}
+	}
+}
+
+/// Faces that aren't drawn, front faces wind counter-clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+	None,
+	Back,
+	Front,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonMode {
+	Fill,
+	/// Wireframe.
+	Line,
+	Point,
+}
+
+impl PolygonMode {
+	fn gl(self) -> u32 {
+		match self {
+			PolygonMode::Fill => gl::FILL,
+			PolygonMode::Line => gl::LINE,
+			PolygonMode::Point => gl::POINT,
+		}
+	}
+}
+
+/// What happens to the stencil value of a fragment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+	Keep,
+	Zero,
+	/// Writes `StencilState::reference`.
+	Replace,
+	Increment,
+	IncrementWrap,
+	Decrement,
+	DecrementWrap,
+	Invert,
+}
+
+impl StencilOp {
+	fn gl(self) -> u32 {
+		match self {
+			StencilOp::Keep => gl::KEEP,
+			StencilOp::Zero => gl::ZERO,
+			StencilOp::Replace => gl::REPLACE,
+			StencilOp::Increment => gl::INCR,
+			StencilOp::IncrementWrap => gl::INCR_WRAP,
+			StencilOp::Decrement => gl::DECR,
+			StencilOp::DecrementWrap => gl::DECR_WRAP,
+			StencilOp::Invert => gl::INVERT,
+		}
+	}
+}
+
+/// The stencil test, the same for front and back faces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+	pub func: CompareFunc,
+	pub reference: i32,
+	/// Bits compared.
+	pub read_mask: u32,
+	/// Bits written.
+	pub write_mask: u32,
+	/// When the stencil test fails.
+	pub fail: StencilOp,
+	/// When the stencil test passes but the depth test fails.
+	pub depth_fail: StencilOp,
+	/// When both pass.
+	pub pass: StencilOp,
+}
+
+impl Default for StencilState {
+	fn default() -> Self {
+		StencilState {
+			func: CompareFunc::Always,
+			reference: 0,
+			read_mask: 0xff,
+			write_mask: 0xff,
+			fail: StencilOp::Keep,
+			depth_fail: StencilOp::Keep,
+			pass: StencilOp::Keep,
+		}
+	}
+}
+
+/// Fixed function state a draw or a pass runs with. `apply` only issues the GL calls for what
+/// differs from the state applied before, so every draw can apply its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+	/// Depth test, `None` to draw over everything.
+	pub depth_test: Option<CompareFunc>,
+	pub depth_write: bool,
+	pub blend: BlendMode,
+	pub cull: CullMode,
+	pub polygon_mode: PolygonMode,
+	/// Slope scaled factor and constant units added to the depth of filled polygons.
+	pub polygon_offset: Option<(f32, f32)>,
+	/// Window rectangle (x, y, width, height from the bottom left) drawing and clearing is limited to.
+	pub scissor: Option<[i32; 4]>,
+	/// Stencil test, `None` to leave the stencil buffer alone.
+	pub stencil: Option<StencilState>,
+}
+
+thread_local! {
+	/// State last applied on this thread's GL context, `None` when unknown.
+	static CURRENT: Cell<Option<RenderState>> = const { Cell::new(None) };
+}
+
+impl RenderState {
+	/// Depth tested and written geometry, alpha blended.
+	pub const DEFAULT: RenderState = RenderState {
+		depth_test: Some(CompareFunc::Less),
+		depth_write: true,
+		blend: BlendMode::Alpha,
+		cull: CullMode::None,
+		polygon_mode: PolygonMode::Fill,
+		polygon_offset: None,
+		scissor: None,
+		stencil: None,
+	};
+	/// Opaque geometry that only writes depth, like the shadow casters.
+	pub const DEPTH_ONLY: RenderState = RenderState {
+		blend: BlendMode::Opaque,
+		..RenderState::DEFAULT
+	};
+	/// Fullscreen triangles covering every pixel.
+	pub const FULLSCREEN: RenderState = RenderState {
+		depth_test: None,
+		depth_write: false,
+		blend: BlendMode::Opaque,
+		..RenderState::DEFAULT
+	};
+	/// 2D drawn on top in draw order, like the UI.
+	pub const OVERLAY: RenderState = RenderState {
+		depth_test: None,
+		depth_write: false,
+		..RenderState::DEFAULT
+	};
+
+	/// State last applied, `DEFAULT` before the first `apply`.
+	pub fn current() -> RenderState {
+		CURRENT.with(|current| current.get()).unwrap_or(RenderState::DEFAULT)
+	}
+
+	/// Forgets the applied state, for after code that changed it with GL calls. The next
+	/// `apply` sets everything.
+	pub fn invalidate() {
+		CURRENT.with(|current| current.set(None));
+	}
+
+	/// Sets the state on the current GL context, skipping what's set already.
+	pub fn apply(&self) {
+		let previous = CURRENT.with(|current| current.replace(Some(*self)));
+		let toggle = |capability, enabled: bool| unsafe {
+			if enabled { gl::Enable(capability) } else { gl::Disable(capability) }
+		};
+
+		unsafe {
+			if previous.is_none_or(|previous| previous.depth_test != self.depth_test) {
+				toggle(gl::DEPTH_TEST, self.depth_test.is_some());
+				if let Some(func) = self.depth_test {
+					gl::DepthFunc(func.gl());
+				}
+			}
+			if previous.is_none_or(|previous| previous.depth_write != self.depth_write) {
+				gl::DepthMask(if self.depth_write { gl::TRUE } else { gl::FALSE });
+			}
+
+			if previous.is_none_or(|previous| previous.blend != self.blend) {
+				toggle(gl::BLEND, self.blend.factors().is_some());
+				if let Some((source, destination)) = self.blend.factors() {
+					gl::BlendFunc(source, destination);
+				}
+			}
+
+			if previous.is_none_or(|previous| previous.cull != self.cull) {
+				toggle(gl::CULL_FACE, self.cull != CullMode::None);
+				match self.cull {
+					CullMode::Back => gl::CullFace(gl::BACK),
+					CullMode::Front => gl::CullFace(gl::FRONT),
+					CullMode::None => {}
+				}
+			}
+
+			if previous.is_none_or(|previous| previous.polygon_mode != self.polygon_mode) {
+				gl::PolygonMode(gl::FRONT_AND_BACK, self.polygon_mode.gl());
+			}
+
+			if previous.is_none_or(|previous| previous.polygon_offset != self.polygon_offset) {
+				toggle(gl::POLYGON_OFFSET_FILL, self.polygon_offset.is_some());
+				if let Some((factor, units)) = self.polygon_offset {
+					gl::PolygonOffset(factor, units);
+				}
+			}
+
+			if previous.is_none_or(|previous| previous.scissor != self.scissor) {
+				toggle(gl::SCISSOR_TEST, self.scissor.is_some());
+				if let Some([x, y, width, height]) = self.scissor {
+					gl::Scissor(x, y, width, height);
+				}
+			}
+
+			if previous.is_none_or(|previous| previous.stencil != self.stencil) {
+				toggle(gl::STENCIL_TEST, self.stencil.is_some());
+				if let Some(stencil) = self.stencil {
+					gl::StencilFunc(stencil.func.gl(), stencil.reference, stencil.read_mask);
+					gl::StencilMask(stencil.write_mask);
+					gl::StencilOp(stencil.fail.gl(), stencil.depth_fail.gl(), stencil.pass.gl());
+				}
+			}
+		}
+	}
+}
+
+impl Default for RenderState {
+	fn default() -> Self {
+		RenderState::DEFAULT
+	}
+}
\ No newline at end of file
@@ -0,0 +1,694 @@
+use std::{collections::{hash_map::Entry, HashMap}, sync::{Arc, Mutex}, time::Instant};
+
+use rquickjs::{function::{Constructor, Func, Opt, Rest, This}, promise::PromiseState, Coerced, Ctx, Function, Object, Type, Value};
+
+use crate::{log, utils::log::manager::{LogLevel, Logger}};
+
+/// How deep nested objects are expanded before they are abbreviated to `[Object]`.
+const MAX_DEPTH: usize = 2;
+/// Array and collection items printed before the rest is summarized as `... n more items`.
+const MAX_ITEMS: usize = 100;
+/// Entries are kept on one line as long as the result stays below this width.
+const BREAK_LENGTH: usize = 72;
+
+/// State shared by the functions of the JS `console` object.
+pub struct Console {
+	logger: Logger,
+	timers: HashMap<String, Instant>,
+	counts: HashMap<String, u64>,
+	indent: usize,
+}
+
+impl Console {
+	pub fn new(log_file: &str) -> Self {
+		let mut logger = Logger::new(log_file);
+		// Scripts may legitimately print the same line every frame.
+		logger.set_deduplicate(false);
+
+		Console {
+			logger,
+			timers: HashMap::new(),
+			counts: HashMap::new(),
+			indent: 0,
+		}
+	}
+
+	fn print(&mut self, level: LogLevel, message: &str) {
+		let message = indent_lines(message, self.indent);
+		log!(self.logger, level, &message);
+	}
+
+	fn time(&mut self, label: String) {
+		let exists = match self.timers.entry(label.clone()) {
+			Entry::Occupied(_) => true,
+			Entry::Vacant(entry) => {
+				entry.insert(Instant::now());
+				false
+			}
+		};
+		if exists {
+			self.print(LogLevel::Warning, &format!("Label '{}' already exists for console.time()", label));
+		}
+	}
+
+	/// Prints the time elapsed since `console.time(label)`, stopping the timer when `stop` is set.
+	fn time_log(&mut self, label: String, stop: bool, extra: String) {
+		let started = if stop { self.timers.remove(&label) } else { self.timers.get(&label).copied() };
+		let Some(started) = started else {
+			let function = if stop { "console.timeEnd()" } else { "console.timeLog()" };
+			self.print(LogLevel::Warning, &format!("No such label '{}' for {}", label, function));
+			return;
+		};
+
+		let milliseconds = started.elapsed().as_secs_f64() * 1000.0;
+		let elapsed = if milliseconds >= 1000.0 {
+			format!("{:.3}s", milliseconds / 1000.0)
+		} else {
+			format!("{:.3}ms", milliseconds)
+		};
+
+		if extra.is_empty() {
+			self.print(LogLevel::Info, &format!("{}: {}", label, elapsed));
+		} else {
+			self.print(LogLevel::Info, &format!("{}: {} {}", label, elapsed, extra));
+		}
+	}
+
+	fn count(&mut self, label: String) {
+		let count = self.counts.entry(label.clone()).or_insert(0);
+		*count += 1;
+		let message = format!("{}: {}", label, count);
+		self.print(LogLevel::Info, &message);
+	}
+
+	fn count_reset(&mut self, label: String) {
+		match self.counts.get_mut(&label) {
+			Some(count) => *count = 0,
+			None => self.print(LogLevel::Warning, &format!("Count for '{}' does not exist", label)),
+		}
+	}
+}
+
+/// TypeScript type of the object `create_console` builds.
+pub const CONSOLE_TYPE: &str = "{
+	log(...values: unknown[]): void;
+	info(...values: unknown[]): void;
+	debug(...values: unknown[]): void;
+	warn(...values: unknown[]): void;
+	error(...values: unknown[]): void;
+	dir(value: unknown): void;
+	assert(condition: unknown, ...values: unknown[]): void;
+	trace(...values: unknown[]): void;
+	table(data: unknown, columns?: string[]): void;
+	group(...label: unknown[]): void;
+	groupCollapsed(...label: unknown[]): void;
+	groupEnd(): void;
+	time(label?: string): void;
+	timeLog(label?: string, ...values: unknown[]): void;
+	timeEnd(label?: string): void;
+	count(label?: string): void;
+	countReset(label?: string): void;
+}";
+
+/// Builds the JS `console` object, every function of it writes through `console`.
+pub fn create_console<'js>(ctx: &Ctx<'js>, console: Arc<Mutex<Console>>) -> rquickjs::Result<Object<'js>> {
+	let object = Object::new(ctx.clone())?;
+
+	for (name, level) in [
+		("log", LogLevel::Info),
+		("info", LogLevel::Info),
+		("debug", LogLevel::Info),
+		("warn", LogLevel::Warning),
+		("error", LogLevel::Error),
+	] {
+		let console = console.clone();
+		object.set(name, Func::new(move |ctx: Ctx<'js>, Rest(values): Rest<Value<'js>>| {
+			let message = format_values(&ctx, &values);
+			console.lock().unwrap().print(level.clone(), &message);
+		}))?;
+	}
+
+	let shared = console.clone();
+	object.set("dir", Func::new(move |ctx: Ctx<'js>, value: Value<'js>| {
+		let message = inspect(&ctx, &value);
+		shared.lock().unwrap().print(LogLevel::Info, &message);
+	}))?;
+
+	let shared = console.clone();
+	object.set("assert", Func::new(move |ctx: Ctx<'js>, condition: Coerced<bool>, Rest(values): Rest<Value<'js>>| {
+		if condition.0 {
+			return;
+		}
+		let message = if values.is_empty() {
+			"Assertion failed".to_string()
+		} else {
+			format!("Assertion failed: {}", format_values(&ctx, &values))
+		};
+		shared.lock().unwrap().print(LogLevel::Error, &message);
+	}))?;
+
+	let shared = console.clone();
+	object.set("trace", Func::new(move |ctx: Ctx<'js>, Rest(values): Rest<Value<'js>>| {
+		let mut message = format!("Trace: {}", format_values(&ctx, &values));
+		if let Some(stack) = current_stack(&ctx) {
+			message.push('\n');
+			message.push_str(stack.trim_end());
+		}
+		shared.lock().unwrap().print(LogLevel::Info, message.trim_end());
+	}))?;
+
+	let shared = console.clone();
+	object.set("table", Func::new(move |ctx: Ctx<'js>, data: Value<'js>, Opt(columns): Opt<Vec<String>>| {
+		let message = match data.as_object() {
+			Some(rows) if !data.is_function() => format_table(&ctx, rows, columns),
+			_ => format_values(&ctx, &[data]),
+		};
+		shared.lock().unwrap().print(LogLevel::Info, &message);
+	}))?;
+
+	for name in ["group", "groupCollapsed"] {
+		let console = console.clone();
+		object.set(name, Func::new(move |ctx: Ctx<'js>, Rest(values): Rest<Value<'js>>| {
+			let mut console = console.lock().unwrap();
+			if !values.is_empty() {
+				let message = format_values(&ctx, &values);
+				console.print(LogLevel::Info, &message);
+			}
+			console.indent += 2;
+		}))?;
+	}
+
+	let shared = console.clone();
+	object.set("groupEnd", Func::new(move || {
+		let mut console = shared.lock().unwrap();
+		console.indent = console.indent.saturating_sub(2);
+	}))?;
+
+	let shared = console.clone();
+	object.set("time", Func::new(move |Opt(label): Opt<String>| {
+		shared.lock().unwrap().time(label.unwrap_or_else(default_label));
+	}))?;
+
+	let shared = console.clone();
+	object.set("timeEnd", Func::new(move |Opt(label): Opt<String>| {
+		shared.lock().unwrap().time_log(label.unwrap_or_else(default_label), true, String::new());
+	}))?;
+
+	let shared = console.clone();
+	object.set("timeLog", Func::new(move |ctx: Ctx<'js>, Opt(label): Opt<String>, Rest(values): Rest<Value<'js>>| {
+		let extra = format_values(&ctx, &values);
+		shared.lock().unwrap().time_log(label.unwrap_or_else(default_label), false, extra);
+	}))?;
+
+	let shared = console.clone();
+	object.set("count", Func::new(move |Opt(label): Opt<String>| {
+		shared.lock().unwrap().count(label.unwrap_or_else(default_label));
+	}))?;
+
+	object.set("countReset", Func::new(move |Opt(label): Opt<String>| {
+		console.lock().unwrap().count_reset(label.unwrap_or_else(default_label));
+	}))?;
+
+	Ok(object)
+}
+
+fn default_label() -> String {
+	"default".to_string()
+}
+
+/// Formats `console.log` arguments, a leading string may contain `%s`, `%d`, `%i`, `%f`, `%j`, `%o`, `%O` and `%c` specifiers.
+pub fn format_values<'js>(ctx: &Ctx<'js>, values: &[Value<'js>]) -> String {
+	let mut parts = Vec::new();
+	let mut rest = values;
+
+	if let Some(template) = values.first().and_then(|value| value.as_string()).and_then(|string| string.to_string().ok()) {
+		if template.contains('%') {
+			let (text, used) = substitute(ctx, &template, &values[1..]);
+			parts.push(text);
+			rest = &values[1 + used..];
+		}
+	}
+
+	for value in rest {
+		match value.as_string() {
+			Some(string) => parts.push(string.to_string().unwrap_or_default()),
+			None => parts.push(inspect(ctx, value)),
+		}
+	}
+	parts.join(" ")
+}
+
+/// Replaces the format specifiers in `template`, returning the text and how many of `arguments` were consumed.
+fn substitute<'js>(ctx: &Ctx<'js>, template: &str, arguments: &[Value<'js>]) -> (String, usize) {
+	let mut text = String::new();
+	let mut used = 0;
+	let mut chars = template.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		if c != '%' {
+			text.push(c);
+			continue;
+		}
+
+		let Some(&specifier) = chars.peek() else {
+			text.push(c);
+			break;
+		};
+		if specifier == '%' {
+			chars.next();
+			text.push('%');
+			continue;
+		}
+		if !"sdifjoOc".contains(specifier) || used >= arguments.len() {
+			text.push(c);
+			continue;
+		}
+
+		chars.next();
+		let argument = &arguments[used];
+		used += 1;
+		match specifier {
+			's' => match argument.as_string() {
+				Some(string) => text.push_str(&string.to_string().unwrap_or_default()),
+				None => text.push_str(&inspect(ctx, argument)),
+			},
+			'd' | 'i' => match argument.as_big_int() {
+				Some(big_int) => text.push_str(&format!("{}n", big_int.clone().to_i64().unwrap_or_default())),
+				None => {
+					let number = to_number(argument);
+					text.push_str(&format_number(if specifier == 'i' { number.trunc() } else { number }));
+				}
+			},
+			'f' => text.push_str(&format_number(to_number(argument))),
+			'j' => text.push_str(&match ctx.json_stringify(argument.clone()) {
+				Ok(Some(json)) => json.to_string().unwrap_or_default(),
+				Ok(None) => "undefined".to_string(),
+				Err(_) => {
+					// Drop the pending TypeError so it does not surface on the next call.
+					let _ = ctx.catch();
+					"[Circular]".to_string()
+				}
+			}),
+			'o' | 'O' => text.push_str(&inspect(ctx, argument)),
+			// CSS styling has no meaning in a terminal, the argument is swallowed.
+			_ => {}
+		}
+	}
+
+	(text, used)
+}
+
+fn to_number(value: &Value) -> f64 {
+	if value.is_object() {
+		return f64::NAN;
+	}
+	value.get::<Coerced<f64>>().map(|number| number.0).unwrap_or(f64::NAN)
+}
+
+/// Formats a value the way it looks nested inside an object, strings are quoted.
+pub fn inspect<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> String {
+	Inspector { ctx: ctx.clone(), seen: Vec::new() }.format(value, 0)
+}
+
+struct Inspector<'js> {
+	ctx: Ctx<'js>,
+	/// Objects currently being formatted, used to detect cycles.
+	seen: Vec<Value<'js>>,
+}
+
+impl<'js> Inspector<'js> {
+	fn format(&mut self, value: &Value<'js>, depth: usize) -> String {
+		match value.type_of() {
+			Type::Uninitialized | Type::Undefined => "undefined".to_string(),
+			Type::Null => "null".to_string(),
+			Type::Bool => value.as_bool().unwrap_or_default().to_string(),
+			Type::Int => value.as_int().unwrap_or_default().to_string(),
+			Type::Float => format_number(value.as_float().unwrap_or_default()),
+			Type::String => quote(&value.as_string().and_then(|string| string.to_string().ok()).unwrap_or_default()),
+			Type::Symbol => {
+				let description = value.as_symbol()
+					.and_then(|symbol| symbol.description().ok())
+					.and_then(|description| description.as_string().and_then(|string| string.to_string().ok()))
+					.unwrap_or_default();
+				format!("Symbol({})", description)
+			}
+			Type::BigInt => format!("{}n", value.as_big_int().and_then(|big_int| big_int.clone().to_i64().ok()).unwrap_or_default()),
+			Type::Function | Type::Constructor => self.format_function(value),
+			Type::Exception => self.format_error(value),
+			Type::Array | Type::Object | Type::Promise => {
+				if self.seen.contains(value) {
+					return "[Circular]".to_string();
+				}
+
+				self.seen.push(value.clone());
+				let text = match value.type_of() {
+					Type::Array => self.format_array(value, depth),
+					Type::Promise => self.format_promise(value, depth),
+					_ => self.format_object(value, depth),
+				};
+				self.seen.pop();
+				text
+			}
+			Type::Module => "[Module]".to_string(),
+			Type::Unknown => "[Unknown]".to_string(),
+		}
+	}
+
+	fn format_function(&mut self, value: &Value<'js>) -> String {
+		let name = value.as_object()
+			.and_then(|object| object.get::<_, Option<String>>("name").ok().flatten())
+			.filter(|name| !name.is_empty());
+		match name {
+			Some(name) => format!("[Function: {}]", name),
+			None => "[Function (anonymous)]".to_string(),
+		}
+	}
+
+	fn format_error(&mut self, value: &Value<'js>) -> String {
+		let Some(object) = value.as_object() else {
+			return "[Error]".to_string();
+		};
+
+		let name = object.get::<_, Option<String>>("name").ok().flatten().unwrap_or_else(|| "Error".to_string());
+		let message = object.get::<_, Option<String>>("message").ok().flatten().unwrap_or_default();
+		let mut text = if message.is_empty() { name } else { format!("{}: {}", name, message) };
+
+		// QuickJS' `stack` only holds the frames, not the `name: message` header.
+		if let Some(stack) = object.get::<_, Option<String>>("stack").ok().flatten() {
+			if !stack.trim().is_empty() {
+				text.push('\n');
+				text.push_str(stack.trim_end());
+			}
+		}
+		text
+	}
+
+	fn format_array(&mut self, value: &Value<'js>, depth: usize) -> String {
+		let Some(array) = value.as_array() else {
+			return "[]".to_string();
+		};
+		if depth > MAX_DEPTH {
+			return "[Array]".to_string();
+		}
+
+		let mut entries = Vec::new();
+		for index in 0..array.len().min(MAX_ITEMS) {
+			let item = array.get::<Value>(index).unwrap_or_else(|_| Value::new_undefined(self.ctx.clone()));
+			entries.push(self.format(&item, depth + 1));
+		}
+		if array.len() > MAX_ITEMS {
+			entries.push(more_items(array.len() - MAX_ITEMS));
+		}
+
+		wrap_entries("", "[", "]", &entries)
+	}
+
+	fn format_promise(&mut self, value: &Value<'js>, depth: usize) -> String {
+		let Some(promise) = value.as_promise() else {
+			return "Promise {}".to_string();
+		};
+
+		let state = match promise.state() {
+			PromiseState::Pending => "<pending>".to_string(),
+			PromiseState::Resolved => match promise.result::<Value>() {
+				Some(Ok(result)) => self.format(&result, depth + 1),
+				_ => "<resolved>".to_string(),
+			},
+			PromiseState::Rejected => {
+				// Reading a rejected result rethrows it, which would leave a pending exception behind.
+				"<rejected>".to_string()
+			}
+		};
+		format!("Promise {{ {} }}", state)
+	}
+
+	fn format_object(&mut self, value: &Value<'js>, depth: usize) -> String {
+		let Some(object) = value.as_object() else {
+			return "{}".to_string();
+		};
+
+		let class = class_name(object);
+		match class.as_deref() {
+			Some("Date") => return call_method(object, "toISOString").unwrap_or_else(|| "Invalid Date".to_string()),
+			Some("RegExp") => return call_method(object, "toString").unwrap_or_else(|| "/(?:)/".to_string()),
+			Some(class @ ("Map" | "Set")) => return self.format_collection(object, class, depth),
+			_ => {}
+		}
+
+		let prefix = match class.as_deref() {
+			None => "[Object: null prototype] ".to_string(),
+			Some("Object") => String::new(),
+			Some(class) => format!("{} ", class),
+		};
+
+		if depth > MAX_DEPTH {
+			return match class.as_deref() {
+				Some("Object") | None => "[Object]".to_string(),
+				Some(class) => format!("[{}]", class),
+			};
+		}
+
+		let mut entries = Vec::new();
+		for key in object.keys::<String>().flatten() {
+			let item = match object.get::<_, Value>(key.as_str()) {
+				Ok(item) => item,
+				Err(_) => {
+					// A throwing getter, drop its exception and keep going.
+					let _ = self.ctx.catch();
+					Value::new_undefined(self.ctx.clone())
+				}
+			};
+			entries.push(format!("{}: {}", format_key(&key), self.format(&item, depth + 1)));
+		}
+
+		if entries.is_empty() {
+			return format!("{}{{}}", prefix);
+		}
+		wrap_entries(&prefix, "{", "}", &entries)
+	}
+
+	fn format_collection(&mut self, object: &Object<'js>, class: &str, depth: usize) -> String {
+		let size = object.get::<_, usize>("size").unwrap_or_default();
+		let prefix = format!("{}({}) ", class, size);
+		if depth > MAX_DEPTH {
+			return format!("[{}]", class);
+		}
+
+		// Both iterate in insertion order, `Array.from` turns them into `[key, value]` pairs or plain values.
+		let items = self.ctx.globals().get::<_, Object>("Array")
+			.and_then(|array| array.get::<_, Function>("from"))
+			.and_then(|from| from.call::<_, Vec<Value>>((object.clone(),)))
+			.unwrap_or_default();
+
+		let mut entries = Vec::new();
+		for item in items.iter().take(MAX_ITEMS) {
+			if class == "Map" {
+				let pair = item.as_array();
+				let key = pair.and_then(|pair| pair.get::<Value>(0).ok());
+				let value = pair.and_then(|pair| pair.get::<Value>(1).ok());
+				if let (Some(key), Some(value)) = (key, value) {
+					entries.push(format!("{} => {}", self.format(&key, depth + 1), self.format(&value, depth + 1)));
+				}
+			} else {
+				entries.push(self.format(item, depth + 1));
+			}
+		}
+		if items.len() > MAX_ITEMS {
+			entries.push(more_items(items.len() - MAX_ITEMS));
+		}
+
+		if entries.is_empty() {
+			return format!("{}{{}}", prefix);
+		}
+		wrap_entries(&prefix, "{", "}", &entries)
+	}
+}
+
+/// Name of the constructor `object` was made by, `None` for objects without a prototype.
+fn class_name(object: &Object) -> Option<String> {
+	object.get_prototype()?;
+	let constructor = object.get::<_, Option<Object>>("constructor").ok().flatten();
+	let name = constructor.and_then(|constructor| constructor.get::<_, Option<String>>("name").ok().flatten());
+	Some(name.filter(|name| !name.is_empty()).unwrap_or_else(|| "Object".to_string()))
+}
+
+fn call_method(object: &Object, name: &str) -> Option<String> {
+	let method = object.get::<_, Function>(name).ok()?;
+	match method.call::<_, String>((This(object.clone()),)) {
+		Ok(text) => Some(text),
+		Err(_) => {
+			let _ = object.ctx().catch();
+			None
+		}
+	}
+}
+
+/// The stack of the JS code currently calling into Rust.
+pub(super) fn current_stack(ctx: &Ctx) -> Option<String> {
+	let constructor = ctx.globals().get::<_, Constructor>("Error").ok()?;
+	let error = constructor.construct::<_, Object>(("",)).ok()?;
+	error.get::<_, Option<String>>("stack").ok().flatten()
+}
+
+/// Lays `entries` out on one line when they fit, one per line otherwise.
+fn wrap_entries(prefix: &str, open: &str, close: &str, entries: &[String]) -> String {
+	if entries.is_empty() {
+		return format!("{}{}{}", prefix, open, close);
+	}
+
+	let length = prefix.len() + entries.iter().map(|entry| entry.chars().count() + 2).sum::<usize>();
+	if length <= BREAK_LENGTH && !entries.iter().any(|entry| entry.contains('\n')) {
+		return format!("{}{} {} {}", prefix, open, entries.join(", "), close);
+	}
+
+	let body = entries.iter()
+		.map(|entry| indent_lines(entry, 2))
+		.collect::<Vec<_>>()
+		.join(",\n");
+	format!("{}{}\n{}\n{}", prefix, open, body, close)
+}
+
+fn more_items(count: usize) -> String {
+	format!("... {} more item{}", count, if count == 1 { "" } else { "s" })
+}
+
+fn indent_lines(text: &str, indent: usize) -> String {
+	if indent == 0 {
+		return text.to_string();
+	}
+	let padding = " ".repeat(indent);
+	text.lines()
+		.map(|line| format!("{}{}", padding, line))
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+/// Formats numbers the way JS prints them, `1` rather than `1.0`.
+fn format_number(number: f64) -> String {
+	if number.is_nan() {
+		"NaN".to_string()
+	} else if number.is_infinite() {
+		if number > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+	} else if number == 0.0 {
+		if number.is_sign_negative() { "-0" } else { "0" }.to_string()
+	} else if number.abs() >= 1e21 || number.abs() < 1e-6 {
+		// Rust leaves out the sign of positive exponents.
+		let text = format!("{:e}", number);
+		if text.contains("e-") { text } else { text.replace('e', "e+") }
+	} else if number.fract() == 0.0 {
+		format!("{:.0}", number)
+	} else {
+		number.to_string()
+	}
+}
+
+fn quote(text: &str) -> String {
+	let mut quoted = String::with_capacity(text.len() + 2);
+	quoted.push('\'');
+	for c in text.chars() {
+		match c {
+			'\'' => quoted.push_str("\\'"),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('\'');
+	quoted
+}
+
+/// Object keys are only quoted when they are not valid identifiers.
+fn format_key(key: &str) -> String {
+	let mut chars = key.chars();
+	let identifier = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+		&& chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+	if identifier { key.to_string() } else { quote(key) }
+}
+
+/// Renders `console.table` data as a box drawn table with an `(index)` column.
+fn format_table<'js>(ctx: &Ctx<'js>, rows: &Object<'js>, columns: Option<Vec<String>>) -> String {
+	const INDEX: &str = "(index)";
+	const VALUES: &str = "Values";
+
+	let mut header = vec![INDEX.to_string()];
+	let mut has_values = false;
+	let mut cells: Vec<HashMap<String, String>> = Vec::new();
+
+	let row_keys: Vec<String> = match rows.as_array() {
+		Some(array) => (0..array.len()).map(|index| index.to_string()).collect(),
+		None => rows.keys::<String>().flatten().collect(),
+	};
+
+	for key in row_keys {
+		let row = rows.get::<_, Value>(key.as_str()).unwrap_or_else(|_| Value::new_undefined(ctx.clone()));
+		let mut row_cells = HashMap::new();
+		row_cells.insert(INDEX.to_string(), key.clone());
+
+		match row.as_object().filter(|_| !row.is_function()) {
+			Some(object) => {
+				for column in object.keys::<String>().flatten() {
+					if columns.as_ref().is_some_and(|columns| !columns.contains(&column)) {
+						continue;
+					}
+					let value = object.get::<_, Value>(column.as_str()).unwrap_or_else(|_| Value::new_undefined(ctx.clone()));
+					if !header.contains(&column) {
+						header.push(column.clone());
+					}
+					row_cells.insert(column, inspect(ctx, &value));
+				}
+			}
+			None => {
+				has_values = true;
+				row_cells.insert(VALUES.to_string(), inspect(ctx, &row));
+			}
+		}
+
+		cells.push(row_cells);
+	}
+
+	if let Some(columns) = columns {
+		// Keep the order the columns were requested in.
+		header.retain(|column| column == INDEX);
+		header.extend(columns);
+	}
+	if has_values {
+		header.push(VALUES.to_string());
+	}
+
+	let widths: Vec<usize> = header.iter()
+		.map(|column| {
+			cells.iter()
+				.filter_map(|row| row.get(column))
+				.map(|cell| cell.chars().count())
+				.chain(std::iter::once(column.chars().count()))
+				.max()
+				.unwrap_or(0) + 2
+		})
+		.collect();
+
+	let line = |left: &str, middle: &str, right: &str| {
+		let segments = widths.iter().map(|width| "─".repeat(*width)).collect::<Vec<_>>();
+		format!("{}{}{}", left, segments.join(middle), right)
+	};
+	let row = |values: Vec<&str>| {
+		let segments = values.iter().zip(widths.iter())
+			.map(|(value, width)| center(value, *width))
+			.collect::<Vec<_>>();
+		format!("│{}│", segments.join("│"))
+	};
+
+	let mut table = vec![line("┌", "┬", "┐"), row(header.iter().map(String::as_str).collect()), line("├", "┼", "┤")];
+	for row_cells in cells.iter() {
+		table.push(row(header.iter().map(|column| row_cells.get(column).map_or("", String::as_str)).collect()));
+	}
+	table.push(line("└", "┴", "┘"));
+	table.join("\n")
+}
+
+fn center(text: &str, width: usize) -> String {
+	let padding = width.saturating_sub(text.chars().count());
+	let left = padding / 2;
+	format!("{}{}{}", " ".repeat(left), text, " ".repeat(padding - left))
+}
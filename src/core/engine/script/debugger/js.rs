@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex};
+
+use rquickjs::{function::Func, CatchResultExt, Ctx, Filter, Function, Value};
+
+use crate::core::{engine::script::{console::{current_stack, inspect}, error::{parse_js_stack, StackFrame}, parser::JSParser}, utils::json::value::Json};
+use super::session::{DebugSession, DebugVariable, PausedScript, JS_THREAD};
+
+/// Global the instrumented scripts call at the start of every line.
+const DEBUG_FUNCTION: &str = "__nengine_debug";
+
+/// Code the instrumentation puts in front of the first statement of `line`. The arrow
+/// function evaluates expressions where the statement is, `names` are the variables
+/// declared around it to show as locals.
+pub fn hook_call(file: &str, line: u32, names: &[String]) -> String {
+	format!("{}({}, {}, (__e) => eval(__e), {}); ", DEBUG_FUNCTION, Json::from(file), line, Json::from(names.join(",")))
+}
+
+impl JSParser {
+	/// Instruments the scripts that run from now on to stop at the breakpoints of `session`.
+	pub fn attach_debugger(&mut self, session: Arc<Mutex<DebugSession>>) {
+		let context = self.context.borrow();
+		context.with(|ctx| register(&ctx, session.clone())).unwrap();
+		drop(context);
+
+		self.debugger = Some(session);
+	}
+
+	/// Stops instrumenting the scripts that run from now on. Scripts that already ran
+	/// instrumented keep calling the hook, it's swapped for one that does nothing.
+	pub fn detach_debugger(&mut self) {
+		let context = self.context.borrow();
+		context.with(|ctx| ctx.eval::<(), _>(format!("globalThis.{} = () => {{}};", DEBUG_FUNCTION))).unwrap();
+		drop(context);
+
+		self.debugger = None;
+	}
+}
+
+fn register<'js>(ctx: &Ctx<'js>, session: Arc<Mutex<DebugSession>>) -> rquickjs::Result<()> {
+	ctx.globals().set(DEBUG_FUNCTION, Func::new(move |ctx: Ctx<'js>, file: String, line: u32, evaluator: Function<'js>, names: String| {
+		on_line(&ctx, &session, &file, line, evaluator, &names);
+	}))
+}
+
+fn on_line<'js>(ctx: &Ctx<'js>, session: &Mutex<DebugSession>, file: &str, line: u32, evaluator: Function<'js>, names: &str) {
+	// Expressions evaluated while paused run instrumented lines as well, they must not pause again.
+	let Ok(mut session) = session.try_lock() else {
+		return;
+	};
+
+	if let Some(reason) = session.should_pause(JS_THREAD, file, line, || current_frames(ctx, file).len()) {
+		let paused = JsPaused {
+			ctx: ctx.clone(),
+			evaluator,
+			names: names.split(',').filter(|name| !name.is_empty()).map(str::to_string).collect(),
+			frames: current_frames(ctx, file),
+		};
+		session.pause(reason, &paused);
+	}
+}
+
+/// The JS frames calling into Rust. Every script is evaluated under the same name, so
+/// they are all reported in `file`.
+fn current_frames(ctx: &Ctx, file: &str) -> Vec<StackFrame> {
+	current_stack(ctx)
+		.map(|stack| parse_js_stack(&stack, file))
+		.unwrap_or_default()
+		.into_iter()
+		// Native functions, the hook among them, have no line.
+		.filter(|frame| frame.line.is_some())
+		.collect()
+}
+
+struct JsPaused<'js> {
+	ctx: Ctx<'js>,
+	/// Evaluates code in the scope of the paused statement.
+	evaluator: Function<'js>,
+	names: Vec<String>,
+	frames: Vec<StackFrame>,
+}
+
+impl<'js> JsPaused<'js> {
+	/// Only the innermost frame can see its local variables, the others evaluate globally.
+	fn eval(&self, frame: usize, expression: &str) -> Result<Value<'js>, String> {
+		let result = if frame == 0 {
+			self.evaluator.call::<_, Value>((expression,))
+		} else {
+			self.ctx.eval::<Value, _>(expression)
+		};
+		// The stack would only show the evaluator.
+		result.catch(&self.ctx).map_err(|err| err.to_string().lines().next().unwrap_or_default().to_string())
+	}
+
+	/// Only objects keep `expression`, nothing else can be expanded.
+	fn variable(&self, name: String, value: &Value<'js>, expression: Option<String>) -> DebugVariable {
+		let text = match value.as_string() {
+			Some(string) => Json::from(string.to_string().unwrap_or_default()).to_string(),
+			None => inspect(&self.ctx, value).lines().map(str::trim).collect::<Vec<_>>().join(" "),
+		};
+
+		DebugVariable {
+			name,
+			value: text,
+			kind: value.type_name().to_string(),
+			expression: expression.filter(|_| value.is_object()),
+		}
+	}
+}
+
+impl PausedScript for JsPaused<'_> {
+	fn thread_id(&self) -> i64 {
+		JS_THREAD
+	}
+
+	fn frames(&self) -> &[StackFrame] {
+		&self.frames
+	}
+
+	fn locals(&self, frame: usize) -> Vec<DebugVariable> {
+		if frame != 0 {
+			return Vec::new();
+		}
+		// Variables still in their temporal dead zone throw and are left out.
+		self.names.iter()
+			.filter_map(|name| self.eval(frame, name).ok().map(|value| self.variable(name.clone(), &value, Some(name.clone()))))
+			.collect()
+	}
+
+	fn children(&self, frame: usize, expression: &str) -> Vec<DebugVariable> {
+		let Ok(value) = self.eval(frame, expression) else {
+			return Vec::new();
+		};
+		let Some(object) = value.as_object() else {
+			return Vec::new();
+		};
+
+		object.own_keys::<String>(Filter::new().string())
+			.flatten()
+			.filter_map(|key| {
+				let value = object.get::<_, Value>(key.as_str()).ok()?;
+				let path = format!("({})[{}]", expression, Json::from(key.as_str()));
+				Some(self.variable(key, &value, Some(path)))
+			})
+			.collect()
+	}
+
+	fn evaluate(&self, frame: usize, expression: &str) -> Result<DebugVariable, String> {
+		let value = self.eval(frame, expression)?;
+		Ok(self.variable(expression.to_string(), &value, Some(expression.to_string())))
+	}
+
+	fn globals_expression(&self) -> &'static str {
+		"globalThis"
+	}
+}
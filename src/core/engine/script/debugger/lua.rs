@@ -0,0 +1,266 @@
+use std::{
+	ffi::CStr,
+	os::raw::c_int,
+	sync::{Arc, Mutex},
+};
+
+use mlua::{ffi, prelude::*, Debug};
+
+use crate::core::engine::script::{error::StackFrame, parser::LuaParser, repl::format_lua_value};
+use super::session::{DebugSession, DebugVariable, PausedScript, LUA_THREAD};
+
+/// Registry key of `frame_locals`.
+const LOCALS_KEY: &str = "nengine.debug.locals";
+/// Name expressions typed into the debugger are compiled under.
+const EVALUATE_NAME: &str = "=debugger";
+
+/// `frame_locals(level)` returns a table of names and a table of values holding the locals
+/// and upvalues of the function running at `level`. The `debug` library may be sandboxed
+/// away, so this goes through the C API.
+unsafe extern "C-unwind" fn frame_locals(state: *mut ffi::lua_State) -> c_int {
+	let level = ffi::luaL_checkinteger(state, 1) as c_int;
+	ffi::lua_createtable(state, 0, 0);
+	let names = ffi::lua_gettop(state);
+	ffi::lua_createtable(state, 0, 0);
+	let values = ffi::lua_gettop(state);
+
+	let mut record: ffi::lua_Debug = std::mem::zeroed();
+	if ffi::lua_getstack(state, level, &mut record) == 0 {
+		return 2;
+	}
+
+	let mut count = 0;
+	let mut push = |name: *const std::os::raw::c_char, hidden: bool| {
+		if hidden {
+			ffi::lua_pop(state, 1);
+			return;
+		}
+		count += 1;
+		ffi::lua_rawseti(state, values, count);
+		ffi::lua_pushstring(state, name);
+		ffi::lua_rawseti(state, names, count);
+	};
+
+	let mut index = 1;
+	loop {
+		let name = ffi::lua_getlocal(state, &record, index);
+		if name.is_null() {
+			break;
+		}
+		// Temporaries like "(for state)" are not variables of the script.
+		push(name, CStr::from_ptr(name).to_bytes().starts_with(b"("));
+		index += 1;
+	}
+
+	ffi::lua_getinfo(state, c"f".as_ptr(), &mut record);
+	let function = ffi::lua_gettop(state);
+	let mut index = 1;
+	loop {
+		let name = ffi::lua_getupvalue(state, function, index);
+		if name.is_null() {
+			break;
+		}
+		push(name, CStr::from_ptr(name).to_bytes() == b"_ENV");
+		index += 1;
+	}
+	ffi::lua_pop(state, 1);
+
+	2
+}
+
+impl LuaParser {
+	/// Stops scripts at the breakpoints of `session` until `detach_debugger` is called.
+	pub fn attach_debugger(&mut self, session: Arc<Mutex<DebugSession>>) {
+		let lua = self.lua.get_mut();
+		let locals = unsafe { lua.create_c_function(frame_locals) }.unwrap();
+		lua.set_named_registry_value(LOCALS_KEY, locals).unwrap();
+
+		self.debugger = Some(session);
+		self.install_hook();
+	}
+
+	pub fn detach_debugger(&mut self) {
+		self.debugger = None;
+		self.install_hook();
+	}
+}
+
+/// Line hook of an attached debugger.
+pub fn on_line(lua: &Lua, debug: &Debug, session: &Mutex<DebugSession>) -> LuaResult<()> {
+	// Expressions evaluated while paused run lines as well, they must not pause again.
+	let Ok(mut session) = session.try_lock() else {
+		return Ok(());
+	};
+
+	let source = debug.source();
+	let Some(file) = source.source.as_deref().and_then(|source| source.strip_prefix('@')) else {
+		return Ok(());
+	};
+	let line = debug.curr_line();
+	if line < 1 {
+		return Ok(());
+	}
+
+	if let Some(reason) = session.should_pause(LUA_THREAD, file, line as u32, || stack_depth(lua)) {
+		let paused = LuaPaused::new(lua);
+		session.pause(reason, &paused);
+	}
+	Ok(())
+}
+
+/// Number of Lua (not C) functions on the stack.
+fn stack_depth(lua: &Lua) -> usize {
+	let mut depth = 0;
+	let mut level = 0;
+	while let Some(debug) = lua.inspect_stack(level) {
+		if debug.source().what != "C" {
+			depth += 1;
+		}
+		level += 1;
+	}
+	depth
+}
+
+struct LuaPaused<'lua> {
+	lua: &'lua Lua,
+	frames: Vec<StackFrame>,
+	/// Stack level of every frame, C functions are not shown.
+	levels: Vec<usize>,
+}
+
+impl<'lua> LuaPaused<'lua> {
+	fn new(lua: &'lua Lua) -> Self {
+		let mut frames = Vec::new();
+		let mut levels = Vec::new();
+
+		let mut level = 0;
+		while let Some(debug) = lua.inspect_stack(level) {
+			let source = debug.source();
+			if source.what != "C" {
+				let function = match (debug.names().name, source.what) {
+					(Some(name), _) => name.into_owned(),
+					(None, "main") => "<main chunk>".to_string(),
+					(None, _) => "<anonymous>".to_string(),
+				};
+				let file = match source.source.as_deref().and_then(|source| source.strip_prefix('@')) {
+					Some(file) => file.to_string(),
+					None => source.short_src.map(|short| short.into_owned()).unwrap_or_else(|| "?".to_string()),
+				};
+				let line = debug.curr_line();
+
+				frames.push(StackFrame { function, file, line: (line > 0).then_some(line as u32), column: None });
+				levels.push(level);
+			}
+			level += 1;
+		}
+
+		LuaPaused { lua, frames, levels }
+	}
+
+	fn frame_locals(&self, frame: usize) -> LuaResult<Vec<(String, LuaValue<'lua>)>> {
+		let Some(level) = self.levels.get(frame) else {
+			return Ok(Vec::new());
+		};
+		let function: LuaFunction = self.lua.named_registry_value(LOCALS_KEY)?;
+		// Calling it adds a level on top of the paused function.
+		let (names, values): (LuaTable, LuaTable) = function.call(*level + 1)?;
+
+		let mut locals = Vec::new();
+		for index in 1..=names.raw_len() {
+			let name: LuaString = names.raw_get(index)?;
+			locals.push((name.to_string_lossy().to_string(), values.raw_get(index)?));
+		}
+		Ok(locals)
+	}
+
+	/// Evaluates `expression` with the locals of `frame` in scope, as an expression first and as a statement otherwise.
+	fn eval(&self, frame: usize, expression: &str) -> LuaResult<LuaValue<'lua>> {
+		let lua = self.lua;
+		let environment = lua.create_table()?;
+		for (name, value) in self.frame_locals(frame)? {
+			environment.raw_set(name, value)?;
+		}
+		let meta = lua.create_table()?;
+		meta.set("__index", lua.globals())?;
+		meta.set("__newindex", lua.globals())?;
+		environment.set_metatable(Some(meta));
+
+		let chunk = match lua.load(format!("return {}", expression)).set_name(EVALUATE_NAME).set_environment(environment.clone()).into_function() {
+			Ok(chunk) => chunk,
+			Err(_) => lua.load(expression).set_name(EVALUATE_NAME).set_environment(environment).into_function()?,
+		};
+		let values: LuaMultiValue = chunk.call(())?;
+		Ok(values.into_iter().next().unwrap_or(LuaNil))
+	}
+}
+
+impl PausedScript for LuaPaused<'_> {
+	fn thread_id(&self) -> i64 {
+		LUA_THREAD
+	}
+
+	fn frames(&self) -> &[StackFrame] {
+		&self.frames
+	}
+
+	fn locals(&self, frame: usize) -> Vec<DebugVariable> {
+		self.frame_locals(frame)
+			.unwrap_or_default()
+			.into_iter()
+			.map(|(name, value)| variable(name.clone(), &value, Some(name)))
+			.collect()
+	}
+
+	fn children(&self, frame: usize, expression: &str) -> Vec<DebugVariable> {
+		let Ok(LuaValue::Table(table)) = self.eval(frame, expression) else {
+			return Vec::new();
+		};
+
+		let mut children = table.pairs::<LuaValue, LuaValue>()
+			.flatten()
+			.map(|(key, value)| {
+				let (name, path) = match &key {
+					LuaValue::String(name) => {
+						let name = name.to_string_lossy().to_string();
+						let path = format!("({})[{:?}]", expression, name);
+						(name, Some(path))
+					}
+					LuaValue::Integer(_) | LuaValue::Number(_) | LuaValue::Boolean(_) => {
+						let name = format_lua_value(&key, 0, &mut Vec::new());
+						let path = format!("({})[{}]", expression, name);
+						(format!("[{}]", name), Some(path))
+					}
+					key => (format!("[{}]", format_lua_value(key, 1, &mut Vec::new())), None),
+				};
+				let sort = match key {
+					LuaValue::Integer(index) => index,
+					_ => i64::MAX,
+				};
+				(sort, variable(name, &value, path))
+			})
+			.collect::<Vec<_>>();
+
+		// The array part in order, then fields by name.
+		children.sort_by(|(a, first), (b, second)| a.cmp(b).then_with(|| first.name.cmp(&second.name)));
+		children.into_iter().map(|(_, variable)| variable).collect()
+	}
+
+	fn evaluate(&self, frame: usize, expression: &str) -> Result<DebugVariable, String> {
+		let value = self.eval(frame, expression).map_err(|err| err.to_string())?;
+		Ok(variable(expression.to_string(), &value, Some(expression.to_string())))
+	}
+
+	fn globals_expression(&self) -> &'static str {
+		"_G"
+	}
+}
+
+/// Only tables keep `expression`, nothing else can be expanded.
+fn variable(name: String, value: &LuaValue, expression: Option<String>) -> DebugVariable {
+	DebugVariable {
+		name,
+		value: format_lua_value(value, 1, &mut Vec::new()),
+		kind: value.type_name().to_string(),
+		expression: expression.filter(|_| matches!(value, LuaValue::Table(_))),
+	}
+}
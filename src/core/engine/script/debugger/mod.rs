@@ -0,0 +1,4 @@
+pub mod protocol;
+pub mod session;
+pub mod lua;
+pub mod js;
\ No newline at end of file
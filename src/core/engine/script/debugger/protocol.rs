@@ -0,0 +1,116 @@
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{TcpListener, TcpStream},
+	sync::mpsc::{self, Receiver, Sender},
+	thread,
+	time::Duration,
+};
+
+use crate::core::{engine::platform::remote::is_http_request, utils::json::value::Json};
+
+/// Largest message body accepted, bigger lengths are refused before anything is allocated.
+const MAX_MESSAGE_LENGTH: usize = 4 * 1024 * 1024;
+/// How long a client has to send the token, only one client is served at a time.
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the listener thread hands to the session.
+pub enum Incoming {
+	/// A debugger connected, replies are written to this stream.
+	Connected(TcpStream),
+	Message(Json),
+	Disconnected,
+}
+
+/// Accepts one debug adapter client at a time on localhost and forwards its messages.
+/// A client first sends `token` on a line of its own, before the framed messages.
+pub fn listen(port: u16, token: String) -> io::Result<Receiver<Incoming>> {
+	let listener = TcpListener::bind(("127.0.0.1", port))?;
+	let (sender, incoming) = mpsc::channel();
+
+	thread::spawn(move || {
+		for stream in listener.incoming() {
+			let Ok(stream) = stream else {
+				continue;
+			};
+			let mut reader = BufReader::new(stream);
+			if !authenticate(&mut reader, &token) {
+				continue;
+			}
+			let Ok(writer) = reader.get_ref().try_clone() else {
+				continue;
+			};
+			if sender.send(Incoming::Connected(writer)).is_err() {
+				// The game has shut down.
+				break;
+			}
+			serve_client(reader, &sender);
+			if sender.send(Incoming::Disconnected).is_err() {
+				break;
+			}
+		}
+	});
+
+	Ok(incoming)
+}
+
+/// Whether the first line of the client is the token, browsers are dropped without an answer.
+fn authenticate(reader: &mut BufReader<TcpStream>, token: &str) -> bool {
+	if reader.get_ref().set_read_timeout(Some(TOKEN_TIMEOUT)).is_err() {
+		return false;
+	}
+	let mut first = String::new();
+	if reader.read_line(&mut first).is_err() || is_http_request(&first) {
+		return false;
+	}
+	if first.trim() != token {
+		let _ = reader.get_mut().write_all(b"invalid token\n");
+		return false;
+	}
+	reader.get_ref().set_read_timeout(None).is_ok()
+}
+
+fn serve_client(mut reader: BufReader<TcpStream>, sender: &Sender<Incoming>) {
+	while let Ok(Some(message)) = read_message(&mut reader) {
+		if sender.send(Incoming::Message(message)).is_err() {
+			break;
+		}
+	}
+}
+
+/// Reads one `Content-Length` framed message, `None` once the client hung up.
+fn read_message(reader: &mut BufReader<TcpStream>) -> io::Result<Option<Json>> {
+	let mut length = None;
+	loop {
+		let mut header = String::new();
+		if reader.read_line(&mut header)? == 0 {
+			return Ok(None);
+		}
+		let header = header.trim();
+		if header.is_empty() {
+			break;
+		}
+		if let Some((name, value)) = header.split_once(':') {
+			if name.trim().eq_ignore_ascii_case("Content-Length") {
+				length = value.trim().parse::<usize>().ok();
+			}
+		}
+	}
+
+	let Some(length) = length else {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "message without a Content-Length header"));
+	};
+	if length > MAX_MESSAGE_LENGTH {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("message of {} bytes is over the limit of {}", length, MAX_MESSAGE_LENGTH)));
+	}
+	let mut body = vec![0; length];
+	reader.read_exact(&mut body)?;
+
+	let text = String::from_utf8_lossy(&body);
+	Json::parse(&text).map(Some).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message(stream: &mut TcpStream, message: &Json) -> io::Result<()> {
+	let body = message.to_string();
+	write!(stream, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+	stream.flush()
+}
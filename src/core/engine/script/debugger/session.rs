@@ -0,0 +1,419 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	net::TcpStream,
+	path::{Path, PathBuf},
+	sync::{mpsc::Receiver, Arc, Mutex},
+};
+
+use crate::{core::{engine::{platform::remote::generate_token, script::{error::StackFrame, parser::{JSParser, LuaParser}}}, utils::json::value::Json}, log, utils::log::manager::{LogLevel, Logger}};
+use super::protocol::{self, Incoming};
+
+/// Port the debug adapter listens on when nothing else is configured.
+pub const DEFAULT_DEBUG_PORT: u16 = 4711;
+/// File the token of the running debug adapter is written to.
+pub const TOKEN_FILE: &str = "debug/debugger.token";
+/// Thread id Lua scripts are reported under.
+pub const LUA_THREAD: i64 = 1;
+/// Thread id JS and TypeScript scripts are reported under.
+pub const JS_THREAD: i64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepMode {
+	Run,
+	/// Stops at the next line of any script.
+	Pause,
+	/// Stops at the next line of the thread.
+	In(i64),
+	/// Stops at the next line of the thread at most this many frames deep.
+	Over(i64, usize),
+	/// Stops at the next line of the thread less than this many frames deep.
+	Out(i64, usize),
+}
+
+/// A variable as shown in the debugger.
+#[derive(Debug, Clone)]
+pub struct DebugVariable {
+	pub name: String,
+	pub value: String,
+	pub kind: String,
+	/// Expression evaluating to the value in its frame, set when it has children to expand.
+	pub expression: Option<String>,
+}
+
+/// A script stopped inside its line hook, inspected while the session waits for the
+/// debugger to resume it.
+pub trait PausedScript {
+	fn thread_id(&self) -> i64;
+	/// Frames of the script, the innermost first.
+	fn frames(&self) -> &[StackFrame];
+	fn locals(&self, frame: usize) -> Vec<DebugVariable>;
+	/// Fields of the value `expression` evaluates to in `frame`.
+	fn children(&self, frame: usize, expression: &str) -> Vec<DebugVariable>;
+	fn evaluate(&self, frame: usize, expression: &str) -> Result<DebugVariable, String>;
+	/// Expression expanded for the "Globals" scope.
+	fn globals_expression(&self) -> &'static str;
+}
+
+/// State of the Debug Adapter Protocol connection, shared by the script hooks.
+pub struct DebugSession {
+	incoming: Receiver<Incoming>,
+	client: Option<TcpStream>,
+	seq: i64,
+
+	/// Lines to stop at, by canonical script path.
+	breakpoints: HashMap<PathBuf, HashSet<u32>>,
+	step: StepMode,
+	/// Scripts whose breakpoints changed, Lua ones run again so top level code hits them too.
+	pub reload: Vec<PathBuf>,
+	/// Canonical paths of the script names the hooks report.
+	paths: HashMap<String, Option<PathBuf>>,
+	/// What the `variablesReference`s handed out while paused point at, a frame and the
+	/// expression to expand (the frame's locals for `None`). References are indices plus one.
+	handles: Vec<(usize, Option<String>)>,
+
+	logger: Logger,
+}
+
+impl DebugSession {
+	pub fn new(incoming: Receiver<Incoming>) -> Self {
+		DebugSession {
+			incoming,
+			client: None,
+			seq: 1,
+
+			breakpoints: HashMap::new(),
+			step: StepMode::Run,
+			reload: Vec::new(),
+			paths: HashMap::new(),
+			handles: Vec::new(),
+
+			logger: Logger::new("debug/debugger.log"),
+		}
+	}
+
+	pub fn is_attached(&self) -> bool {
+		self.client.is_some()
+	}
+
+	/// Called by the hooks on every line, returns the reason to stop for when the script
+	/// should pause. `depth` counts the frames of the thread and is only asked for while stepping.
+	pub fn should_pause(&mut self, thread: i64, file: &str, line: u32, depth: impl Fn() -> usize) -> Option<&'static str> {
+		self.client.as_ref()?;
+
+		let reason = match self.step {
+			StepMode::Run => None,
+			StepMode::Pause => Some("pause"),
+			StepMode::In(id) if id == thread => Some("step"),
+			StepMode::Over(id, frames) if id == thread && depth() <= frames => Some("step"),
+			StepMode::Out(id, frames) if id == thread && depth() < frames => Some("step"),
+			_ => None,
+		};
+		if reason.is_some() || self.breakpoints.is_empty() {
+			return reason;
+		}
+
+		let path = self.resolve(file)?;
+		self.breakpoints.get(&path).is_some_and(|lines| lines.contains(&line)).then_some("breakpoint")
+	}
+
+	fn resolve(&mut self, file: &str) -> Option<PathBuf> {
+		self.paths.entry(file.to_string()).or_insert_with(|| fs::canonicalize(file).ok()).clone()
+	}
+
+	/// Answers the requests that arrived while the scripts are running.
+	pub fn poll(&mut self) {
+		let messages = self.incoming.try_iter().collect::<Vec<_>>();
+		for message in messages {
+			self.receive(message, None);
+		}
+	}
+
+	/// Blocks the game until the debugger resumes `script`.
+	pub fn pause(&mut self, reason: &str, script: &dyn PausedScript) {
+		self.step = StepMode::Run;
+		self.send_event("stopped", Json::object([
+			("reason", reason.into()),
+			("threadId", script.thread_id().into()),
+			("allThreadsStopped", true.into()),
+		]));
+
+		while self.client.is_some() {
+			let Ok(message) = self.incoming.recv() else {
+				break;
+			};
+			if self.receive(message, Some(script)) {
+				break;
+			}
+		}
+		self.handles.clear();
+	}
+
+	/// Handles a message from the listener thread, returns whether a paused script resumes.
+	fn receive(&mut self, message: Incoming, paused: Option<&dyn PausedScript>) -> bool {
+		match message {
+			Incoming::Connected(stream) => {
+				log!(self.logger, LogLevel::Info, "Debugger connected from \"{}\"", stream.peer_addr().map(|address| address.to_string()).unwrap_or_default());
+				self.client = Some(stream);
+				self.seq = 1;
+				false
+			}
+			Incoming::Disconnected => {
+				log!(self.logger, LogLevel::Info, "Debugger disconnected");
+				self.client = None;
+				self.breakpoints.clear();
+				self.step = StepMode::Run;
+				true
+			}
+			Incoming::Message(message) if message.get("type").and_then(Json::as_str) == Some("request") => self.request(&message, paused),
+			Incoming::Message(_) => false,
+		}
+	}
+
+	fn request(&mut self, message: &Json, paused: Option<&dyn PausedScript>) -> bool {
+		let command = message.get("command").and_then(Json::as_str).unwrap_or_default().to_string();
+		let arguments = message.get("arguments").cloned().unwrap_or_default();
+		let mut resume = false;
+
+		let result = match (command.as_str(), paused) {
+			("initialize", _) => Ok(Json::object([
+				("supportsConfigurationDoneRequest", true.into()),
+				("supportsEvaluateForHovers", true.into()),
+			])),
+			("launch" | "attach" | "configurationDone" | "setExceptionBreakpoints", _) => Ok(Json::Null),
+			("disconnect", _) => {
+				self.breakpoints.clear();
+				self.step = StepMode::Run;
+				resume = true;
+				Ok(Json::Null)
+			}
+			("setBreakpoints", _) => self.set_breakpoints(&arguments),
+			("threads", _) => Ok(Json::object([("threads", Json::Array(vec![
+				Json::object([("id", LUA_THREAD.into()), ("name", "Lua".into())]),
+				Json::object([("id", JS_THREAD.into()), ("name", "JavaScript".into())]),
+			]))])),
+			("pause", _) => {
+				self.step = StepMode::Pause;
+				Ok(Json::Null)
+			}
+			("continue", _) => {
+				resume = true;
+				Ok(Json::object([("allThreadsContinued", true.into())]))
+			}
+			("next" | "stepIn" | "stepOut", Some(script)) => {
+				let (thread, depth) = (script.thread_id(), script.frames().len());
+				self.step = match command.as_str() {
+					"next" => StepMode::Over(thread, depth),
+					"stepIn" => StepMode::In(thread),
+					_ => StepMode::Out(thread, depth),
+				};
+				resume = true;
+				Ok(Json::Null)
+			}
+			("stackTrace", Some(script)) => Ok(self.stack_trace(script)),
+			("stackTrace", None) => Ok(Json::object([("stackFrames", Json::Array(Vec::new())), ("totalFrames", 0i64.into())])),
+			("scopes", Some(script)) => Ok(self.scopes(script, &arguments)),
+			("variables", Some(script)) => Ok(self.variables(script, &arguments)),
+			("evaluate", Some(script)) => self.evaluate(script, &arguments),
+			("scopes" | "variables" | "evaluate" | "next" | "stepIn" | "stepOut", None) => {
+				Err("The scripts are not paused".to_string())
+			}
+			_ => Err(format!("Unsupported request \"{}\"", command)),
+		};
+
+		self.respond(message, &command, result);
+		if command == "initialize" {
+			self.send_event("initialized", Json::Null);
+		}
+		resume
+	}
+
+	fn set_breakpoints(&mut self, arguments: &Json) -> Result<Json, String> {
+		let Some(path) = arguments.at(&["source", "path"]).and_then(Json::as_str) else {
+			return Err("setBreakpoints without a source path".to_string());
+		};
+		let path = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+
+		let lines = arguments.get("breakpoints")
+			.and_then(Json::as_array)
+			.map(|breakpoints| breakpoints.iter().filter_map(|breakpoint| breakpoint.get("line").and_then(Json::as_i64)).collect::<Vec<_>>())
+			.unwrap_or_default();
+
+		let reported = lines.iter()
+			.map(|line| Json::object([("verified", true.into()), ("line", (*line).into())]))
+			.collect::<Vec<_>>();
+
+		if lines.is_empty() {
+			self.breakpoints.remove(&path);
+		} else {
+			self.breakpoints.insert(path.clone(), lines.into_iter().map(|line| line as u32).collect());
+		}
+		if !self.reload.contains(&path) {
+			self.reload.push(path);
+		}
+
+		Ok(Json::object([("breakpoints", Json::Array(reported))]))
+	}
+
+	fn stack_trace(&mut self, script: &dyn PausedScript) -> Json {
+		let frames = script.frames().iter().enumerate().map(|(id, frame)| {
+			let path = self.resolve(&frame.file).unwrap_or_else(|| PathBuf::from(&frame.file));
+			let name = Path::new(&frame.file).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| frame.file.clone());
+			Json::object([
+				("id", id.into()),
+				("name", frame.function.clone().into()),
+				("source", Json::object([("name", name.into()), ("path", path.to_string_lossy().to_string().into())])),
+				("line", frame.line.unwrap_or(0).into()),
+				("column", frame.column.unwrap_or(1).into()),
+			])
+		}).collect::<Vec<_>>();
+
+		let total = frames.len();
+		Json::object([("stackFrames", Json::Array(frames)), ("totalFrames", total.into())])
+	}
+
+	fn scopes(&mut self, script: &dyn PausedScript, arguments: &Json) -> Json {
+		let frame = arguments.get("frameId").and_then(Json::as_i64).unwrap_or(0) as usize;
+		let locals = self.handle(frame, None);
+		let globals = self.handle(frame, Some(script.globals_expression().to_string()));
+
+		Json::object([("scopes", Json::Array(vec![
+			Json::object([("name", "Locals".into()), ("variablesReference", locals.into()), ("expensive", false.into())]),
+			Json::object([("name", "Globals".into()), ("variablesReference", globals.into()), ("expensive", true.into())]),
+		]))])
+	}
+
+	fn variables(&mut self, script: &dyn PausedScript, arguments: &Json) -> Json {
+		let reference = arguments.get("variablesReference").and_then(Json::as_i64).unwrap_or(0) as usize;
+		let variables = match self.handles.get(reference.wrapping_sub(1)).cloned() {
+			Some((frame, None)) => script.locals(frame).into_iter().map(|variable| (frame, variable)).collect(),
+			Some((frame, Some(expression))) => script.children(frame, &expression).into_iter().map(|variable| (frame, variable)).collect(),
+			None => Vec::new(),
+		};
+
+		let variables = variables.into_iter().map(|(frame, variable)| {
+			let reference = variable.expression.clone().map_or(0, |expression| self.handle(frame, Some(expression)));
+			Json::object([
+				("name", variable.name.into()),
+				("value", variable.value.into()),
+				("type", variable.kind.into()),
+				("variablesReference", reference.into()),
+			])
+		}).collect();
+		Json::object([("variables", Json::Array(variables))])
+	}
+
+	fn evaluate(&mut self, script: &dyn PausedScript, arguments: &Json) -> Result<Json, String> {
+		let frame = arguments.get("frameId").and_then(Json::as_i64).unwrap_or(0) as usize;
+		let expression = arguments.get("expression").and_then(Json::as_str).unwrap_or_default();
+
+		let variable = script.evaluate(frame, expression)?;
+		let reference = variable.expression.map_or(0, |expression| self.handle(frame, Some(expression)));
+		Ok(Json::object([
+			("result", variable.value.into()),
+			("type", variable.kind.into()),
+			("variablesReference", reference.into()),
+		]))
+	}
+
+	fn handle(&mut self, frame: usize, expression: Option<String>) -> usize {
+		self.handles.push((frame, expression));
+		self.handles.len()
+	}
+
+	fn respond(&mut self, request: &Json, command: &str, result: Result<Json, String>) {
+		let mut response = Json::object([
+			("type", "response".into()),
+			("request_seq", request.get("seq").cloned().unwrap_or_default()),
+			("command", command.into()),
+			("success", result.is_ok().into()),
+		]);
+		match result {
+			Ok(Json::Null) => {}
+			Ok(body) => response.set("body", body),
+			Err(message) => response.set("message", message.into()),
+		}
+		self.send(response);
+	}
+
+	fn send_event(&mut self, event: &str, body: Json) {
+		let mut message = Json::object([("type", "event".into()), ("event", event.into())]);
+		if body != Json::Null {
+			message.set("body", body);
+		}
+		self.send(message);
+	}
+
+	fn send(&mut self, mut message: Json) {
+		message.set("seq", self.seq.into());
+		self.seq += 1;
+
+		let Some(client) = self.client.as_mut() else {
+			return;
+		};
+		if let Err(err) = protocol::write_message(client, &message) {
+			log!(self.logger, LogLevel::Warning, "Failed to send a message to the debugger: {}", err);
+		}
+	}
+}
+
+/// Debug Adapter Protocol server stopping Lua and JS scripts at breakpoints, so editors
+/// like VS Code can attach to the running game. Clients send the token of the run (see
+/// `TOKEN_FILE`) on the first line.
+pub struct Debugger {
+	session: Arc<Mutex<DebugSession>>,
+	/// Whether the Lua line hook is currently installed.
+	attached: bool,
+}
+
+impl Debugger {
+	pub fn bind(port: u16) -> Option<Self> {
+		let mut logger = Logger::new("debug/debugger.log");
+
+		// Debuggers evaluate code, only clients that can read the token get to connect
+		let token = generate_token();
+		if let Err(err) = fs::write(TOKEN_FILE, &token) {
+			log!(logger, LogLevel::Warning, "Failed to write the debug adapter token to \"{}\": {}", TOKEN_FILE, err);
+		}
+		let incoming = match protocol::listen(port, token.clone()) {
+			Ok(incoming) => incoming,
+			Err(err) => {
+				log!(logger, LogLevel::Error, "Failed to start the debug adapter on port \"{}\": {}", port, err);
+				return None;
+			}
+		};
+		log!(logger, LogLevel::Info, "Debug adapter listening on \"127.0.0.1:{}\" with the token \"{}\"", port, token);
+
+		Some(Debugger {
+			session: Arc::new(Mutex::new(DebugSession::new(incoming))),
+			attached: false,
+		})
+	}
+
+	/// Answers the debugger and hooks the scripts in while one is connected. Call before
+	/// the scripts load, so Lua scripts whose breakpoints changed run again.
+	pub fn poll(&mut self, lua_parser: &mut LuaParser, js_parser: &mut JSParser) {
+		let mut session = self.session.lock().unwrap();
+		session.poll();
+		let attached = session.is_attached();
+		let reload = std::mem::take(&mut session.reload);
+		drop(session);
+
+		// Running JS again redeclares its top level `let` and `const`, so JS that already ran
+		// only reaches breakpoints once it reloads on its own.
+		if attached != self.attached {
+			self.attached = attached;
+			if attached {
+				lua_parser.attach_debugger(self.session.clone());
+				js_parser.attach_debugger(self.session.clone());
+			} else {
+				lua_parser.detach_debugger();
+				js_parser.detach_debugger();
+			}
+		}
+
+		for path in reload {
+			lua_parser.loaded.retain(|file, _| !fs::canonicalize(file).is_ok_and(|file| file == path));
+		}
+	}
+}
@@ -0,0 +1,223 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use mlua::prelude::*;
+use rquickjs::{CaughtError, Ctx};
+
+/// File name QuickJS reports for code passed to `Ctx::eval`.
+const JS_EVAL_FILE: &str = "eval_script";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptLanguage {
+	Lua,
+	JavaScript,
+}
+
+impl fmt::Display for ScriptLanguage {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ScriptLanguage::Lua => write!(f, "Lua"),
+			ScriptLanguage::JavaScript => write!(f, "JS"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+	pub function: String,
+	pub file: String,
+	pub line: Option<u32>,
+	pub column: Option<u32>,
+}
+
+impl fmt::Display for StackFrame {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "at {} ({}", self.function, self.file)?;
+		if let Some(line) = self.line {
+			write!(f, ":{}", line)?;
+		}
+		if let Some(column) = self.column {
+			write!(f, ":{}", column)?;
+		}
+		write!(f, ")")
+	}
+}
+
+/// A script failure with the location it happened at and the stack leading to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+	pub language: ScriptLanguage,
+	pub file: String,
+	pub line: Option<u32>,
+	pub column: Option<u32>,
+	pub message: String,
+	pub stack: Vec<StackFrame>,
+}
+
+impl ScriptError {
+	pub fn new(language: ScriptLanguage, file: &str, message: &str) -> Self {
+		ScriptError {
+			language,
+			file: file.to_string(),
+			line: None,
+			column: None,
+			message: message.to_string(),
+			stack: Vec::new(),
+		}
+	}
+
+	/// `file:line:column` of the failure, as far as it is known.
+	pub fn location(&self) -> String {
+		let mut location = self.file.clone();
+		if let Some(line) = self.line {
+			location.push_str(&format!(":{}", line));
+			if let Some(column) = self.column {
+				location.push_str(&format!(":{}", column));
+			}
+		}
+		location
+	}
+
+	pub fn from_lua(file: &str, error: &LuaError, stack: Vec<StackFrame>) -> Self {
+		let text = match error {
+			LuaError::SyntaxError { message, .. } => message.clone(),
+			LuaError::CallbackError { cause, .. } => cause.to_string(),
+			error => error.to_string(),
+		};
+		Self::from_lua_message(file, &text, stack)
+	}
+
+	pub fn from_lua_message(file: &str, text: &str, stack: Vec<StackFrame>) -> Self {
+		// Runtime errors carry their own traceback after the message, the frames are captured separately.
+		let text = text.split("\nstack traceback:").next().unwrap_or(text).trim();
+		let text = text.strip_prefix("runtime error: ").unwrap_or(text);
+		let text = text.strip_prefix("syntax error: ").unwrap_or(text);
+
+		let mut error = ScriptError::new(ScriptLanguage::Lua, file, text);
+		if let Some((source, line, message)) = split_lua_location(text) {
+			error.file = source;
+			error.line = Some(line);
+			error.message = message;
+		} else if let Some(frame) = stack.iter().find(|frame| frame.line.is_some()) {
+			error.file = frame.file.clone();
+			error.line = frame.line;
+		}
+		error.stack = stack;
+		error
+	}
+
+	pub fn from_js<'js>(ctx: &Ctx<'js>, file: &str, error: CaughtError<'js>) -> Self {
+		match error {
+			CaughtError::Exception(exception) => {
+				let name = exception.get::<_, Option<String>>("name").ok().flatten().unwrap_or_else(|| "Error".to_string());
+				let message = exception.message().unwrap_or_default();
+
+				let mut error = ScriptError::new(ScriptLanguage::JavaScript, file, &format!("{}: {}", name, message));
+				error.stack = exception.stack().map(|stack| parse_js_stack(&stack, file)).unwrap_or_default();
+				error.line = exception.line().filter(|line| *line > 0).map(|line| line as u32);
+				error.column = exception.column().filter(|column| *column > 0).map(|column| column as u32);
+
+				if error.line.is_none() {
+					if let Some(frame) = error.stack.iter().find(|frame| frame.file == file) {
+						error.line = frame.line;
+						error.column = frame.column;
+					}
+				}
+				error
+			}
+			CaughtError::Value(value) => {
+				let text = ctx.json_stringify(value.clone()).ok().flatten()
+					.and_then(|json| json.to_string().ok())
+					.unwrap_or_else(|| format!("{:?}", value.type_of()));
+				ScriptError::new(ScriptLanguage::JavaScript, file, &format!("Uncaught {}", text))
+			}
+			CaughtError::Error(error) => ScriptError::new(ScriptLanguage::JavaScript, file, &error.to_string()),
+		}
+	}
+}
+
+impl fmt::Display for ScriptError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "[{}] {}: {}", self.language, self.location(), self.message)?;
+		for frame in self.stack.iter() {
+			write!(f, "\n    {}", frame)?;
+		}
+		Ok(())
+	}
+}
+
+/// Splits `"path/to/file.lua:12: message"` into its parts.
+fn split_lua_location(text: &str) -> Option<(String, u32, String)> {
+	let mut search = 0;
+	while let Some(offset) = text[search..].find(':') {
+		let colon = search + offset;
+		let rest = &text[colon + 1..];
+		let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+		if digits > 0 && rest[digits..].starts_with(':') {
+			let line = rest[..digits].parse().ok()?;
+			let message = rest[digits + 1..].trim().to_string();
+			return Some((text[..colon].to_string(), line, message));
+		}
+		search = colon + 1;
+	}
+	None
+}
+
+/// Parses QuickJS' `"    at name (file:line:column)"` stack lines.
+pub(super) fn parse_js_stack(stack: &str, file: &str) -> Vec<StackFrame> {
+	stack.lines()
+		.filter_map(|line| line.trim().strip_prefix("at "))
+		.map(|frame| {
+			let (function, location) = match frame.rfind(" (") {
+				Some(open) => (&frame[..open], frame[open + 2..].trim_end_matches(')')),
+				None => ("<anonymous>", frame),
+			};
+
+			let mut parts = location.rsplitn(3, ':');
+			let column = parts.next().and_then(|c| c.parse().ok());
+			let line = parts.next().and_then(|l| l.parse().ok());
+			let source = parts.next().unwrap_or(location);
+			let (source, line, column) = match (line, column) {
+				(Some(line), Some(column)) => (source, Some(line), Some(column)),
+				// Only `file:line` was given.
+				(None, Some(line)) => (location.rsplit_once(':').map_or(location, |(s, _)| s), Some(line), None),
+				_ => (location, None, None),
+			};
+
+			StackFrame {
+				function: function.to_string(),
+				file: if source == JS_EVAL_FILE { file.to_string() } else { source.to_string() },
+				line,
+				column,
+			}
+		})
+		.collect()
+}
+
+/// Builds an `xpcall` message handler recording the Lua call stack into `frames`.
+pub fn lua_traceback_handler<'lua>(lua: &'lua Lua, frames: Rc<RefCell<Vec<StackFrame>>>) -> LuaResult<LuaFunction<'lua>> {
+	lua.create_function(move |lua, error: LuaValue| {
+		let mut stack = Vec::new();
+		// Level 0 is this handler, level 1 is whatever raised the error.
+		let mut level = 1;
+		while let Some(debug) = lua.inspect_stack(level) {
+			let source = debug.source();
+			if source.what != "C" {
+				let function = match (debug.names().name, source.what) {
+					(Some(name), _) => name.into_owned(),
+					(None, "main") => "<main chunk>".to_string(),
+					(None, _) => "<anonymous>".to_string(),
+				};
+				let line = debug.curr_line();
+				stack.push(StackFrame {
+					function,
+					file: source.short_src.map(|s| s.into_owned()).unwrap_or_else(|| "?".to_string()),
+					line: (line > 0).then_some(line as u32),
+					column: None,
+				});
+			}
+			level += 1;
+		}
+		*frames.borrow_mut() = stack;
+		Ok(error)
+	})
+}
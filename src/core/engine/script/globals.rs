@@ -1,6 +1,6 @@
-use std::{thread, time::Duration};
-use rquickjs::{prelude::{Func, Rest}, Context, Object, Runtime, Value};
-use super::parser::{JSParser, LuaParser};
+use std::{sync::{Arc, Mutex}, thread, time::Duration};
+use rquickjs::{prelude::Func, Context, Ctx, IntoJs, Runtime, Value};
+use super::{console::{create_console, Console, CONSOLE_TYPE}, parser::{JSParser, LuaParser}};
 
 impl LuaParser {
 	fn export_functions(&mut self) {
@@ -24,81 +24,92 @@ impl LuaParser {
 
 impl JSParser {
 	fn export_functions(&mut self) {
-	   let context = self.context.borrow();
-	   context.with(|ctx| {
-		  // Example: Exporting a Rust function to JavaScript as "clear_window_color"
+		let context = self.context.borrow();
+		context.with(|ctx| {
 			let globals = ctx.globals();
-		  globals.set(
-			 "clear_window_color",
-			 Func::new(|red: f32, green: f32, blue: f32, alpha: f32| {
-				clear_gl_window_color(red, green, blue, alpha);
-			 })
-		  ).unwrap();
-			
-
-			// Create a `console` object
-			let console = Object::new(ctx).unwrap();
-
-			// Set the `log` function on the `console` object
-			console.set("log", Func::new(console_log)).unwrap();
-			console.set("warn", Func::new(console_log)).unwrap();
-			console.set("error", Func::new(console_log)).unwrap();
-		
-			// Set the `console` object globally
-			globals.set("console", console).unwrap();
-
-			globals.set(
-				"setTimeout",
-				Func::new(move |callback: String, delay_ms: u64| {
-					thread::spawn(move || {
-						thread::sleep(Duration::from_millis(delay_ms));
-						let rt = Runtime::new().unwrap();
-						let ctx = Context::full(&rt).unwrap();
-						ctx.with(|ct| {
-							ct.eval::<(), _>(format!("({})()", callback)).unwrap();
-						});
-					});
-				})
-			).unwrap();
-	   });
+			for global in JS_GLOBALS {
+				globals.set(global.name, (global.value)(&ctx).unwrap()).unwrap();
+			}
+		});
 	}
 
 	pub fn set_globals(&mut self) {
-		self.context.borrow().with(|ctx| {
-			ctx.globals().set("myGlobal", 42).unwrap();
-		});
 		self.export_functions();
 		// todo
 	}
 }
 
-// Define a Rust function that you want to expose to Lua
-fn clear_gl_window_color(red: f32, green: f32, blue: f32, alpha: f32) {
-	unsafe { gl::ClearColor(red, green, blue, alpha) };
+/// A global of the JS scripts, registered and declared for TypeScript from the same entry.
+struct JsGlobal {
+	name: &'static str,
+	doc: &'static str,
+	/// What follows the name in the declaration, a leading `(` declares a function.
+	signature: &'static str,
+	value: for<'js> fn(&Ctx<'js>) -> rquickjs::Result<Value<'js>>,
 }
 
-fn print_value(value: Value) {
-	let mut message = Vec::new();
-
-	if let Some(string_value) = value.clone().into_string() {
-	   message.push(string_value.to_string().unwrap());
-	} else if let Some(num_value) = value.as_int() {
-	   message.push(num_value.to_string());
-	} else if value.is_array() {
-	   let length = value.as_array().unwrap().len();
-	   let mut array_items = Vec::new();
-	   for i in 0..length {
-		  let item = value.as_array().unwrap().get(i).unwrap();
-		  let mut item_message = Vec::new();
-		  print_value_into(item, &mut item_message);
-		  array_items.push(item_message.join(", ").trim().to_string());
-	   }
-	   message.push(format!("[{}]", array_items.join(", ")));
-	} else {
-	   message.push("Unknown type".to_string());
+const JS_GLOBALS: &[JsGlobal] = &[
+	JsGlobal {
+		name: "clear_window_color",
+		doc: "Sets the color the window is cleared with, every channel ranges from 0 to 1.",
+		signature: "(red: number, green: number, blue: number, alpha: number): void",
+		value: |ctx| Func::new(|red: f32, green: f32, blue: f32, alpha: f32| {
+			clear_gl_window_color(red, green, blue, alpha);
+		}).into_js(ctx),
+	},
+	JsGlobal {
+		name: "setTimeout",
+		doc: "Runs the function source `callback` once after `delay_ms` milliseconds, in a fresh context.",
+		signature: "(callback: string, delay_ms: number): void",
+		value: |ctx| Func::new(move |callback: String, delay_ms: u64| {
+			thread::spawn(move || {
+				thread::sleep(Duration::from_millis(delay_ms));
+				let rt = Runtime::new().unwrap();
+				let ctx = Context::full(&rt).unwrap();
+				ctx.with(|ct| {
+					ct.eval::<(), _>(format!("({})()", callback)).unwrap();
+				});
+			});
+		}).into_js(ctx),
+	},
+	JsGlobal {
+		name: "myGlobal",
+		doc: "",
+		signature: "number",
+		value: |ctx| 42.into_js(ctx),
+	},
+	JsGlobal {
+		name: "console",
+		doc: "Writes to the terminal and `debug/script.log`, a leading string may use `%s %d %i %f %j %o %O %c` specifiers.",
+		signature: CONSOLE_TYPE,
+		value: |ctx| {
+			let console = Arc::new(Mutex::new(Console::new("debug/script.log")));
+			create_console(ctx, console)?.into_js(ctx)
+		},
+	},
+];
+
+/// TypeScript declarations of the JS globals, written next to the scripts so editors can
+/// type check them against the engine.
+pub fn js_declarations() -> String {
+	let mut declarations = String::from("// Written by nengine from the globals it registers, edits are overwritten.\n");
+	for global in JS_GLOBALS {
+		declarations.push('\n');
+		if !global.doc.is_empty() {
+			declarations.push_str(&format!("/** {} */\n", global.doc));
+		}
+		if global.signature.starts_with('(') {
+			declarations.push_str(&format!("declare function {}{};\n", global.name, global.signature));
+		} else {
+			declarations.push_str(&format!("declare const {}: {};\n", global.name, global.signature));
+		}
 	}
+	declarations
+}
 
-	print!("{} ", message.join(", ").trim());
+// Define a Rust function that you want to expose to Lua
+fn clear_gl_window_color(red: f32, green: f32, blue: f32, alpha: f32) {
+	unsafe { gl::ClearColor(red, green, blue, alpha) };
 }
 
 // fn set_timeout(delay_ms: u64, callback: String) {
@@ -113,33 +124,3 @@ fn print_value(value: Value) {
 // 		})
 //	});
 // }
-
-// Helper function to populate message vector
-fn print_value_into(value: Value, message: &mut Vec<String>) {
-	if let Some(string_value) = value.clone().into_string() {
-	   message.push(string_value.to_string().unwrap());
-	} else if let Some(num_value) = value.as_int() {
-	   message.push(num_value.to_string());
-	} else if value.is_array() {
-	   let length = value.as_array().unwrap().len();
-	   let mut array_items = Vec::new();
-	   for i in 0..length {
-		  let item = value.as_array().unwrap().get(i).unwrap();
-		  let mut item_message = Vec::new();
-		  print_value_into(item, &mut item_message);
-		  array_items.push(item_message.join(", ").trim().to_string());
-	   }
-	   message.push(format!("[{}]", array_items.join(", ")));
-	} else {
-	   message.push("Unknown type".to_string());
-	}
-}
-
-fn console_log(
-	values: Rest<Value>
-) {
-	for value in values.0 {
-		print_value(value);
-	}
-	println!();
-}
@@ -1,2 +1,9 @@
 pub mod parser;
-pub mod globals;
\ No newline at end of file
+pub mod globals;
+pub mod modules;
+pub mod sandbox;
+pub mod typescript;
+pub mod error;
+pub mod console;
+pub mod repl;
+pub mod debugger;
\ No newline at end of file
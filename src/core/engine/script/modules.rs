@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use mlua::{prelude::*, ChunkMode};
+
+use crate::{core::utils::archive::pack::Archive, log, utils::log::manager::{LogLevel, Logger}};
+use super::parser::LuaParser;
+
+impl LuaParser {
+	/// Points `package.path` at the script search roots and installs a searcher that
+	/// resolves modules from the mounted archives.
+	pub(super) fn setup_package(&mut self) {
+		self.update_package_path();
+
+		let archives = self.archives.clone();
+		let lua = self.lua.get_mut();
+		let package: LuaTable = lua.globals().get("package").unwrap();
+		let searchers: LuaTable = package.get("searchers").unwrap();
+
+		let archive_searcher = lua.create_function(move |lua, name: String| {
+			let relative = name.replace('.', "/");
+			let candidates = [format!("{}.lua", relative), format!("{}/init.lua", relative)];
+
+			for archive in archives.borrow().iter() {
+				for candidate in candidates.iter() {
+					if let Some(source) = archive.read_to_string(candidate) {
+						let chunk_name = format!("@{}:{}", archive.path, candidate);
+						let loader = lua.load(source).set_name(chunk_name.clone()).set_mode(ChunkMode::Text).into_function()?;
+						return (loader, chunk_name).into_lua_multi(lua);
+					}
+				}
+			}
+
+			let tried = archives.borrow().iter()
+				.flat_map(|archive| candidates.iter().map(move |c| format!("\n\tno file '{}' in archive '{}'", c, archive.path)))
+				.collect::<String>();
+			tried.into_lua_multi(lua)
+		}).unwrap();
+
+		searchers.raw_insert(searchers.raw_len() as i64 + 1, archive_searcher).unwrap();
+	}
+
+	/// Mounts a packed asset archive so `require` can load modules from it.
+	pub fn mount(&mut self, path: &str) {
+		match Archive::open(path) {
+			Ok(archive) => self.archives.borrow_mut().push(archive),
+			Err(err) => {
+				log!(Logger::new("debug/script.log"), LogLevel::Error, "Failed to mount script archive \"{}\": {}", path, err);
+			}
+		}
+	}
+
+	pub fn add_search_root(&mut self, root: &str) {
+		if !self.search_roots.iter().any(|r| r == root) {
+			self.search_roots.push(root.to_string());
+			self.update_package_path();
+		}
+	}
+
+	pub(super) fn add_search_root_for(&mut self, script: &str) {
+		let root = Path::new(script).parent()
+			.map(|p| p.to_string_lossy().into_owned())
+			.filter(|p| !p.is_empty())
+			.unwrap_or_else(|| ".".to_string());
+		self.add_search_root(&root);
+	}
+
+	fn update_package_path(&mut self) {
+		let path = self.search_roots.iter()
+			.flat_map(|root| [format!("{}/?.lua", root), format!("{}/?/init.lua", root)])
+			.collect::<Vec<_>>()
+			.join(";");
+
+		let lua = self.lua.get_mut();
+		let package: LuaTable = lua.globals().get("package").unwrap();
+		package.set("path", path).unwrap();
+	}
+}
@@ -1,23 +1,85 @@
-use std::{collections::HashMap, fs};
+use std::{cell::{Cell, RefCell}, collections::HashMap, fs, rc::Rc, sync::{Arc, Mutex}};
 
 use mlua::prelude::*;
-use rquickjs::{CatchResultExt, CaughtError, Context, Runtime};
+use rquickjs::{CatchResultExt, Context, Runtime};
+
+use crate::{core::utils::archive::pack::Archive, log, utils::log::manager::{LogLevel, Logger}};
+use super::{debugger::{self, session::DebugSession}, error::{lua_traceback_handler, ScriptError, ScriptLanguage}, typescript};
+
+/// Registry key of the untouched `xpcall`, scripts may replace the global one.
+const XPCALL_KEY: &str = "nengine.xpcall";
+
+/// Limits applied to untrusted (mod) scripts.
+#[derive(Debug, Clone, Default)]
+pub struct LuaSandbox {
+	/// Maximum number of VM instructions a single script execution may run.
+	pub instruction_limit: Option<u64>,
+	/// Maximum number of bytes the Lua state may allocate.
+	pub memory_limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LuaParserOptions {
+	/// Extra directories `require` searches, on top of the directory of every added script.
+	pub search_roots: Vec<String>,
+	/// Packed asset archives `require` falls back to when a module isn't found on disk.
+	pub archives: Vec<String>,
+	/// Removes `os.execute`, `io`, `debug` and friends and enforces the given limits.
+	pub sandbox: Option<LuaSandbox>,
+}
 
 pub struct LuaParser {
     pub lua: std::cell::RefCell<Lua>,
 	pub loaded: HashMap<String, String>,
-    pub files: Vec<String>
+    pub files: Vec<String>,
+
+	pub options: LuaParserOptions,
+	pub search_roots: Vec<String>,
+	pub archives: Rc<RefCell<Vec<Archive>>>,
+	pub(super) instructions: Rc<Cell<u64>>,
+	/// Session of the attached debugger, scripts stop at its breakpoints.
+	pub(super) debugger: Option<Arc<Mutex<DebugSession>>>,
+
+	/// Failure of the latest run of every script that is currently broken.
+	pub errors: HashMap<String, ScriptError>,
+	logger: Logger,
 }
 
 impl LuaParser {
 	pub fn setup() -> Self {
+		Self::setup_with(LuaParserOptions::default())
+	}
+
+	pub fn setup_with(options: LuaParserOptions) -> Self {
 		let lua = Lua::new();
+		let xpcall: LuaFunction = lua.globals().get("xpcall").unwrap();
+		lua.set_named_registry_value(XPCALL_KEY, xpcall).unwrap();
 		
-		LuaParser {
+		let mut parser = LuaParser {
 			lua: lua.into(),
 			loaded: HashMap::new(),
-			files: Vec::new()
+			files: Vec::new(),
+
+			search_roots: options.search_roots.clone(),
+			archives: Rc::new(RefCell::new(Vec::new())),
+			instructions: Rc::new(Cell::new(0)),
+			debugger: None,
+			options,
+
+			errors: HashMap::new(),
+			logger: Logger::new("debug/script.log"),
+		};
+
+		for archive in parser.options.archives.clone() {
+			parser.mount(&archive);
 		}
+
+		parser.setup_package();
+		if let Some(sandbox) = parser.options.sandbox.clone() {
+			parser.apply_sandbox(&sandbox);
+		}
+
+		parser
 	}
 
 	pub fn init_globals(&mut self) {
@@ -25,17 +87,18 @@ impl LuaParser {
 	}
 
 	pub fn add(&mut self, file: String) {
+		self.add_search_root_for(&file);
 		self.files.push(file);
 	}
 
 	pub fn load(&mut self) {
-        for file in self.files.iter() {
+        for file in self.files.clone() {
             // Read the current content of the file
-            let file_content = fs::read_to_string(file)
+            let file_content = fs::read_to_string(&file)
                 .unwrap_or_else(|_| panic!("Failed to read '{}' file and couldn't parse it.", file));
             
             // Check if the file content has changed
-            if let Some(old_content) = self.loaded.get(file) {
+            if let Some(old_content) = self.loaded.get(&file) {
                 if old_content == file_content.trim() {
                     // Content hasn't changed, skip loading
                     continue;
@@ -43,15 +106,47 @@ impl LuaParser {
             }
 
             // Load and execute the new script
-            let lua = self.lua.get_mut();
-            lua.load(&file_content).exec().unwrap_or_else(|err| {
-				eprintln!("=> Failed to execute '{}':\nOutput: {}", file.clone(), err);
-			});
+            match self.execute(&file, &file_content) {
+                Ok(()) => {
+                    self.errors.remove(&file);
+                }
+                Err(error) => {
+                    log!(self.logger, LogLevel::Error, "Failed to execute script:\n{}", error);
+                    self.errors.insert(file.clone(), error);
+                }
+            }
 
             // Update the hash map with the new content
             self.loaded.insert(file.to_string(), file_content.trim().to_string());
         }
     }
+
+	/// Runs `source` through `xpcall` so the call stack can be captured when it fails.
+	/// Every run starts with the whole instruction budget.
+	fn execute(&mut self, file: &str, source: &str) -> Result<(), ScriptError> {
+		self.instructions.set(0);
+		let lua = self.lua.get_mut();
+		let to_error = |err: LuaError| ScriptError::from_lua(file, &err, Vec::new());
+
+		let chunk = lua.load(source).set_name(format!("@{}", file)).into_function().map_err(to_error)?;
+		let frames = Rc::new(RefCell::new(Vec::new()));
+		let handler = lua_traceback_handler(lua, frames.clone()).map_err(to_error)?;
+		let xpcall: LuaFunction = lua.named_registry_value(XPCALL_KEY).map_err(to_error)?;
+
+		let (ok, error): (bool, LuaValue) = xpcall.call((chunk, handler)).map_err(to_error)?;
+		if ok {
+			return Ok(());
+		}
+
+		let stack = frames.take();
+		Err(match error {
+			LuaValue::Error(err) => ScriptError::from_lua(file, &err, stack),
+			value => {
+				let text = value.to_string().unwrap_or_else(|_| format!("(error object is a {} value)", value.type_name()));
+				ScriptError::from_lua_message(file, &text, stack)
+			}
+		})
+	}
 }
 
 pub struct JSParser {
@@ -59,6 +154,12 @@ pub struct JSParser {
     pub context: std::cell::RefCell<Context>,
     pub loaded: HashMap<String, String>,
     pub files: Vec<String>,
+    /// Session of the attached debugger, scripts are instrumented to stop at its breakpoints.
+    pub(super) debugger: Option<Arc<Mutex<DebugSession>>>,
+
+    /// Failure of the latest run of every script that is currently broken.
+    pub errors: HashMap<String, ScriptError>,
+    logger: Logger,
 }
 
 impl JSParser {
@@ -71,6 +172,10 @@ impl JSParser {
             context: std::cell::RefCell::new(ctx),
             loaded: HashMap::new(),
             files: Vec::new(),
+            debugger: None,
+
+            errors: HashMap::new(),
+            logger: Logger::new("debug/script.log"),
         }
     }
 
@@ -79,33 +184,64 @@ impl JSParser {
     }
 
     pub fn add(&mut self, file: String) {
+        if is_typescript(&file) {
+            typescript::write_declarations(&file);
+        }
         self.files.push(file);
     }
 
     pub fn load(&mut self) {
-        for file in self.files.iter() {
+        for file in self.files.clone() {
             // Read the file content
-            let file_content = fs::read_to_string(file)
+            let file_content = fs::read_to_string(&file)
                 .unwrap_or_else(|_| panic!("Failed to read '{}' file and couldn't parse it.", file));
             
             // Check if the file content has changed
-            if let Some(old_content) = self.loaded.get(file) {
+            if let Some(old_content) = self.loaded.get(&file) {
                 if old_content == file_content.trim() {
                     // Content hasn't changed, skip loading
                     continue;
                 }
             }
 
-            // Load and execute the new script in the JS context
-            let context = self.context.borrow();
-            context.with(|ctx| {
-                if let Err(CaughtError::Error(err)) = ctx.eval::<(), _>(file_content.clone()).catch(&ctx) {
-                    eprintln!("=> Failed to execute '{}':\nOutput: {}", file, err);
+            match self.execute(&file, &file_content) {
+                Ok(()) => {
+                    self.errors.remove(&file);
+                }
+                Err(error) => {
+                    log!(self.logger, LogLevel::Error, "Failed to execute script:\n{}", error);
+                    self.errors.insert(file.clone(), error);
                 }
-            });
+            }
 
             // Update the hash map with the new content
             self.loaded.insert(file.to_string(), file_content.trim().to_string());
         }
     }
+
+    fn execute(&mut self, file: &str, file_content: &str) -> Result<(), ScriptError> {
+        // TypeScript is stripped down to JavaScript before it reaches QuickJS,
+        // plain JS goes through the same pass when it needs instrumenting for the debugger
+        let source = if self.debugger.is_some() {
+            typescript::instrument(file_content, &|line, names| debugger::js::hook_call(file, line, names))
+        } else if is_typescript(file) {
+            typescript::transpile(file_content)
+        } else {
+            Ok(file_content.to_string())
+        };
+        let source = source.map_err(|err| {
+            let stage = if is_typescript(file) { "TypeScript" } else { "Debugger instrumentation" };
+            ScriptError::new(ScriptLanguage::JavaScript, file, &format!("{}: {}", stage, err))
+        })?;
+
+        // Load and execute the new script in the JS context
+        let context = self.context.borrow();
+        context.with(|ctx| {
+            ctx.eval::<(), _>(source).catch(&ctx).map_err(|err| ScriptError::from_js(&ctx, file, err))
+        })
+    }
+}
+
+pub fn is_typescript(file: &str) -> bool {
+    file.ends_with(".ts") && !file.ends_with(".d.ts")
 }
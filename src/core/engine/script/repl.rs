@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+
+use mlua::prelude::*;
+use rquickjs::{CatchResultExt, Filter, Object, Value};
+
+use super::{console::inspect, error::ScriptError, parser::{JSParser, LuaParser}};
+
+/// Name errors of console input are reported under.
+const CONSOLE_FILE: &str = "console";
+/// How deep tables are expanded when printing a Lua result.
+const MAX_TABLE_DEPTH: usize = 2;
+
+impl LuaParser {
+	/// Evaluates a line typed into the developer console in the live Lua state.
+	/// Expressions print their value, anything else runs as a statement.
+	pub fn evaluate(&mut self, source: &str) -> Result<String, ScriptError> {
+		self.instructions.set(0);
+		let lua = self.lua.get_mut();
+		let to_error = |err: LuaError| ScriptError::from_lua(CONSOLE_FILE, &err, Vec::new());
+
+		let chunk = match lua.load(format!("return {}", source)).set_name(format!("={}", CONSOLE_FILE)).into_function() {
+			Ok(chunk) => chunk,
+			Err(_) => lua.load(source).set_name(format!("={}", CONSOLE_FILE)).into_function().map_err(to_error)?,
+		};
+
+		let values: LuaMultiValue = chunk.call(()).map_err(to_error)?;
+		Ok(values.iter()
+			.map(|value| format_lua_value(value, 0, &mut Vec::new()))
+			.collect::<Vec<_>>()
+			.join("\t"))
+	}
+
+	/// Globals (or fields of tables, for `a.b` paths) starting with the last part of `path`.
+	pub fn complete(&self, path: &str) -> Vec<String> {
+		let lua = self.lua.borrow();
+		let (parents, partial) = split_path(path, &['.', ':']);
+
+		let mut table = lua.globals();
+		for parent in parents.iter() {
+			match table.get::<_, LuaValue>(parent.as_str()) {
+				Ok(LuaValue::Table(next)) => table = next,
+				_ => return Vec::new(),
+			}
+		}
+
+		let mut names = HashSet::new();
+		let mut current = Some(table);
+		// Follow `__index` tables so methods of classes complete as well.
+		while let Some(table) = current.take() {
+			for (key, _) in table.clone().pairs::<LuaValue, LuaValue>().flatten() {
+				if let LuaValue::String(key) = key {
+					if let Ok(key) = key.to_str() {
+						if key.starts_with(partial) {
+							names.insert(key.to_string());
+						}
+					}
+				}
+			}
+			current = table.get_metatable().and_then(|meta| meta.get::<_, LuaTable>("__index").ok());
+		}
+
+		let prefix = &path[..path.len() - partial.len()];
+		let mut completions = names.into_iter().map(|name| format!("{}{}", prefix, name)).collect::<Vec<_>>();
+		completions.sort();
+		completions
+	}
+}
+
+impl JSParser {
+	/// Evaluates a line typed into the developer console in the live JS context.
+	pub fn evaluate(&mut self, source: &str) -> Result<String, ScriptError> {
+		let context = self.context.borrow();
+		context.with(|ctx| {
+			let value = ctx.eval::<Value, _>(source).catch(&ctx).map_err(|err| ScriptError::from_js(&ctx, CONSOLE_FILE, err))?;
+			Ok(inspect(&ctx, &value))
+		})
+	}
+
+	/// Globals (or properties of objects, for `a.b` paths) starting with the last part of `path`.
+	pub fn complete(&self, path: &str) -> Vec<String> {
+		let context = self.context.borrow();
+		context.with(|ctx| {
+			let (parents, partial) = split_path(path, &['.']);
+
+			let mut object = ctx.globals();
+			for parent in parents.iter() {
+				match object.get::<_, Option<Object>>(parent.as_str()) {
+					Ok(Some(next)) => object = next,
+					_ => return Vec::new(),
+				}
+			}
+
+			let mut names = HashSet::new();
+			let mut current = Some(object);
+			// Built-ins are not enumerable, so list every string key along the prototype chain.
+			while let Some(object) = current.take() {
+				for key in object.own_keys::<String>(Filter::new().string()).flatten() {
+					if key.starts_with(partial) {
+						names.insert(key);
+					}
+				}
+				current = object.get_prototype();
+			}
+
+			let prefix = &path[..path.len() - partial.len()];
+			let mut completions = names.into_iter().map(|name| format!("{}{}", prefix, name)).collect::<Vec<_>>();
+			completions.sort();
+			completions
+		})
+	}
+}
+
+/// The trailing `a.b.c` style path of `line` that completion should extend.
+pub fn completion_path(line: &str) -> &str {
+	let start = line.char_indices()
+		.rev()
+		.take_while(|(_, c)| c.is_alphanumeric() || matches!(c, '_' | '$' | '.' | ':'))
+		.last()
+		.map_or(line.len(), |(index, _)| index);
+	&line[start..]
+}
+
+/// Splits `a.b.par` into `["a", "b"]` and `"par"`.
+fn split_path<'a>(path: &'a str, separators: &[char]) -> (Vec<String>, &'a str) {
+	match path.rfind(separators) {
+		Some(index) => {
+			let parents = path[..index].split(separators).map(str::to_string).collect();
+			(parents, &path[index + 1..])
+		}
+		None => (Vec::new(), path),
+	}
+}
+
+pub(super) fn format_lua_value(value: &LuaValue, depth: usize, seen: &mut Vec<*const std::ffi::c_void>) -> String {
+	match value {
+		LuaValue::Nil => "nil".to_string(),
+		LuaValue::String(string) if depth > 0 => format!("{:?}", string.to_string_lossy()),
+		LuaValue::String(string) => string.to_string_lossy().to_string(),
+		LuaValue::Table(table) => {
+			let pointer = table.to_pointer();
+			if seen.contains(&pointer) {
+				return "<cycle>".to_string();
+			}
+			if depth >= MAX_TABLE_DEPTH {
+				return format!("table: {:?}", pointer);
+			}
+
+			seen.push(pointer);
+			let mut entries = Vec::new();
+			let length = table.raw_len();
+			for (key, item) in table.clone().pairs::<LuaValue, LuaValue>().flatten() {
+				let item = format_lua_value(&item, depth + 1, seen);
+				match key {
+					// The array part is listed without its keys.
+					LuaValue::Integer(index) if index >= 1 && index as usize <= length => entries.push((index, item)),
+					LuaValue::String(ref name) if name.to_str().is_ok_and(is_lua_identifier) => {
+						entries.push((i64::MAX, format!("{} = {}", name.to_string_lossy(), item)));
+					}
+					key => entries.push((i64::MAX, format!("[{}] = {}", format_lua_value(&key, depth + 1, seen), item))),
+				}
+			}
+			seen.pop();
+
+			entries.sort_by_key(|(index, _)| *index);
+			if entries.is_empty() {
+				return "{}".to_string();
+			}
+			format!("{{ {} }}", entries.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>().join(", "))
+		}
+		value => value.to_string().unwrap_or_else(|_| value.type_name().to_string()),
+	}
+}
+
+fn is_lua_identifier(name: &str) -> bool {
+	let mut chars = name.chars();
+	chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,110 @@
+use mlua::{prelude::*, ChunkMode, DebugEvent, HookTriggers};
+
+use super::{debugger, parser::{LuaParser, LuaSandbox}};
+
+/// How many VM instructions run between two instruction limit checks.
+const INSTRUCTION_CHECK_INTERVAL: u32 = 1000;
+
+impl LuaParser {
+	/// Strips everything that lets an untrusted script reach outside the engine and
+	/// installs the CPU and memory limits.
+	pub(super) fn apply_sandbox(&mut self, sandbox: &LuaSandbox) {
+		let lua = self.lua.get_mut();
+
+		lua.load(r#"
+			io = nil
+			debug = nil
+			dofile = nil
+			loadfile = nil
+
+			os.execute = nil
+			os.exit = nil
+			os.remove = nil
+			os.rename = nil
+			os.tmpname = nil
+			os.getenv = nil
+			os.setlocale = nil
+
+			string.dump = nil
+
+			-- Native C modules are already refused by the safe state, make sure they stay that way.
+			package.loadlib = nil
+			package.cpath = ""
+
+			-- Binary chunks can break out of the VM, only accept source text. An explicit nil
+			-- env clears `_ENV`, so it's only forwarded when the caller gave one.
+			local raw_load = load
+			load = function(...)
+				local chunk, name, _, env = ...
+				if select('#', ...) >= 4 then
+					return raw_load(chunk, name, "t", env)
+				end
+				return raw_load(chunk, name, "t")
+			end
+		"#).set_name("=sandbox").exec().unwrap();
+
+		// The stock file searcher loads binary chunks as well, swap it for one that only takes source text
+		{
+			let package: LuaTable = lua.globals().get("package").unwrap();
+			let searchers: LuaTable = package.get("searchers").unwrap();
+			let file_searcher = lua.create_function(|lua, name: String| {
+				let package: LuaTable = lua.globals().get("package")?;
+				let searchpath: LuaFunction = package.get("searchpath")?;
+				let (path, error): (Option<String>, Option<String>) = searchpath.call((name.as_str(), package.get::<_, String>("path")?))?;
+				let Some(path) = path else {
+					return error.unwrap_or_default().into_lua_multi(lua);
+				};
+
+				let source = std::fs::read_to_string(&path).map_err(|err| {
+					LuaError::runtime(format!("error loading module '{}' from file '{}':\n\t{}", name, path, err))
+				})?;
+				let loader = lua.load(source).set_name(format!("@{}", path)).set_mode(ChunkMode::Text).into_function()?;
+				(loader, path).into_lua_multi(lua)
+			}).unwrap();
+			searchers.raw_set(2, file_searcher).unwrap();
+		}
+
+		if let Some(limit) = sandbox.memory_limit {
+			lua.set_memory_limit(limit).unwrap();
+		}
+
+		self.install_hook();
+	}
+
+	/// Lua only takes one hook, this one checks the instruction limit and runs the line
+	/// hook of an attached debugger.
+	pub(super) fn install_hook(&mut self) {
+		let limit = self.options.sandbox.as_ref().and_then(|sandbox| sandbox.instruction_limit);
+		let session = self.debugger.clone();
+		let lua = self.lua.get_mut();
+
+		let mut triggers = HookTriggers::new();
+		if limit.is_some() {
+			triggers = triggers.every_nth_instruction(INSTRUCTION_CHECK_INTERVAL);
+		}
+		if session.is_some() {
+			triggers = triggers.every_line();
+		}
+		if limit.is_none() && session.is_none() {
+			lua.remove_hook();
+			return;
+		}
+
+		let instructions = self.instructions.clone();
+		lua.set_hook(triggers, move |lua, debug| {
+			match (debug.event(), limit, &session) {
+				(DebugEvent::Count, Some(limit), _) => {
+					let executed = instructions.get() + INSTRUCTION_CHECK_INTERVAL as u64;
+					instructions.set(executed);
+
+					if executed > limit {
+						return Err(LuaError::runtime(format!("script exceeded its instruction limit of {}", limit)));
+					}
+					Ok(())
+				}
+				(DebugEvent::Line, _, Some(session)) => debugger::lua::on_line(lua, &debug, session),
+				_ => Ok(()),
+			}
+		});
+	}
+}
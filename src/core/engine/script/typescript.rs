@@ -0,0 +1,1382 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{log, utils::log::manager::{LogLevel, Logger}};
+use super::globals::js_declarations;
+
+/// Name of the declaration file written next to TypeScript scripts.
+pub const DECLARATIONS_FILE: &str = "nengine.d.ts";
+
+/// Strips TypeScript syntax from `source` so it can be evaluated as plain JavaScript.
+///
+/// Types are replaced with whitespace instead of being removed so every line and
+/// column of the output still points at the same place in the `.ts` file, only
+/// `enum`s and constructor parameter properties generate new code.
+pub fn transpile(source: &str) -> Result<String, String> {
+	let tokens = tokenize(source)?;
+	let mut transpiler = Transpiler::new(source, tokens)?;
+	transpiler.run()?;
+	Ok(transpiler.output())
+}
+
+/// Builds the code inserted in front of an instrumented statement from its line and the
+/// names of the variables declared in the scopes around it.
+pub type StatementHook<'a> = &'a dyn Fn(u32, &[String]) -> String;
+
+/// Transpiles `source` like `transpile` and inserts the code `hook` builds in front of the
+/// first statement of every line, used to stop scripts at breakpoints. The code is put on
+/// the same line, so line numbers keep pointing at the source.
+pub fn instrument(source: &str, hook: StatementHook) -> Result<String, String> {
+	let tokens = tokenize(source)?;
+	let mut transpiler = Transpiler::new(source, tokens)?;
+	transpiler.hook = Some(hook);
+	transpiler.run()?;
+	Ok(transpiler.output())
+}
+
+/// Writes the engine API declarations next to `script` so editors pick them up.
+pub fn write_declarations(script: &str) {
+	let directory = Path::new(script).parent().unwrap_or(Path::new("."));
+	let path = directory.join(DECLARATIONS_FILE);
+
+	let declarations = js_declarations();
+	if fs::read_to_string(&path).is_ok_and(|existing| existing == declarations) {
+		return;
+	}
+	if let Err(err) = fs::write(&path, declarations) {
+		log!(Logger::new("debug/script.log"), LogLevel::Warning, "Failed to write TypeScript declarations to \"{}\": {}", path.display(), err);
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+	Ident,
+	Number,
+	Str,
+	Template,
+	Regex,
+	Punct,
+	End,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+	kind: Kind,
+	start: usize,
+	end: usize,
+	newline_before: bool,
+}
+
+const PUNCTUATORS: [&str; 45] = [
+	"...", "===", "!==", "**=", "&&=", "||=", "??=", "<<=",
+	"=>", "==", "!=", "&&", "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "**", "<<",
+	"{", "}", "(", ")", "[", "]", ";", ",", "<", ">", "=", "!", "?", ":", ".", "+", "-", "*",
+];
+
+/// Words after which a `/` starts a regular expression rather than a division.
+const REGEX_PREFIX_WORDS: [&str; 12] = [
+	"return", "typeof", "case", "do", "else", "in", "of", "new", "delete", "void", "throw", "yield",
+];
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+	let bytes = src.as_bytes();
+	let mut tokens: Vec<Token> = Vec::new();
+	let mut i = 0;
+	let mut newline_before = false;
+
+	while i < bytes.len() {
+		let c = bytes[i];
+
+		if c == b'\n' {
+			newline_before = true;
+			i += 1;
+			continue;
+		}
+		if c.is_ascii_whitespace() {
+			i += 1;
+			continue;
+		}
+		if src[i..].starts_with("//") {
+			i = src[i..].find('\n').map_or(bytes.len(), |n| i + n);
+			continue;
+		}
+		if src[i..].starts_with("/*") {
+			let end = src[i + 2..].find("*/").map(|n| i + 2 + n + 2)
+				.ok_or_else(|| format!("Unterminated comment at {}", location(src, i)))?;
+			newline_before |= src[i..end].contains('\n');
+			i = end;
+			continue;
+		}
+
+		let start = i;
+		let kind = if c == b'_' || c == b'$' || c == b'#' || c == b'@' || c.is_ascii_alphabetic() || c >= 0x80 {
+			i += 1;
+			while i < bytes.len() && (bytes[i] == b'_' || bytes[i] == b'$' || bytes[i].is_ascii_alphanumeric() || bytes[i] >= 0x80) {
+				i += 1;
+			}
+			Kind::Ident
+		} else if c.is_ascii_digit() || (c == b'.' && bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit())) {
+			i += 1;
+			while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'_') {
+				i += 1;
+			}
+			Kind::Number
+		} else if c == b'"' || c == b'\'' {
+			i = skip_string(src, i)?;
+			Kind::Str
+		} else if c == b'`' {
+			i = skip_template(src, i, &mut Vec::new())?;
+			Kind::Template
+		} else if c == b'/' && regex_allowed(src, tokens.last()) {
+			i = skip_regex(src, i)?;
+			Kind::Regex
+		} else {
+			let punct = PUNCTUATORS.iter().find(|p| src[i..].starts_with(**p)).copied()
+				.unwrap_or(&src[i..i + src[i..].chars().next().unwrap().len_utf8()]);
+			i += punct.len();
+			Kind::Punct
+		};
+
+		tokens.push(Token { kind, start, end: i, newline_before });
+		newline_before = false;
+	}
+
+	tokens.push(Token { kind: Kind::End, start: src.len(), end: src.len(), newline_before: true });
+	Ok(tokens)
+}
+
+fn skip_string(src: &str, start: usize) -> Result<usize, String> {
+	let bytes = src.as_bytes();
+	let quote = bytes[start];
+	let mut i = start + 1;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' => i += 2,
+			b'\n' => break,
+			b if b == quote => return Ok(i + 1),
+			_ => i += 1,
+		}
+	}
+	Err(format!("Unterminated string at {}", location(src, start)))
+}
+
+/// Skips a template literal, collecting the byte ranges of its `${}` expressions.
+fn skip_template(src: &str, start: usize, expressions: &mut Vec<(usize, usize)>) -> Result<usize, String> {
+	let bytes = src.as_bytes();
+	let mut i = start + 1;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' => i += 2,
+			b'`' => return Ok(i + 1),
+			b'$' if bytes.get(i + 1) == Some(&b'{') => {
+				// Skip the embedded expression, keeping track of nested braces and strings.
+				let mut depth = 0;
+				i += 1;
+				let expression_start = i + 1;
+				while i < bytes.len() {
+					match bytes[i] {
+						b'{' => depth += 1,
+						b'}' => {
+							depth -= 1;
+							if depth == 0 {
+								break;
+							}
+						}
+						b'"' | b'\'' => i = skip_string(src, i)? - 1,
+						b'`' => i = skip_template(src, i, &mut Vec::new())? - 1,
+						_ => {}
+					}
+					i += 1;
+				}
+				expressions.push((expression_start, i.min(bytes.len())));
+				i += 1;
+			}
+			_ => i += 1,
+		}
+	}
+	Err(format!("Unterminated template literal at {}", location(src, start)))
+}
+
+fn skip_regex(src: &str, start: usize) -> Result<usize, String> {
+	let bytes = src.as_bytes();
+	let mut i = start + 1;
+	let mut in_class = false;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' => i += 1,
+			b'[' => in_class = true,
+			b']' => in_class = false,
+			b'/' if !in_class => {
+				i += 1;
+				while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+					i += 1;
+				}
+				return Ok(i);
+			}
+			b'\n' => break,
+			_ => {}
+		}
+		i += 1;
+	}
+	Err(format!("Unterminated regular expression at {}", location(src, start)))
+}
+
+fn regex_allowed(src: &str, previous: Option<&Token>) -> bool {
+	match previous {
+		None => true,
+		Some(token) => {
+			let text = &src[token.start..token.end];
+			match token.kind {
+				Kind::Ident => REGEX_PREFIX_WORDS.contains(&text),
+				Kind::Punct => !matches!(text, ")" | "]" | "}" | "++" | "--"),
+				_ => false,
+			}
+		}
+	}
+}
+
+fn location(src: &str, offset: usize) -> String {
+	let before = &src[..offset];
+	let line = before.matches('\n').count() + 1;
+	let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+	format!("{}:{}", line, column)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+	Top,
+	Block,
+	Class,
+	Object,
+	Params,
+	Paren,
+	Bracket,
+}
+
+#[derive(Debug)]
+struct Frame {
+	scope: Scope,
+	/// Unmatched `?` of conditional expressions at this depth.
+	ternaries: usize,
+	/// Inside a `let`/`const`/`var` declaration list.
+	declaring: bool,
+	/// Token a function or method signature starts at, used to drop bodiless overloads.
+	signature_start: Option<usize>,
+	/// Constructor parameter properties collected while inside the parameter list.
+	properties: Option<Vec<String>>,
+	/// The class has an `extends` clause, so properties are assigned after `super()`.
+	derived: bool,
+	/// Variables, parameters and functions declared directly in this scope.
+	names: Vec<String>,
+}
+
+impl Frame {
+	fn new(scope: Scope) -> Self {
+		Frame { scope, ternaries: 0, declaring: false, signature_start: None, properties: None, derived: false, names: Vec::new() }
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+struct ParamsMark {
+	signature_start: Option<usize>,
+	constructor: bool,
+}
+
+struct Edit {
+	start: usize,
+	end: usize,
+	replacement: Option<String>,
+}
+
+struct Transpiler<'a> {
+	src: &'a str,
+	tokens: Vec<Token>,
+	matching: Vec<usize>,
+	stack: Vec<Frame>,
+	edits: Vec<Edit>,
+
+	params: HashMap<usize, ParamsMark>,
+	classes: HashMap<usize, bool>,
+	/// Constructor bodies (keyed by their `{`) that need parameter properties assigned.
+	constructor_bodies: HashMap<usize, (Vec<String>, bool)>,
+	/// Last class member start that was inspected, computed names are walked again.
+	member_checked: Option<usize>,
+
+	/// Set by `instrument`, builds the code put in front of statements.
+	hook: Option<StatementHook<'a>>,
+	/// Parameters of the latest closed parameter list, they belong to the body that follows.
+	pending_names: Vec<String>,
+	/// Line the last hook was inserted on, only the first statement of a line gets one.
+	hooked_line: Option<u32>,
+	/// Byte offset and line number of the last token a line was looked up for.
+	line_cursor: (usize, u32),
+	/// First source the transpiler couldn't make sense of, it stops at the end right away.
+	error: Option<String>,
+}
+
+impl<'a> Transpiler<'a> {
+	fn new(src: &'a str, tokens: Vec<Token>) -> Result<Self, String> {
+		let mut matching = vec![usize::MAX; tokens.len()];
+		let mut open: Vec<usize> = Vec::new();
+		for (index, token) in tokens.iter().enumerate() {
+			let text = &src[token.start..token.end];
+			match text {
+				"(" | "[" | "{" if token.kind == Kind::Punct => open.push(index),
+				")" | "]" | "}" if token.kind == Kind::Punct => {
+					let opener = open.pop()
+						.ok_or_else(|| format!("Unexpected '{}' at {}", text, location(src, token.start)))?;
+					matching[opener] = index;
+					matching[index] = opener;
+				}
+				_ => {}
+			}
+		}
+		if let Some(opener) = open.pop() {
+			return Err(format!("Unclosed bracket at {}", location(src, tokens[opener].start)));
+		}
+
+		Ok(Transpiler {
+			src,
+			tokens,
+			matching,
+			stack: vec![Frame::new(Scope::Top)],
+			edits: Vec::new(),
+			params: HashMap::new(),
+			classes: HashMap::new(),
+			constructor_bodies: HashMap::new(),
+			member_checked: None,
+
+			hook: None,
+			pending_names: Vec::new(),
+			hooked_line: None,
+			line_cursor: (0, 1),
+			error: None,
+		})
+	}
+
+	fn text(&self, index: usize) -> &'a str {
+		let token = self.tokens[index.min(self.tokens.len() - 1)];
+		&self.src[token.start..token.end]
+	}
+
+	fn is(&self, index: usize, text: &str) -> bool {
+		index < self.tokens.len() && self.tokens[index].kind != Kind::Str && self.text(index) == text
+	}
+
+	fn is_ident(&self, index: usize) -> bool {
+		index < self.tokens.len() && self.tokens[index].kind == Kind::Ident
+	}
+
+	fn frame(&mut self) -> &mut Frame {
+		self.stack.last_mut().unwrap()
+	}
+
+	fn scope(&self) -> Scope {
+		self.stack.last().unwrap().scope
+	}
+
+	/// Blanks the tokens in `[from, to)` including whatever sits between them.
+	fn blank(&mut self, from: usize, to: usize) {
+		if from < to {
+			let start = self.tokens[from].start;
+			let end = self.tokens[to - 1].end;
+			self.edits.push(Edit { start, end, replacement: None });
+		}
+	}
+
+	fn output(mut self) -> String {
+		self.edits.sort_by_key(|edit| (edit.start, std::cmp::Reverse(edit.end)));
+
+		let mut out = String::with_capacity(self.src.len());
+		let mut cursor = 0;
+		for edit in self.edits.iter() {
+			if edit.start < cursor {
+				continue;
+			}
+			out.push_str(&self.src[cursor..edit.start]);
+			let original = &self.src[edit.start..edit.end];
+			match &edit.replacement {
+				Some(code) => {
+					// Keep the line count so later lines still map onto the source.
+					let missing = original.matches('\n').count().saturating_sub(code.matches('\n').count());
+					out.push_str(code);
+					out.push_str(&"\n".repeat(missing));
+				}
+				None => out.extend(original.chars().map(|c| if c == '\n' || c == '\r' { c } else { ' ' })),
+			}
+			cursor = edit.end;
+		}
+		out.push_str(&self.src[cursor..]);
+		out
+	}
+
+	fn run(&mut self) -> Result<(), String> {
+		let mut index = 0;
+		while self.tokens[index].kind != Kind::End {
+			index = self.step(index);
+		}
+		self.error.take().map_or(Ok(()), Err)
+	}
+
+	/// Records why the source can't be transpiled and returns the `End` token to stop at.
+	fn fail(&mut self, index: usize, reason: &str) -> usize {
+		let end = self.tokens.len() - 1;
+		if self.error.is_none() {
+			let offset = self.tokens[index.min(end)].start;
+			self.error = Some(format!("{} at {}", reason, location(self.src, offset)));
+		}
+		end
+	}
+
+	/// The token after the bracket closing the one at `index`.
+	fn after_close(&mut self, index: usize) -> usize {
+		match self.matching.get(index) {
+			Some(&close) if close != usize::MAX => close + 1,
+			_ => self.fail(index, "Expected a bracket"),
+		}
+	}
+
+	fn previous_ends_expression(&self, index: usize) -> bool {
+		if index == 0 {
+			return false;
+		}
+		let token = self.tokens[index - 1];
+		match token.kind {
+			Kind::Ident => !REGEX_PREFIX_WORDS.contains(&self.text(index - 1)) && !matches!(self.text(index - 1), "return" | "await"),
+			Kind::Number | Kind::Str | Kind::Template | Kind::Regex => true,
+			Kind::Punct => matches!(self.text(index - 1), ")" | "]" | "}"),
+			Kind::End => false,
+		}
+	}
+
+	fn at_statement_start(&self, index: usize) -> bool {
+		if !matches!(self.scope(), Scope::Top | Scope::Block) {
+			return false;
+		}
+		if index == 0 || self.tokens[index].newline_before {
+			return true;
+		}
+		matches!(self.text(index - 1), ";" | "{" | "}")
+	}
+
+	fn line_of(&mut self, index: usize) -> u32 {
+		let start = self.tokens[index].start;
+		let (offset, line) = self.line_cursor;
+		let line = line + self.src[offset..start].matches('\n').count() as u32;
+		self.line_cursor = (start, line);
+		line
+	}
+
+	/// Puts the instrumentation hook in front of the statement at `index` if it is the
+	/// first one on its line and inserting code there can't change what the code means.
+	fn insert_hook(&mut self, index: usize) {
+		let Some(hook) = self.hook else {
+			return;
+		};
+		if index > 0 && (!self.tokens[index].newline_before || self.continues_line(index)) {
+			return;
+		}
+		if matches!(self.text(index), "else" | "catch" | "finally" | "case" | "default" | "while" | "instanceof" | "in" | "of" | "as" | "satisfies") {
+			return;
+		}
+		if index > 0 {
+			let previous = index - 1;
+			match self.tokens[previous].kind {
+				// Bodies of `if (...)`, `for (...)` and `while (...)` without braces.
+				Kind::Punct if self.is(previous, ")") => {
+					let opener = self.matching[previous];
+					if opener > 0 && matches!(self.text(opener - 1), "if" | "for" | "while" | "with") && !self.ends_do_while(opener - 1) {
+						return;
+					}
+				}
+				Kind::Punct if !matches!(self.text(previous), ";" | "{" | "}" | "]" | "++" | "--") => return,
+				Kind::Ident if matches!(self.text(previous), "else" | "do" | "new" | "typeof" | "void" | "delete" | "await" | "yield" | "in" | "of" | "instanceof" | "extends") => return,
+				_ => {}
+			}
+		}
+
+		let line = self.line_of(index);
+		if self.hooked_line == Some(line) {
+			return;
+		}
+		self.hooked_line = Some(line);
+
+		let mut names: Vec<String> = Vec::new();
+		for name in self.stack.iter().flat_map(|frame| frame.names.iter()) {
+			if !names.contains(name) {
+				names.push(name.clone());
+			}
+		}
+		let position = self.tokens[index].start;
+		self.edits.push(Edit { start: position, end: position, replacement: Some(hook(line, &names)) });
+	}
+
+	/// Whether the `while` at `keyword` closes a `do { ... } while (...)` loop.
+	fn ends_do_while(&self, keyword: usize) -> bool {
+		keyword > 0 && self.is(keyword, "while") && self.is(keyword - 1, "}") && {
+			let open = self.matching[keyword - 1];
+			open > 0 && self.is(open - 1, "do")
+		}
+	}
+
+	/// Remembers a declared name for the instrumentation hook.
+	fn declare(&mut self, index: usize) {
+		if self.hook.is_some() && self.is_ident(index) {
+			let name = self.text(index).to_string();
+			let names = &mut self.frame().names;
+			if !names.contains(&name) {
+				names.push(name);
+			}
+		}
+	}
+
+	/// Declares the names bound by a `{ a, b: c }` or `[a, ...b]` destructuring pattern.
+	fn declare_pattern(&mut self, open: usize) {
+		for index in open + 1..self.matching[open] {
+			if self.is_ident(index) && matches!(self.text(index + 1), "," | "}" | "]" | "=") && !self.is(index - 1, ".") {
+				self.declare(index);
+			}
+		}
+	}
+
+	fn step(&mut self, index: usize) -> usize {
+		let text = self.text(index);
+		let kind = self.tokens[index].kind;
+
+		if self.tokens[index].newline_before && index > 0 && !self.continues_line(index) {
+			self.frame().declaring = false;
+		}
+
+		if self.scope() == Scope::Class && self.member_checked != Some(index) && self.at_member_start(index) {
+			self.member_checked = Some(index);
+			let next = self.member(index);
+			if next != index {
+				return next;
+			}
+		}
+
+		if kind == Kind::Ident && self.at_statement_start(index) {
+			self.insert_hook(index);
+			if let Some(next) = self.statement(index) {
+				return next;
+			}
+		}
+
+		if kind == Kind::Ident {
+			return self.word(index, text);
+		}
+		if kind == Kind::Template {
+			self.template(index);
+			return index + 1;
+		}
+		if kind != Kind::Punct {
+			return index + 1;
+		}
+
+		match text {
+			"(" => self.open_paren(index),
+			"[" => {
+				self.stack.push(Frame::new(Scope::Bracket));
+				index + 1
+			}
+			"{" => self.open_brace(index),
+			")" => self.close_paren(index),
+			"]" | "}" => {
+				if self.stack.len() > 1 {
+					self.stack.pop();
+				}
+				index + 1
+			}
+			"," => {
+				if self.scope() == Scope::Params {
+					return self.parameter(index + 1);
+				}
+				if self.stack.last().unwrap().declaring {
+					return self.binding(index + 1);
+				}
+				index + 1
+			}
+			";" => {
+				self.frame().declaring = false;
+				index + 1
+			}
+			"?" => {
+				self.frame().ternaries += 1;
+				index + 1
+			}
+			":" => {
+				let frame = self.frame();
+				frame.ternaries = frame.ternaries.saturating_sub(1);
+				index + 1
+			}
+			"!" => {
+				// Non-null assertion: `value!.field`, `call()!`.
+				let token = self.tokens[index];
+				if self.previous_ends_expression(index) && self.tokens[index - 1].end == token.start && !token.newline_before {
+					self.blank(index, index + 1);
+				}
+				index + 1
+			}
+			"<" => self.angle(index),
+			_ => index + 1,
+		}
+	}
+
+	/// Transpiles the expressions embedded in a template literal on their own.
+	fn template(&mut self, index: usize) {
+		let mut expressions = Vec::new();
+		if skip_template(self.src, self.tokens[index].start, &mut expressions).is_err() {
+			return;
+		}
+		for (start, end) in expressions {
+			let expression = &self.src[start..end];
+			if let Ok(code) = transpile(expression) {
+				if code != expression {
+					self.edits.push(Edit { start, end, replacement: Some(code) });
+				}
+			}
+		}
+	}
+
+	/// Handles TypeScript-only statements, returns `None` for regular ones.
+	fn statement(&mut self, index: usize) -> Option<usize> {
+		let text = self.text(index);
+		let next = self.text(index + 1);
+
+		match text {
+			"export" if matches!(next, "interface" | "declare" | "abstract" | "enum") || (next == "type" && (self.is_ident(index + 2) || self.is(index + 2, "{"))) => {
+				self.blank(index, index + 1);
+				self.statement(index + 1).or(Some(index + 1))
+			}
+			"export" if next == "const" && self.is(index + 2, "enum") => {
+				self.blank(index, index + 1);
+				self.statement(index + 1).or(Some(index + 1))
+			}
+			"declare" if self.is_ident(index + 1) && !self.tokens[index + 1].newline_before => {
+				let end = self.statement_end(index, true);
+				self.blank(index, end);
+				Some(end)
+			}
+			"interface" if self.is_ident(index + 1) && !self.tokens[index + 1].newline_before => {
+				let mut end = index + 1;
+				while !self.is(end, "{") && self.tokens[end].kind != Kind::End {
+					end += 1;
+				}
+				let end = if self.is(end, "{") { self.after_close(end) } else { self.fail(index, "Expected the body of the interface") };
+				self.blank(index, end);
+				Some(end)
+			}
+			"type" if self.is_ident(index + 1) && !self.tokens[index + 1].newline_before && (self.is(index + 2, "=") || self.is(index + 2, "<")) => {
+				let mut end = index + 2;
+				if self.is(end, "<") {
+					end = self.match_angle(end)? + 1;
+				}
+				end = self.parse_type(end + 1);
+				if self.is(end, ";") {
+					end += 1;
+				}
+				self.blank(index, end);
+				Some(end)
+			}
+			"import" if next == "type" => {
+				let end = self.statement_end(index, false);
+				self.blank(index, end);
+				Some(end)
+			}
+			"type" if next == "{" => {
+				let end = self.statement_end(index, false);
+				self.blank(index, end);
+				Some(end)
+			}
+			"abstract" if next == "class" => {
+				self.blank(index, index + 1);
+				Some(index + 1)
+			}
+			"enum" if self.is_ident(index + 1) => Some(self.enumeration(index, index)),
+			"const" if next == "enum" && self.is_ident(index + 2) => Some(self.enumeration(index, index + 1)),
+			_ => None,
+		}
+	}
+
+	/// Finds the token after a statement and its `;`, `with_block` ends it after the first `{ ... }`.
+	fn statement_end(&mut self, index: usize, with_block: bool) -> usize {
+		let mut end = index + 1;
+		while self.tokens[end].kind != Kind::End {
+			if self.is(end, "{") && !with_block {
+				end = self.after_close(end);
+				continue;
+			}
+			if self.is(end, "{") {
+				end = self.after_close(end);
+				break;
+			}
+			if self.is(end, "(") || self.is(end, "[") {
+				end = self.after_close(end);
+				continue;
+			}
+			if self.is(end, ";") {
+				return end + 1;
+			}
+			if self.tokens[end].newline_before && !self.continues_line(end) {
+				return end;
+			}
+			end += 1;
+		}
+		if self.is(end, ";") { end + 1 } else { end }
+	}
+
+	fn continues_line(&self, index: usize) -> bool {
+		let previous = self.text(index - 1);
+		let current = self.text(index);
+		matches!(previous, "," | "=" | "|" | "&" | ":" | "=>" | "(" | "<" | ".")
+			|| matches!(current, "|" | "&" | "." | "=>" | "extends" | "=" | ">")
+	}
+
+	fn enumeration(&mut self, start: usize, keyword: usize) -> usize {
+		let name = self.text(keyword + 1).to_string();
+		let open = keyword + 2;
+		if !self.is(open, "{") {
+			return keyword + 1;
+		}
+		let close = self.matching[open];
+
+		let mut code = format!("var {name}; (function ({name}) {{");
+		let mut next_value: Option<String> = Some("0".to_string());
+		let mut index = open + 1;
+		while index < close {
+			let member = match self.tokens[index].kind {
+				Kind::Str => self.text(index).to_string(),
+				_ => format!("\"{}\"", self.text(index)),
+			};
+			index += 1;
+
+			let mut initializer = None;
+			if self.is(index, "=") {
+				let value_start = index + 1;
+				index = value_start;
+				while index < close && !self.is(index, ",") {
+					index = if self.is(index, "(") || self.is(index, "[") || self.is(index, "{") { self.after_close(index) } else { index + 1 };
+				}
+				if index == value_start {
+					return self.fail(value_start, &format!("Expected a value for the enum member {}", member));
+				}
+				initializer = Some(self.src[self.tokens[value_start].start..self.tokens[index - 1].end].to_string());
+			}
+			if self.is(index, ",") {
+				index += 1;
+			}
+
+			let value = initializer.or(next_value.clone()).unwrap_or_else(|| "undefined".to_string());
+			if value.starts_with('"') || value.starts_with('\'') || value.starts_with('`') {
+				code.push_str(&format!(" {name}[{member}] = {value};"));
+				next_value = None;
+			} else {
+				code.push_str(&format!(" {name}[{name}[{member}] = {value}] = {member};"));
+				next_value = Some(match value.parse::<f64>() {
+					Ok(number) => (number + 1.0).to_string(),
+					Err(_) => format!("{name}[{member}] + 1"),
+				});
+			}
+		}
+		code.push_str(&format!(" }})({name} || ({name} = {{}}));"));
+
+		self.edits.push(Edit {
+			start: self.tokens[start].start,
+			end: self.tokens[close].end,
+			replacement: Some(code),
+		});
+		close + 1
+	}
+
+	fn word(&mut self, index: usize, text: &str) -> usize {
+		match text {
+			"function" => {
+				let mut next = index + 1;
+				if self.is(next, "*") {
+					next += 1;
+				}
+				if self.is_ident(next) {
+					self.declare(next);
+					next += 1;
+				}
+				if self.is(next, "<") {
+					if let Some(close) = self.match_angle(next) {
+						self.blank(next, close + 1);
+						next = close + 1;
+					}
+				}
+				if self.is(next, "(") {
+					let start = if index > 0 && self.is(index - 1, "async") { index - 1 } else { index };
+					let start = if start > 0 && self.is(start - 1, "export") { start - 1 } else { start };
+					self.params.insert(next, ParamsMark { signature_start: Some(start), constructor: false });
+				}
+				next
+			}
+			"class" => self.class_header(index),
+			"let" | "const" | "var" if self.is_ident(index + 1) || self.is(index + 1, "{") || self.is(index + 1, "[") => {
+				self.frame().declaring = true;
+				self.binding(index + 1)
+			}
+			"catch" if self.is(index + 1, "(") => {
+				self.params.insert(index + 1, ParamsMark::default());
+				index + 1
+			}
+			"as" | "satisfies" if self.previous_ends_expression(index) && !self.tokens[index].newline_before => {
+				let end = self.parse_type(index + 1);
+				self.blank(index, end);
+				end
+			}
+			_ => {
+				// Object literal methods: `{ name(args): Type { ... } }`.
+				if self.scope() == Scope::Object && index > 0 && (self.is(index - 1, "{") || self.is(index - 1, ",")) {
+					let mut name = index;
+					while matches!(self.text(name), "async" | "get" | "set" | "*") && !self.is(name + 1, "(") && !self.is(name + 1, ":") {
+						name += 1;
+					}
+					let mut next = name + 1;
+					if self.is(next, "<") {
+						if let Some(close) = self.match_angle(next) {
+							self.blank(next, close + 1);
+							next = close + 1;
+						}
+					}
+					if self.is(next, "(") {
+						self.params.insert(next, ParamsMark::default());
+						return next;
+					}
+				}
+				index + 1
+			}
+		}
+	}
+
+	/// Handles a binding after `let`/`const`/`var` or a `,` in a declaration list.
+	fn binding(&mut self, index: usize) -> usize {
+		let mut next = index;
+		if self.is_ident(next) {
+			self.declare(next);
+			next += 1;
+		} else if self.is(next, "{") || self.is(next, "[") {
+			self.declare_pattern(next);
+			next = self.matching[next] + 1;
+		} else {
+			return index;
+		}
+
+		if self.is(next, "!") {
+			self.blank(next, next + 1);
+			next += 1;
+		}
+		if self.is(next, ":") {
+			let end = self.parse_type(next + 1);
+			self.blank(next, end);
+			next = end;
+		}
+		next
+	}
+
+	fn class_header(&mut self, index: usize) -> usize {
+		let mut next = index + 1;
+		let mut derived = false;
+		if self.is_ident(next) && !matches!(self.text(next), "extends" | "implements") {
+			self.declare(next);
+			next += 1;
+		}
+		if self.is(next, "<") {
+			if let Some(close) = self.match_angle(next) {
+				self.blank(next, close + 1);
+				next = close + 1;
+			}
+		}
+
+		while !self.is(next, "{") && self.tokens[next].kind != Kind::End {
+			match self.text(next) {
+				"extends" => {
+					derived = true;
+					next += 1;
+				}
+				"implements" => {
+					let mut end = next + 1;
+					while !self.is(end, "{") && self.tokens[end].kind != Kind::End {
+						end += 1;
+					}
+					self.blank(next, end);
+					next = end;
+				}
+				"<" => {
+					match self.match_angle(next) {
+						Some(close) => {
+							self.blank(next, close + 1);
+							next = close + 1;
+						}
+						None => next += 1,
+					}
+				}
+				"(" | "[" => next = self.matching[next] + 1,
+				_ => next += 1,
+			}
+		}
+
+		self.classes.insert(next, derived);
+		// Let the main loop walk the `extends` expression normally.
+		index + 1
+	}
+
+	fn at_member_start(&self, index: usize) -> bool {
+		if matches!(self.text(index), ";" | "}") {
+			return false;
+		}
+		index > 0 && ((self.tokens[index].newline_before && !self.continues_line(index)) || matches!(self.text(index - 1), "{" | ";" | "}"))
+	}
+
+	fn is_modifier_use(&self, index: usize) -> bool {
+		// `readonly() {}` or `public = 1` use the word as a member name instead.
+		!matches!(self.text(index + 1), "(" | "=" | ":" | ";" | "?" | "!" | "<") && !self.tokens[index + 1].newline_before
+	}
+
+	fn member(&mut self, start: usize) -> usize {
+		let mut index = start;
+
+		loop {
+			match self.text(index) {
+				"public" | "private" | "protected" | "readonly" | "override" if self.is_modifier_use(index) => {
+					self.blank(index, index + 1);
+					index += 1;
+				}
+				"declare" | "abstract" if self.is_modifier_use(index) => {
+					let end = self.member_end(index);
+					self.blank(start, end);
+					return end;
+				}
+				"static" | "async" | "get" | "set" | "accessor" if self.is_modifier_use(index) => index += 1,
+				"*" => index += 1,
+				_ => break,
+			}
+		}
+
+		// Index signature: `[key: string]: Type;`
+		if self.is(index, "[") && self.is_ident(index + 1) && self.is(index + 2, ":") {
+			let end = self.member_end(index);
+			self.blank(start, end);
+			return end;
+		}
+
+		let name = index;
+		match self.text(index) {
+			// A static initialization block.
+			"{" => return index,
+			"[" => index = self.matching[index] + 1,
+			_ => index += 1,
+		}
+
+		if self.is(index, "?") || self.is(index, "!") {
+			self.blank(index, index + 1);
+			index += 1;
+		}
+		if self.is(index, "<") {
+			if let Some(close) = self.match_angle(index) {
+				self.blank(index, close + 1);
+				index = close + 1;
+			}
+		}
+		if self.is(index, ":") {
+			let end = self.parse_type(index + 1);
+			self.blank(index, end);
+			index = end;
+		}
+		if self.is(index, "(") {
+			self.params.insert(index, ParamsMark {
+				signature_start: Some(start),
+				constructor: self.text(name) == "constructor",
+			});
+		}
+		// Computed names go through the main loop, the marks and blanks above still apply.
+		if self.is(name, "[") { name } else { index }
+	}
+
+	fn member_end(&mut self, index: usize) -> usize {
+		let mut end = index + 1;
+		while self.tokens[end].kind != Kind::End && !self.is(end, "}") {
+			if self.is(end, ";") {
+				return end + 1;
+			}
+			if self.tokens[end].newline_before && !self.continues_line(end) {
+				return end;
+			}
+			end = if self.is(end, "(") || self.is(end, "[") || self.is(end, "{") { self.after_close(end) } else { end + 1 };
+		}
+		end
+	}
+
+	fn open_paren(&mut self, index: usize) -> usize {
+		let mark = self.params.remove(&index).or_else(|| self.is_arrow_parameters(index).then(ParamsMark::default));
+
+		match mark {
+			Some(mark) => {
+				let mut frame = Frame::new(Scope::Params);
+				frame.signature_start = mark.signature_start;
+				if mark.constructor {
+					frame.properties = Some(Vec::new());
+					frame.derived = self.enclosing_class_is_derived();
+				}
+				self.stack.push(frame);
+				self.parameter(index + 1)
+			}
+			None => {
+				self.stack.push(Frame::new(Scope::Paren));
+				index + 1
+			}
+		}
+	}
+
+	fn enclosing_class_is_derived(&self) -> bool {
+		self.stack.iter().rev().find(|frame| frame.scope == Scope::Class).is_some_and(|frame| frame.derived)
+	}
+
+	fn is_arrow_parameters(&self, index: usize) -> bool {
+		let close = self.matching[index];
+		if self.is(close + 1, "=>") {
+			return true;
+		}
+		// `(args): Type => body`, but not the `: alternative` of a conditional expression.
+		if self.is(close + 1, ":") && self.stack.last().unwrap().ternaries == 0 {
+			let end = self.parse_type(close + 2);
+			return self.is(end, "=>");
+		}
+		false
+	}
+
+	/// Handles the start of a parameter: modifiers, `?` and its type annotation.
+	fn parameter(&mut self, index: usize) -> usize {
+		let mut next = index;
+
+		if self.is(next, "this") && self.is(next + 1, ":") {
+			let mut end = self.parse_type(next + 2);
+			if self.is(end, ",") {
+				end += 1;
+			}
+			self.blank(next, end);
+			return self.parameter(end);
+		}
+
+		while matches!(self.text(next), "public" | "private" | "protected" | "readonly" | "override")
+			&& (self.is_ident(next + 1) || self.is(next + 1, "{") || self.is(next + 1, "["))
+		{
+			self.blank(next, next + 1);
+			next += 1;
+			if self.is_ident(next) && !matches!(self.text(next), "public" | "private" | "protected" | "readonly" | "override") {
+				let name = self.text(next).to_string();
+				if let Some(properties) = self.frame().properties.as_mut() {
+					if !properties.contains(&name) {
+						properties.push(name);
+					}
+				}
+			}
+		}
+
+		if self.is(next, "...") {
+			next += 1;
+		}
+		if self.is_ident(next) {
+			self.declare(next);
+			next += 1;
+		} else if self.is(next, "{") || self.is(next, "[") {
+			self.declare_pattern(next);
+			next = self.matching[next] + 1;
+		} else {
+			return next;
+		}
+
+		if self.is(next, "?") {
+			self.blank(next, next + 1);
+			next += 1;
+		}
+		if self.is(next, ":") {
+			let end = self.parse_type(next + 1);
+			self.blank(next, end);
+			next = end;
+		}
+		next
+	}
+
+	fn close_paren(&mut self, index: usize) -> usize {
+		let mut frame = if self.stack.len() > 1 { self.stack.pop().unwrap() } else { Frame::new(Scope::Paren) };
+		// Parameters and `for (let ...)` bindings are visible in the body that follows.
+		self.pending_names = std::mem::take(&mut frame.names);
+		if frame.scope != Scope::Params {
+			return index + 1;
+		}
+
+		let mut next = index + 1;
+		if self.is(next, ":") {
+			let end = self.parse_type(next + 1);
+			self.blank(next, end);
+			next = end;
+		}
+
+		// A signature without a body is an overload or an abstract member.
+		if let Some(start) = frame.signature_start {
+			let bodiless = self.is(next, ";") || self.is(next, "}") || self.tokens[next].kind == Kind::End
+				|| (self.tokens[next].newline_before && !self.is(next, "{"));
+			if bodiless {
+				let end = if self.is(next, ";") { next + 1 } else { next };
+				self.blank(start, end);
+				return end;
+			}
+		}
+
+		if let Some(properties) = frame.properties {
+			if !properties.is_empty() && self.is(next, "{") {
+				self.constructor_bodies.insert(next, (properties, frame.derived));
+			}
+		}
+		next
+	}
+
+	fn open_brace(&mut self, index: usize) -> usize {
+		if let Some(derived) = self.classes.remove(&index) {
+			let mut frame = Frame::new(Scope::Class);
+			frame.derived = derived;
+			self.stack.push(frame);
+			return index + 1;
+		}
+
+		if let Some((properties, derived)) = self.constructor_bodies.remove(&index) {
+			let assignments = properties.iter().map(|p| format!(" this.{p} = {p};")).collect::<String>();
+			let close = self.matching[index];
+			let anchor = if derived { self.super_call_end(index, close) } else { None };
+			let position = match anchor {
+				Some(token) => self.tokens[token].end,
+				None => self.tokens[index].end,
+			};
+			self.edits.push(Edit { start: position, end: position, replacement: Some(assignments) });
+		}
+
+		let previous = if index > 0 { self.text(index - 1) } else { ";" };
+		let object = index > 0 && self.tokens[index - 1].kind == Kind::Punct && matches!(
+			previous,
+			"=" | "(" | "," | ":" | "[" | "?" | "||" | "&&" | "??" | "..." | "+" | "-" | "*" | "!" | "==" | "===" | "!=" | "!==" | "<" | ">"
+		) || matches!(previous, "return" | "yield" | "await" | "typeof" | "in" | "of");
+
+		let mut frame = Frame::new(if object { Scope::Object } else { Scope::Block });
+		if !object {
+			frame.names = std::mem::take(&mut self.pending_names);
+		}
+		self.stack.push(frame);
+		index + 1
+	}
+
+	fn super_call_end(&self, open: usize, close: usize) -> Option<usize> {
+		let mut index = open + 1;
+		while index < close {
+			if self.is(index, "super") && self.is(index + 1, "(") {
+				let end = self.matching[index + 1];
+				return Some(if self.is(end + 1, ";") { end + 1 } else { end });
+			}
+			index += 1;
+		}
+		None
+	}
+
+	fn angle(&mut self, index: usize) -> usize {
+		// Explicit type arguments on a call: `create<Foo>(...)`.
+		if self.previous_ends_expression(index) {
+			if let Some(close) = self.match_angle(index) {
+				if self.is(close + 1, "(") || self.tokens[close + 1].kind == Kind::Template {
+					self.blank(index, close + 1);
+					return close + 1;
+				}
+			}
+			return index + 1;
+		}
+
+		// Generic arrow functions `<T>(value: T) => value` and `<Type>value` assertions.
+		if let Some(close) = self.match_angle(index) {
+			self.blank(index, close + 1);
+			return close + 1;
+		}
+		index + 1
+	}
+
+	/// Returns the index of the `>` closing the type argument list opened at `index`.
+	fn match_angle(&self, index: usize) -> Option<usize> {
+		let mut depth = 0;
+		let mut next = index;
+		while self.tokens[next].kind != Kind::End {
+			match self.text(next) {
+				"<" => depth += 1,
+				">" => {
+					depth -= 1;
+					if depth == 0 {
+						return Some(next);
+					}
+				}
+				"(" | "[" | "{" => {
+					next = self.matching[next];
+				}
+				";" | "?" | ":" | "==" | "===" | "!=" | "!==" | "&&" | "||" | "+" | "*" | "/" | "%" | "++" | "--" | "+=" | "-=" | "}" | ")" | "]" => return None,
+				_ => {}
+			}
+			next += 1;
+		}
+		None
+	}
+
+	/// Returns the token index right after the type starting at `index`.
+	fn parse_type(&self, index: usize) -> usize {
+		let mut next = index;
+		if self.is(next, "|") || self.is(next, "&") {
+			next += 1;
+		}
+		next = self.type_operand(next);
+		while self.is(next, "|") || self.is(next, "&") {
+			next = self.type_operand(next + 1);
+		}
+
+		// Conditional types: `T extends U ? X : Y`.
+		if self.is(next, "extends") {
+			let check = self.type_operand(next + 1);
+			if self.is(check, "?") {
+				let consequent = self.parse_type(check + 1);
+				if self.is(consequent, ":") {
+					return self.parse_type(consequent + 1);
+				}
+			}
+		}
+		next
+	}
+
+	fn type_operand(&self, index: usize) -> usize {
+		let mut next = index;
+		if matches!(self.text(next), "keyof" | "typeof" | "readonly" | "unique" | "infer" | "asserts" | "new")
+			&& !self.tokens[next + 1].newline_before
+			&& !matches!(self.text(next + 1), "|" | "&" | ")" | "]" | "," | ";" | "=" | "}" | ">" | "=>")
+		{
+			next += 1;
+		}
+
+		match self.tokens[next].kind {
+			Kind::End => return next,
+			Kind::Ident | Kind::Str | Kind::Number | Kind::Template => {
+				next += 1;
+				while self.is(next, ".") && self.is_ident(next + 1) {
+					next += 2;
+				}
+				if self.is(next, "<") && !self.tokens[next].newline_before {
+					if let Some(close) = self.match_angle(next) {
+						next = close + 1;
+					}
+				}
+				// Type predicates: `value is string`.
+				if self.is(next, "is") && !self.tokens[next].newline_before {
+					next = self.parse_type(next + 1);
+				}
+			}
+			Kind::Punct => match self.text(next) {
+				"-" => next += 2,
+				"<" => {
+					// Generic function types: `<T>(value: T) => T`.
+					if let Some(close) = self.match_angle(next) {
+						return self.type_operand(close + 1);
+					}
+					return next;
+				}
+				"(" => {
+					next = self.matching[next] + 1;
+					if self.is(next, "=>") {
+						return self.parse_type(next + 1);
+					}
+				}
+				"{" | "[" => next = self.matching[next] + 1,
+				_ => return next,
+			},
+			Kind::Regex => return next,
+		}
+
+		// Array and indexed access types.
+		while self.is(next, "[") && !self.tokens[next].newline_before {
+			next = self.matching[next] + 1;
+		}
+		next
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rquickjs::{CatchResultExt, Context, Runtime};
+
+	use super::{instrument, transpile};
+
+	/// Evaluates `script` and returns its completion value, or the error it threw.
+	fn run(script: &str) -> Result<String, String> {
+		let runtime = Runtime::new().unwrap();
+		let context = Context::full(&runtime).unwrap();
+		context.with(|ctx| {
+			ctx.globals().set("__line", rquickjs::Function::new(ctx.clone(), |_: u32| ()).unwrap()).unwrap();
+			ctx.eval::<rquickjs::Coerced<String>, _>(script)
+				.catch(&ctx)
+				.map(|value| value.0)
+				.map_err(|err| format!("{}\n--- output ---\n{}", err, script))
+		})
+	}
+
+	/// Transpiles and instruments `source`, checks both run and give `expected`.
+	fn check(source: &str, expected: &str) {
+		let transpiled = transpile(source).unwrap();
+		assert_eq!(run(&transpiled).as_deref(), Ok(expected), "transpiled");
+
+		let instrumented = instrument(source, &|line, _| format!("__line({});", line)).unwrap();
+		assert_eq!(run(&instrumented).as_deref(), Ok(expected), "instrumented");
+	}
+
+	#[test]
+	fn this_parameter() {
+		check("function scale(this: { factor: number }, value: number): number { return value * this.factor; }\n\
+			scale.call({ factor: 2 }, 21)", "42");
+		check("function name(this: Window) { return 'window'; }\nname()", "window");
+		check("function pair(this: any, a: string, b: string) { return a + b; }\npair('a', 'b')", "ab");
+	}
+
+	#[test]
+	fn generics() {
+		check("function first<T>(items: T[]): T { return items[0]; }\nfirst<number>([7, 8])", "7");
+		check("class Box<T extends object = {}> { value: T; constructor(value: T) { this.value = value; } }\n\
+			new Box<{ a: number }>({ a: 3 }).value.a", "3");
+		check("const map: Map<string, Array<number>> = new Map();\nmap.set('a', [1]);\nmap.get('a')!.length", "1");
+	}
+
+	#[test]
+	fn arrow_types() {
+		check("const apply = (f: (x: number) => number, x: number): number => f(x);\napply((x) => x + 1, 1)", "2");
+		check("let callback: (() => void) | null = null;\ncallback === null", "true");
+		check("const add = <T,>(a: T[], b: T[]): T[] => a.concat(b);\nadd([1], [2]).length", "2");
+	}
+
+	#[test]
+	fn as_expressions() {
+		check("const value = '5' as unknown as string;\nvalue", "5");
+		check("const items = [1, 2] as const;\n(items as number[]).length", "2");
+		check("const object = {} as Record<string, number>;\nobject.a = 1;\nobject.a", "1");
+	}
+
+	#[test]
+	fn enums() {
+		check("enum Color { Red, Green = 4, Blue }\nColor.Blue + Color[4]", "5Green");
+		check("enum Key { Up = 'up', Down = 'down' }\nKey.Down", "down");
+		check("const enum Flag { A = 1 << 0, B = 1 << 1 }\nFlag.A | Flag.B", "3");
+	}
+
+	#[test]
+	fn optional_parameters() {
+		check("function greet(name?: string, greeting: string = 'hi'): string { return greeting + (name ?? ''); }\ngreet()", "hi");
+		check("class Point { constructor(public x: number, private y?: number) {} }\nnew Point(1).x", "1");
+		check("const count = (...rest: number[]): number => rest.length;\ncount(1, 2, 3)", "3");
+	}
+
+	#[test]
+	fn incomplete_declarations() {
+		for source in ["interface Foo", "interface Foo extends Bar<T>", "enum E { A = }", "enum E { A = , B }"] {
+			assert!(transpile(source).is_err(), "transpiled {}", source);
+			assert!(instrument(source, &|line, _| format!("__line({});", line)).is_err(), "instrumented {}", source);
+		}
+	}
+}
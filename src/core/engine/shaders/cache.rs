@@ -0,0 +1,131 @@
+use std::{ffi::CStr, fs, path::PathBuf};
+
+use crate::core::engine::objects::gpu::Program;
+use super::{error::ShaderStage, permutation::ShaderFeatures, preprocessor::Preprocessed};
+
+/// Where linked program binaries are kept between launches.
+pub const CACHE_DIRECTORY: &str = "cache/shaders";
+
+/// A program binary on disk, named `<program>-<key>.bin`. `program` stands for the files and
+/// features the program is built from, `key` for its preprocessed sources and the driver, so
+/// any edit or driver update misses the cache and replaces the old binary of the program.
+pub struct CacheEntry {
+    program: u64,
+    key: u64,
+}
+
+impl CacheEntry {
+    pub fn new(paths: &[(ShaderStage, &str)], features: ShaderFeatures, sources: &[(ShaderStage, Preprocessed)]) -> Self {
+        let mut program = Fnv::new();
+        for (stage, path) in paths {
+            program.write(stage.to_string().as_bytes());
+            program.write(path.as_bytes());
+        }
+        program.write(features.to_string().as_bytes());
+
+        let mut key = Fnv::new();
+        for name in [gl::VENDOR, gl::RENDERER, gl::VERSION] {
+            key.write(gl_string(name).as_bytes());
+        }
+        for (stage, source) in sources {
+            key.write(stage.to_string().as_bytes());
+            key.write(source.source.as_bytes());
+        }
+
+        CacheEntry { program: program.finish(), key: key.finish() }
+    }
+
+    fn path(&self) -> PathBuf {
+        PathBuf::from(CACHE_DIRECTORY).join(format!("{:016x}-{:016x}.bin", self.program, self.key))
+    }
+
+    /// A program linked from the cached binary, `None` when there is none or the driver
+    /// rejects it (the file is removed then).
+    pub fn load(&self) -> Option<Program> {
+        let path = self.path();
+        let data = fs::read(&path).ok()?;
+        if data.len() < 4 {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        let (format, binary) = data.split_at(4);
+        let format = u32::from_le_bytes(format.try_into().unwrap());
+
+        let program = Program::new();
+        let mut success = gl::FALSE as i32;
+        unsafe {
+            gl::ProgramBinary(program.id(), format, binary.as_ptr() as *const _, binary.len() as i32);
+            gl::GetProgramiv(program.id(), gl::LINK_STATUS, &mut success);
+        }
+        if success != gl::TRUE as i32 {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(program)
+    }
+
+    /// Writes the binary of a freshly linked program and removes the stale ones of the same program.
+    pub fn store(&self, program: u32) -> std::io::Result<()> {
+        let mut formats = 0;
+        unsafe { gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats) };
+        if formats == 0 {
+            return Ok(());
+        }
+
+        let mut length = 0;
+        unsafe { gl::GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut length) };
+        if length <= 0 {
+            return Ok(());
+        }
+        let mut binary = vec![0u8; length as usize];
+        let mut format = 0;
+        let mut written = 0;
+        unsafe { gl::GetProgramBinary(program, length, &mut written, &mut format, binary.as_mut_ptr() as *mut _) };
+        binary.truncate(written.max(0) as usize);
+
+        fs::create_dir_all(CACHE_DIRECTORY)?;
+        let prefix = format!("{:016x}-", self.program);
+        for entry in fs::read_dir(CACHE_DIRECTORY)?.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+
+        let mut data = format.to_le_bytes().to_vec();
+        data.extend(binary);
+        fs::write(self.path(), data)
+    }
+}
+
+fn gl_string(name: u32) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(string as *const _).to_string_lossy().to_string()
+    }
+}
+
+/// FNV-1a, unlike `DefaultHasher` it hashes the same on every build.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+        // Separates consecutive writes, "ab" + "c" doesn't hash like "a" + "bc".
+        self.0 ^= 0xff;
+        self.0 = self.0.wrapping_mul(0x100000001b3);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+
+use super::preprocessor::Preprocessed;
+
+/// Number of source lines shown before and after the line an error points at.
+const CONTEXT_LINES: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShaderStage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    /// `glCreateShader` type of the stage.
+    pub fn gl_kind(self) -> u32 {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::TessControl => write!(f, "tessellation control"),
+            ShaderStage::TessEvaluation => write!(f, "tessellation evaluation"),
+            ShaderStage::Geometry => write!(f, "geometry"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+            ShaderStage::Compute => write!(f, "compute"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShaderErrorKind {
+    Read,
+    /// A bad `#include`.
+    Preprocess,
+    Compile(ShaderStage),
+    Link,
+}
+
+/// A shader that failed to read, compile or link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderError {
+    pub kind: ShaderErrorKind,
+    /// File the failing source was read from, every file of the program for link errors.
+    pub path: String,
+    /// Complete info log of the driver.
+    pub log: String,
+    /// The log with `path:line` locations, each followed by the source lines it points at.
+    pub annotated: String,
+}
+
+impl ShaderError {
+    pub fn compile(stage: ShaderStage, log: &str, source: &Preprocessed) -> Self {
+        ShaderError {
+            kind: ShaderErrorKind::Compile(stage),
+            path: source.files.first().cloned().unwrap_or_default(),
+            log: log.to_string(),
+            annotated: annotate(log, source),
+        }
+    }
+
+    pub fn preprocess(path: &str, line: u32, message: &str) -> Self {
+        ShaderError {
+            kind: ShaderErrorKind::Preprocess,
+            path: path.to_string(),
+            log: message.to_string(),
+            annotated: format!("{}:{}: {}", path, line, message),
+        }
+    }
+
+    /// Notes the `#include` that led to the file this error happened in.
+    pub fn included_from(mut self, path: &str, line: u32) -> Self {
+        self.annotated.push_str(&format!("\n    included from {}:{}", path, line));
+        self
+    }
+
+    pub fn link(paths: &[&str], log: &str) -> Self {
+        ShaderError {
+            kind: ShaderErrorKind::Link,
+            path: paths.join(", "),
+            log: log.to_string(),
+            annotated: log.to_string(),
+        }
+    }
+
+    pub fn read(path: &str, error: &std::io::Error) -> Self {
+        ShaderError {
+            kind: ShaderErrorKind::Read,
+            path: path.to_string(),
+            log: error.to_string(),
+            annotated: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ShaderErrorKind::Read => writeln!(f, "Could not read shader \"{}\":", self.path)?,
+            ShaderErrorKind::Preprocess => writeln!(f, "Failed to preprocess shader \"{}\":", self.path)?,
+            ShaderErrorKind::Compile(stage) => writeln!(f, "Failed to compile {} shader \"{}\":", stage, self.path)?,
+            ShaderErrorKind::Link => writeln!(f, "Failed to link \"{}\":", self.path)?,
+        }
+        write!(f, "{}", self.annotated)
+    }
+}
+
+/// Rewrites the locations of `log` as `file:line` of the file the line was included from
+/// and puts the lines around it below.
+fn annotate(log: &str, source: &Preprocessed) -> String {
+    let mut annotated = Vec::new();
+
+    for entry in log.lines() {
+        let Some((number, message)) = info_log_location(entry) else {
+            annotated.push(entry.to_string());
+            continue;
+        };
+        let Some((file, line)) = source.origin(number) else {
+            annotated.push(format!("<injected>:{}: {}", number, message));
+            continue;
+        };
+        annotated.push(format!("{}:{}: {}", file, line, message));
+
+        // Only lines of the same file are shown around it.
+        let context = (number.saturating_sub(CONTEXT_LINES as u32).max(1)..=number + CONTEXT_LINES as u32)
+            .filter_map(|current| {
+                let (other, original) = source.origin(current)?;
+                (other == file).then(|| (current == number, original, source.line(current).unwrap_or_default()))
+            })
+            .collect::<Vec<_>>();
+        let width = context.iter().map(|(_, original, _)| original.to_string().len()).max().unwrap_or(1);
+        for (current, original, text) in context {
+            let marker = if current { ">" } else { " " };
+            annotated.push(format!("{} {:>width$} | {}", marker, original, text));
+        }
+    }
+
+    annotated.join("\n")
+}
+
+/// Driver info logs point at lines as `0:12(5): error` (Mesa), `0(12) : error` (NVIDIA) or
+/// `ERROR: 0:12: ...` (AMD, Intel), where `0` is the source string. Returns the line and
+/// the message that follows.
+pub fn info_log_location(line: &str) -> Option<(u32, String)> {
+    let trimmed = line.trim_start();
+    let (severity, rest) = match trimmed.split_once(": ") {
+        Some((severity @ ("ERROR" | "WARNING"), rest)) => (Some(severity), rest),
+        _ => (None, trimmed),
+    };
+
+    let rest = rest.strip_prefix(|c: char| c.is_ascii_digit())?.trim_start_matches(|c: char| c.is_ascii_digit());
+    let (number, rest) = if let Some(rest) = rest.strip_prefix('(') {
+        let (number, rest) = rest.split_once(')')?;
+        (number, rest)
+    } else {
+        let rest = rest.strip_prefix(':')?;
+        let end = rest.find(|c: char| !c.is_ascii_digit())?;
+        let (number, rest) = rest.split_at(end);
+        // Mesa follows the line with the column.
+        let rest = match rest.strip_prefix('(') {
+            Some(column) => column.split_once(')').map_or(rest, |(_, rest)| rest),
+            None => rest,
+        };
+        (number, rest)
+    };
+    let number = number.parse().ok()?;
+
+    let message = rest.trim_start_matches([' ', ':']).trim();
+    // Keep the severity AMD and Intel put in front of the location.
+    let message = match severity {
+        Some(severity) => format!("{}: {}", severity.to_lowercase(), message),
+        None => message.to_string(),
+    };
+    Some((number, message))
+}
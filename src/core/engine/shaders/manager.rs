@@ -1,175 +1,551 @@
-use core::str;
-use std::{ffi::CString, fs, path::Path};
+use std::{collections::{HashMap, HashSet}, ffi::CString, fs, sync::atomic::{AtomicU64, Ordering}, time::SystemTime};
 use gl::types::{GLchar, GLint};
-use nalgebra::Matrix4;
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
 
-use crate::{log, utils::log::manager::{LogLevel, Logger}};
+use crate::{core::engine::objects::gpu::Program, log, utils::log::manager::{LogLevel, Logger}};
+use super::{cache::CacheEntry, error::{ShaderError, ShaderStage}, permutation::ShaderFeatures, preprocessor::{self, Preprocessed}, reflection::{self, ProgramReflection}, uniform::UniformValue};
 
+/// Serial of the next program built, by any shader.
+static NEXT_BUILD: AtomicU64 = AtomicU64::new(1);
+
+/// Files of the stages of a program. Compute programs only have `compute`, the others at
+/// least `vertex` and `fragment`.
+#[derive(Clone, Default)]
 pub struct ShaderSources {
     pub vertex: String,
     pub fragment: String,
+    pub geometry: Option<String>,
+    pub tess_control: Option<String>,
+    pub tess_evaluation: Option<String>,
+    pub compute: Option<String>,
+}
+
+impl ShaderSources {
+    /// A standalone compute program.
+    pub fn compute(path: &str) -> Self {
+        ShaderSources { compute: Some(path.to_string()), ..Default::default() }
+    }
+
+    /// Stages the program is linked from, in pipeline order.
+    pub fn stages(&self) -> Vec<(ShaderStage, &str)> {
+        if let Some(compute) = &self.compute {
+            return vec![(ShaderStage::Compute, compute.as_str())];
+        }
+
+        [
+            (ShaderStage::Vertex, Some(&self.vertex)),
+            (ShaderStage::TessControl, self.tess_control.as_ref()),
+            (ShaderStage::TessEvaluation, self.tess_evaluation.as_ref()),
+            (ShaderStage::Geometry, self.geometry.as_ref()),
+            (ShaderStage::Fragment, Some(&self.fragment)),
+        ]
+        .into_iter()
+        .filter_map(|(stage, path)| path.map(|path| (stage, path.as_str())))
+        .collect()
+    }
+
+    fn set(&mut self, stage: ShaderStage, source: String) {
+        match stage {
+            ShaderStage::Vertex => self.vertex = source,
+            ShaderStage::TessControl => self.tess_control = Some(source),
+            ShaderStage::TessEvaluation => self.tess_evaluation = Some(source),
+            ShaderStage::Geometry => self.geometry = Some(source),
+            ShaderStage::Fragment => self.fragment = source,
+            ShaderStage::Compute => self.compute = Some(source),
+        }
+    }
 }
 
 pub struct Shader {
+    /// Program of the variant without features.
     pub program_id: u32,
+    /// Preprocessed sources of `program_id`.
     pub content: ShaderSources,
+    /// Files `content` was read from.
+    pub paths: ShaderSources,
+    /// `#define`s every variant is compiled with on top of its features, applied by the next `setup`.
+    pub defines: Vec<(String, String)>,
+    /// Whether linked programs are loaded from and saved to the binary cache.
+    pub use_cache: bool,
+    /// Programs of the feature variants built so far, 0 for the ones that failed to.
+    variants: HashMap<ShaderFeatures, u32>,
+    /// Program bound by the last `use_program` or `use_variant`, the uniform setters work on it.
+    active: u32,
+    /// Every file read for the sources (includes too) and its modification time at that point,
+    /// `hot_reload` compares against them.
+    modified: Vec<(String, Option<SystemTime>)>,
+    /// Every program built and still in use by its id, deleted when removed.
+    programs: HashMap<u32, Program>,
+    /// Active uniforms, attributes and blocks of every program, the setters look locations up here.
+    reflections: HashMap<u32, ProgramReflection>,
+    /// Serial of every program's build, GL reuses the ids of deleted programs.
+    builds: HashMap<u32, u64>,
+    /// Binding points of uniform blocks, given to every program that's built.
+    block_bindings: HashMap<String, u32>,
+    /// Binding points of shader storage blocks, the same way.
+    storage_bindings: HashMap<String, u32>,
+    /// Uniforms a setter already warned about, so a missing uniform isn't logged every frame.
+    warned: HashSet<String>,
     logger: Logger
 }
 
 impl Shader {
-    fn read_file_to_string(path: String) -> String {
-        fs::read_to_string(&path)
-            .unwrap_or_else(|_| {
-                log!(Logger::new("debug/shader.log"), LogLevel::Error, "Could not read shader file at path: {}", &path);
-                panic!("[S:RFTS] Could not read vertex and/or fragment shader's file. Possibly doesn't exist at all.")
-            })
+    fn modified_time(path: &str) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
     }
 
     pub fn new(sources: ShaderSources) -> Self {
-        Shader {
+        let mut shader = Shader {
             program_id: 0,
-            content: ShaderSources {
-                vertex: Shader::read_file_to_string(sources.vertex),
-                fragment: Shader::read_file_to_string(sources.fragment),
-            },
+            content: ShaderSources::default(),
+            paths: sources,
+            defines: Vec::new(),
+            use_cache: true,
+            variants: HashMap::new(),
+            active: 0,
+            modified: Vec::new(),
+            programs: HashMap::new(),
+            reflections: HashMap::new(),
+            builds: HashMap::new(),
+            block_bindings: HashMap::new(),
+            storage_bindings: HashMap::new(),
+            warned: HashSet::new(),
             logger: Logger::new("debug/shader.log")
+        };
+
+        match shader.preprocess(ShaderFeatures::NONE) {
+            Ok(sources) => shader.watch(&sources),
+            Err(err) => {
+                log!(shader.logger, LogLevel::Error, "{}", err);
+                panic!("[S:RFTS] Could not read vertex and/or fragment shader's file. Possibly doesn't exist at all.")
+            }
         }
+        shader
     }
 
-    pub fn setup(&mut self) {
-        let vs = self.generate_and_link_vertex_program();
-        let fs = self.generate_and_link_fragment_program();
-        self.link_vs_and_fs_to_shader_program(vs, fs);
+    /// Recompiles the programs when one of their files changed on disk since it was last read.
+    /// Returns `Ok(true)` when new programs are in use.
+    pub fn hot_reload(&mut self) -> Result<bool, ShaderError> {
+        let mut changed = Vec::new();
+        for (path, modified) in self.modified.iter_mut() {
+            let now = Shader::modified_time(path);
+            if now != *modified {
+                *modified = now;
+                changed.push(path.clone());
+            }
+        }
+        if changed.is_empty() {
+            return Ok(false);
+        }
+
+        log!(self.logger, LogLevel::Info, "\"{}\" changed on disk, recompiling", changed.join("\", \""));
+        self.reload().map(|_| true)
     }
 
-    fn generate_and_link_vertex_program(&mut self) -> u32 {
-        unsafe {
-            let vertex_shader: u32 = gl::CreateShader(gl::VERTEX_SHADER);
-            let vertex_src = CString::new(self.content.vertex.as_bytes()).unwrap();
-            gl::ShaderSource(vertex_shader, 1, &vertex_src.as_ptr(), std::ptr::null());
-            gl::CompileShader(vertex_shader);
+    /// Reads the sources from disk again and swaps in freshly compiled programs. The current
+    /// programs stay in use when the files can't be read or fail to compile or link.
+    pub fn reload(&mut self) -> Result<(), ShaderError> {
+        self.setup()
+    }
 
-            let mut success = gl::FALSE as GLint;
-            let mut info_log = Vec::with_capacity(512);
-            gl::GetShaderiv(vertex_shader, gl::COMPILE_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                gl::GetShaderInfoLog(vertex_shader, 512, std::ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-                info_log.set_len(512);
+    /// Preprocesses, compiles and links the sources, then rebuilds every variant built so far.
+    /// A program is only replaced by one that linked, failures are logged and the first one returned.
+    pub fn setup(&mut self) -> Result<(), ShaderError> {
+        let result = match self.build(ShaderFeatures::NONE) {
+            Ok((program, sources)) => {
+                if self.active == self.program_id {
+                    self.active = program;
+                }
+                self.delete_program(self.program_id);
+                self.program_id = program;
+                self.watch(&sources);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        };
+
+        let features = self.variants.keys().copied().collect::<Vec<_>>();
+        let mut results = vec![result];
+        for features in features {
+            results.push(self.build(features).map(|(program, _)| {
+                let old = self.variants.insert(features, program).unwrap_or(0);
+                if self.active == old {
+                    self.active = program;
+                }
+                self.delete_program(old);
+            }));
+        }
+        self.warned.clear();
+
+        // Every failure was logged by `build`, the first one is returned.
+        results.into_iter().collect()
+    }
+
+    /// Program of the variant compiled with `features`, built the first time it's asked for.
+    pub fn variant(&mut self, features: ShaderFeatures) -> Result<u32, ShaderError> {
+        if features.is_empty() {
+            return Ok(self.program_id);
+        }
+        if let Some(program) = self.variants.get(&features) {
+            return Ok(*program);
+        }
 
-                let parent_dir = Path::new(&self.content.vertex).parent().map_or_else(
-                    || "Unknown directory".to_string(),
-                    |p| p.display().to_string()
-                );
+        let result = self.build(features);
+        // Failed variants are remembered as 0 so they aren't compiled again every frame.
+        self.variants.insert(features, result.as_ref().map_or(0, |(program, _)| *program));
+        result.map(|(program, _)| program)
+    }
+
+    /// Uses the variant compiled with `features`, or the one without any when it fails to build.
+    pub fn use_variant(&mut self, features: ShaderFeatures) -> u32 {
+        let program = match self.variant(features) {
+            Ok(program) if program != 0 => program,
+            _ => self.program_id,
+        };
+        self.active = program;
+        unsafe { gl::UseProgram(program) };
+        program
+    }
+
+    /// Reads the uniform block `name` of every program from the uniform buffer bound to `binding`,
+    /// programs built later included.
+    pub fn bind_uniform_block(&mut self, name: &str, binding: u32) {
+        self.block_bindings.insert(name.to_string(), binding);
+        let programs = self.reflections.keys().copied().collect::<Vec<_>>();
+        for program in programs {
+            self.apply_block_bindings(program);
+        }
+    }
+
+    /// Reflection of the program the uniform setters work on.
+    pub fn reflection(&self) -> Option<&ProgramReflection> {
+        self.reflections.get(&self.active_program())
+    }
 
-                log!(self.logger, LogLevel::Error, "ERROR::SHADER::VERTEX::COMPILATION_FAILED");
-                log!(self.logger, LogLevel::Error, "Shader Directory: {}", parent_dir);
-                log!(self.logger, LogLevel::Error, "Error: {}", String::from_utf8_lossy(&info_log));
-            } else {
-                log!(self.logger, LogLevel::Info, "Vertex shader compiled successfully.");
+    /// Reads the shader storage block `name` of every program from the storage buffer bound
+    /// to `binding`, programs built later included.
+    pub fn bind_storage_block(&mut self, name: &str, binding: u32) {
+        self.storage_bindings.insert(name.to_string(), binding);
+        let programs = self.reflections.keys().copied().collect::<Vec<_>>();
+        for program in programs {
+            self.apply_block_bindings(program);
+        }
+    }
+
+    fn apply_block_bindings(&mut self, program: u32) {
+        let Some(reflection) = self.reflections.get_mut(&program) else {
+            return;
+        };
+        for (name, binding) in self.block_bindings.iter() {
+            if let Some(block) = reflection.blocks.get_mut(name) {
+                unsafe { gl::UniformBlockBinding(program, block.index, *binding) };
+                block.binding = *binding;
+            }
+        }
+        for (name, binding) in self.storage_bindings.iter() {
+            if let Some(block) = reflection.storage_blocks.get_mut(name) {
+                unsafe { gl::ShaderStorageBlockBinding(program, block.index, *binding) };
+                block.binding = *binding;
             }
+        }
+    }
 
-            vertex_shader
+    /// Whether the program has tessellation stages, it must then be drawn with `gl::PATCHES`.
+    pub fn uses_tessellation(&self) -> bool {
+        self.paths.compute.is_none() && (self.paths.tess_control.is_some() || self.paths.tess_evaluation.is_some())
+    }
+
+    /// Local work group size the compute stage declared with `layout (local_size_x = ...) in`.
+    pub fn work_group_size(&self) -> [u32; 3] {
+        let mut size = [0; 3];
+        if self.paths.compute.is_some() {
+            unsafe { gl::GetProgramiv(self.active_program(), gl::COMPUTE_WORK_GROUP_SIZE, size.as_mut_ptr()) };
         }
+        size.map(|size| size as u32)
     }
 
-    fn generate_and_link_fragment_program(&mut self) -> u32 {
+    /// Runs the active compute program over `x * y * z` work groups. Results are only visible to
+    /// later commands after the matching `Shader::memory_barrier`.
+    pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        if self.paths.compute.is_none() {
+            log!(self.logger, LogLevel::Error, "Can't dispatch \"{}\", it has no compute stage", self.name());
+            return;
+        }
         unsafe {
-            let fragment_shader: u32 = gl::CreateShader(gl::FRAGMENT_SHADER);
-            let fragment_src = CString::new(self.content.fragment.as_bytes()).unwrap();
-            gl::ShaderSource(fragment_shader, 1, &fragment_src.as_ptr(), std::ptr::null());
-            gl::CompileShader(fragment_shader);
+            gl::UseProgram(self.active_program());
+            gl::DispatchCompute(x, y, z);
+        }
+    }
 
-            let mut success = gl::FALSE as GLint;
-            let mut info_log = Vec::with_capacity(512);
-            gl::GetShaderiv(fragment_shader, gl::COMPILE_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                gl::GetShaderInfoLog(fragment_shader, 512, std::ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-                info_log.set_len(512);
+    /// Runs enough work groups to cover `items` invocations along x, the shader has to skip
+    /// the ones past the end.
+    pub fn dispatch_for(&mut self, items: u32) {
+        let [local_x, _, _] = self.work_group_size();
+        self.dispatch(items.div_ceil(local_x.max(1)), 1, 1);
+    }
 
-                let parent_dir = Path::new(&self.content.fragment).parent().map_or_else(
-                    || "Unknown directory".to_string(),
-                    |p| p.display().to_string()
-                );
+    /// Makes the writes of dispatched shaders visible to the accesses in `barriers`,
+    /// `gl::SHADER_STORAGE_BARRIER_BIT`, `gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT`, `gl::COMMAND_BARRIER_BIT`...
+    pub fn memory_barrier(barriers: u32) {
+        unsafe { gl::MemoryBarrier(barriers) };
+    }
 
-                log!(self.logger, LogLevel::Error, "ERROR::SHADER::FRAGMENT::COMPILATION_FAILED");
-                log!(self.logger, LogLevel::Error, "Shader Directory: {}", parent_dir);
-                log!(self.logger, LogLevel::Error, "Error: {}", String::from_utf8_lossy(&info_log));
-            } else {
-                log!(self.logger, LogLevel::Info, "Fragment shader compiled successfully.");
+    /// File the program is named after in messages.
+    fn name(&self) -> &str {
+        self.paths.compute.as_deref().unwrap_or(&self.paths.fragment)
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        self.reflections.remove(&program);
+        self.builds.remove(&program);
+        self.programs.remove(&program);
+    }
+
+    fn preprocess(&self, features: ShaderFeatures) -> Result<Vec<(ShaderStage, Preprocessed)>, ShaderError> {
+        let mut defines = self.defines.clone();
+        defines.extend(features.defines());
+        self.paths.stages().into_iter()
+            .map(|(stage, path)| preprocessor::preprocess(path, &defines).map(|source| (stage, source)))
+            .collect()
+    }
+
+    /// Remembers the preprocessed base sources and watches every file they were read from.
+    fn watch(&mut self, sources: &[(ShaderStage, Preprocessed)]) {
+        let mut content = ShaderSources::default();
+        for (stage, source) in sources {
+            content.set(*stage, source.source.clone());
+        }
+        self.content = content;
+
+        let mut modified = Vec::new();
+        for path in sources.iter().flat_map(|(_, source)| source.files.iter()) {
+            if !modified.iter().any(|(watched, _)| watched == path) {
+                modified.push((path.clone(), Shader::modified_time(path)));
+            }
+        }
+        self.modified = modified;
+    }
+
+    /// Builds the program of a variant, failures are logged.
+    fn build(&mut self, features: ShaderFeatures) -> Result<(u32, Vec<(ShaderStage, Preprocessed)>), ShaderError> {
+        let result = self.preprocess(features).and_then(|sources| {
+            let entry = CacheEntry::new(&self.paths.stages(), features, &sources);
+            if self.use_cache {
+                if let Some(program) = entry.load() {
+                    log!(self.logger, LogLevel::Info, "Loaded the \"{}\" program of \"{}\" from the binary cache", features, self.name());
+                    return Ok((program, sources));
+                }
+            }
+
+            let mut shaders = Vec::new();
+            for (stage, source) in sources.iter() {
+                match self.compile_stage(*stage, source) {
+                    Ok(shader) => shaders.push(shader),
+                    Err(err) => {
+                        for shader in shaders {
+                            unsafe { gl::DeleteShader(shader) };
+                        }
+                        return Err(err);
+                    }
+                }
+            }
+            let program = self.link(&shaders)?;
+            if self.use_cache {
+                if let Err(err) = entry.store(program.id()) {
+                    log!(self.logger, LogLevel::Warning, "Could not cache the program of \"{}\": {}", self.name(), err);
+                }
             }
+            Ok((program, sources))
+        });
 
-            fragment_shader
+        let result = result.map(|(program, sources)| {
+            let id = program.id();
+            program.label(&format!("{} ({})", self.name(), features));
+            let reflection = ProgramReflection::new(id);
+            let mut uniforms = reflection.uniforms.values()
+                .map(|uniform| format!("{} {}{}", reflection::type_name(uniform.kind), uniform.name, if uniform.size > 1 { format!("[{}]", uniform.size) } else { String::new() }))
+                .collect::<Vec<_>>();
+            uniforms.sort();
+            log!(self.logger, LogLevel::Info, "Program {} of \"{}\" has the uniforms: {}", id, self.name(), uniforms.join(", "));
+
+            self.programs.insert(id, program);
+            self.reflections.insert(id, reflection);
+            self.builds.insert(id, NEXT_BUILD.fetch_add(1, Ordering::Relaxed));
+            self.apply_block_bindings(id);
+            (id, sources)
+        });
+
+        match &result {
+            Ok(_) if !features.is_empty() => {
+                log!(self.logger, LogLevel::Info, "Built the \"{}\" variant of \"{}\"", features, self.name());
+            }
+            Ok(_) => {}
+            Err(err) => {
+                log!(self.logger, LogLevel::Error, "{}", err);
+                log!(self.logger, LogLevel::Warning, "Keeping the previous \"{}\" program of \"{}\"", features, self.name());
+            }
         }
+        result
     }
 
-    fn link_vs_and_fs_to_shader_program(&mut self, vertex_shader: u32, fragment_shader: u32) {
+    fn compile_stage(&mut self, stage: ShaderStage, source: &Preprocessed) -> Result<u32, ShaderError> {
         unsafe {
-            let shader_program = gl::CreateProgram();
+            let shader: u32 = gl::CreateShader(stage.gl_kind());
+            let shader_src = CString::new(source.source.as_bytes()).unwrap();
+            gl::ShaderSource(shader, 1, &shader_src.as_ptr(), std::ptr::null());
+            gl::CompileShader(shader);
 
-            // Attach vertex shader to the shader program
-            gl::AttachShader(shader_program, vertex_shader);
+            let mut success = gl::FALSE as GLint;
+            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+            if success != gl::TRUE as GLint {
+                let error = ShaderError::compile(stage, &shader_info_log(shader), source);
+                gl::DeleteShader(shader);
+                return Err(error);
+            }
 
-            // Attach fragment shader to the shader program
-            gl::AttachShader(shader_program, fragment_shader);
+            log!(self.logger, LogLevel::Info, "Compiled {} shader \"{}\".", stage, source.files[0]);
+            Ok(shader)
+        }
+    }
+
+    fn link(&mut self, shaders: &[u32]) -> Result<Program, ShaderError> {
+        let program = Program::new();
+        let shader_program = program.id();
+        unsafe {
+
+            // Attach the compiled stages to the shader program
+            for shader in shaders {
+                gl::AttachShader(shader_program, *shader);
+            }
 
-            // Link the shader program
+            // Link the shader program, keeping its binary retrievable for the cache
+            gl::ProgramParameteri(shader_program, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as i32);
             gl::LinkProgram(shader_program);
 
-            let mut success = gl::FALSE as GLint;
-            let mut info_log = vec![0; 512];
-            info_log.set_len(512 - 1); // subtract 1 to skip the trailing null character
+            // Once the program is linked (or failed to) the stages aren't needed anymore.
+            for shader in shaders {
+                gl::DeleteShader(*shader);
+            }
 
             // check for linking errors
+            let mut success = gl::FALSE as GLint;
             gl::GetProgramiv(shader_program, gl::LINK_STATUS, &mut success);
             if success != gl::TRUE as GLint {
-                gl::GetProgramInfoLog(shader_program, 512, std::ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-
-                log!(self.logger, LogLevel::Error, "ERROR::SHADER::PROGRAM::LINKING_FAILED");
-                log!(self.logger, LogLevel::Error, "Error: {}", str::from_utf8(&info_log).unwrap());
-            } else {
-                log!(self.logger, LogLevel::Info, "Shader program compiled and linked successfully.");
+                let paths = self.paths.stages().into_iter().map(|(_, path)| path).collect::<Vec<_>>();
+                return Err(ShaderError::link(&paths, &program_info_log(shader_program)));
             }
-
-            // Set the shader implementation's struct program_id to the shader_program (The program id)
-            self.program_id = shader_program;
-
-            // Once we are done and we got the program id, We can now delete both of the vertex and fragment shader.
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
         }
+
+        log!(self.logger, LogLevel::Info, "Shader program compiled and linked successfully.");
+        Ok(program)
     }
 
     pub fn use_program(&mut self) {
         // Use the shader program.
+        self.active = self.program_id;
         unsafe { gl::UseProgram(self.program_id) }
     }
 
-    pub fn set_uniform_matrix4fv(&mut self, name: &str, matrix: &Matrix4<f32>) {
-        // Get the location of the uniform in the shader program
-        let cstr = std::ffi::CString::new(name).unwrap();
-        let location = unsafe { gl::GetUniformLocation(self.program_id, cstr.as_ptr()) };
+    /// Program the uniform setters work on.
+    pub fn active_program(&self) -> u32 {
+        if self.active == 0 { self.program_id } else { self.active }
+    }
 
-        if location != -1 {
-            // Send the matrix data to the uniform
-            unsafe {
-                gl::UniformMatrix4fv(location, 1, gl::FALSE, matrix.as_ptr());
+    /// Serial of the build of the active program. Unlike program ids it changes when the
+    /// program is rebuilt, 0 before anything was built.
+    pub fn active_build(&self) -> u64 {
+        self.builds.get(&self.active_program()).copied().unwrap_or(0)
+    }
+
+    /// Whether `name` is an active uniform of the active program, outside of a uniform block.
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.reflection().and_then(|reflection| reflection.uniform(name)).is_some_and(|uniform| uniform.block.is_none())
+    }
+
+    /// Sets the uniform `name` of the active program. Uniforms that aren't active or have
+    /// another type are skipped with a warning.
+    pub fn set_uniform<T: UniformValue>(&mut self, name: &str, value: T) {
+        self.set_uniform_array(name, &[value]);
+    }
+
+    /// Sets the elements of the uniform array `name` from the first one on.
+    pub fn set_uniform_array<T: UniformValue>(&mut self, name: &str, values: &[T]) {
+        let program = self.active_program();
+        let uniform = match self.reflections.get(&program).and_then(|reflection| reflection.uniform(name)) {
+            Some(uniform) if uniform.block.is_some() => Err(format!("\"{}\" is in a uniform block, it's written through the block's buffer", name)),
+            Some(uniform) if !T::accepts(uniform.kind) => Err(format!("\"{}\" is a {}, not a {}", name, reflection::type_name(uniform.kind), T::TYPE)),
+            Some(uniform) => Ok((uniform.location, uniform.size as usize)),
+            None => Err(format!("\"{}\" is not an active uniform", name)),
+        };
+
+        match uniform {
+            Ok((location, size)) => {
+                if values.len() > size {
+                    self.warn(name, format!("\"{}\" has {} elements, {} were given", name, size, values.len()));
+                }
+                unsafe { T::upload(program, location, &values[..values.len().min(size)]) };
             }
+            Err(message) => self.warn(name, message),
         }
     }
 
-    pub fn set_uniform3f(&mut self, name: &str, x: f32, y: f32, z: f32) {
-        let cstr = CString::new(name).unwrap();
-        let location = unsafe { gl::GetUniformLocation(self.program_id, cstr.as_ptr()) };
-        unsafe {
-            gl::Uniform3f(location, x, y, z);
+    fn warn(&mut self, name: &str, message: String) {
+        if self.warned.insert(name.to_string()) {
+            log!(self.logger, LogLevel::Warning, "{} of \"{}\"", message, self.name());
         }
     }
 
-	pub fn set_uniform4f(&mut self, name: &str, x: f32, y: f32, z: f32, a: f32) {
-        let cstr = CString::new(name).unwrap();
-        let location = unsafe { gl::GetUniformLocation(self.program_id, cstr.as_ptr()) };
-        unsafe {
-            gl::Uniform4f(location, x, y, z, a);
-        }
+    pub fn set_uniform_matrix4fv(&mut self, name: &str, matrix: &Matrix4<f32>) {
+        self.set_uniform(name, *matrix);
+    }
+
+    pub fn set_uniform1i(&mut self, name: &str, value: i32) {
+        self.set_uniform(name, value);
+    }
+
+    pub fn set_uniform1f(&mut self, name: &str, value: f32) {
+        self.set_uniform(name, value);
+    }
+
+    pub fn set_uniform_bool(&mut self, name: &str, value: bool) {
+        self.set_uniform(name, value);
+    }
+
+    pub fn set_uniform2f(&mut self, name: &str, x: f32, y: f32) {
+        self.set_uniform(name, Vector2::new(x, y));
+    }
+
+    pub fn set_uniform3f(&mut self, name: &str, x: f32, y: f32, z: f32) {
+        self.set_uniform(name, Vector3::new(x, y, z));
+    }
+
+    pub fn set_uniform4f(&mut self, name: &str, x: f32, y: f32, z: f32, a: f32) {
+        self.set_uniform(name, Vector4::new(x, y, z, a));
+    }
+}
+
+/// The complete info log of a shader, however long the driver made it.
+fn shader_info_log(shader: u32) -> String {
+    unsafe {
+        let mut length = 0;
+        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut length);
+        let mut log = vec![0u8; length.max(1) as usize];
+        let mut written = 0;
+        gl::GetShaderInfoLog(shader, length, &mut written, log.as_mut_ptr() as *mut GLchar);
+        log.truncate(written.max(0) as usize);
+        String::from_utf8_lossy(&log).trim_end().to_string()
+    }
+}
+
+/// The complete info log of a program.
+fn program_info_log(program: u32) -> String {
+    unsafe {
+        let mut length = 0;
+        gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut length);
+        let mut log = vec![0u8; length.max(1) as usize];
+        let mut written = 0;
+        gl::GetProgramInfoLog(program, length, &mut written, log.as_mut_ptr() as *mut GLchar);
+        log.truncate(written.max(0) as usize);
+        String::from_utf8_lossy(&log).trim_end().to_string()
     }
 }
\ No newline at end of file
@@ -1 +1,7 @@
-pub mod manager;
\ No newline at end of file
+pub mod manager;
+pub mod error;
+pub mod preprocessor;
+pub mod permutation;
+pub mod reflection;
+pub mod uniform;
+pub mod cache;
\ No newline at end of file
@@ -0,0 +1,70 @@
+use std::{fmt, ops::{BitOr, BitOrAssign}};
+
+/// Compile-time features a shader variant is built with, every set flag becomes a `#define`
+/// of its name so shaders branch with `#ifdef` instead of uniforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ShaderFeatures(u32);
+
+impl ShaderFeatures {
+    pub const NONE: ShaderFeatures = ShaderFeatures(0);
+    /// Normals come from a tangent space normal map.
+    pub const HAS_NORMAL_MAP: ShaderFeatures = ShaderFeatures(1 << 0);
+    /// Vertices are deformed by joint matrices.
+    pub const SKINNED: ShaderFeatures = ShaderFeatures(1 << 1);
+    /// Texels below the alpha cutoff are discarded.
+    pub const ALPHA_TEST: ShaderFeatures = ShaderFeatures(1 << 2);
+
+    const NAMES: [(ShaderFeatures, &'static str); 3] = [
+        (ShaderFeatures::HAS_NORMAL_MAP, "HAS_NORMAL_MAP"),
+        (ShaderFeatures::SKINNED, "SKINNED"),
+        (ShaderFeatures::ALPHA_TEST, "ALPHA_TEST"),
+    ];
+
+    pub fn contains(self, other: ShaderFeatures) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The features unless `enabled` is false, `ShaderFeatures::ALPHA_TEST.when(material.alpha_cutoff.is_some())`.
+    pub fn when(self, enabled: bool) -> ShaderFeatures {
+        if enabled { self } else { ShaderFeatures::NONE }
+    }
+
+    /// `#define`s the variant is compiled with.
+    pub fn defines(self) -> Vec<(String, String)> {
+        ShaderFeatures::NAMES.iter()
+            .filter(|(feature, _)| self.contains(*feature))
+            .map(|(_, name)| (name.to_string(), String::new()))
+            .collect()
+    }
+}
+
+impl BitOr for ShaderFeatures {
+    type Output = ShaderFeatures;
+
+    fn bitor(self, other: ShaderFeatures) -> ShaderFeatures {
+        ShaderFeatures(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for ShaderFeatures {
+    fn bitor_assign(&mut self, other: ShaderFeatures) {
+        self.0 |= other.0;
+    }
+}
+
+impl fmt::Display for ShaderFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "NONE");
+        }
+        let names = ShaderFeatures::NAMES.iter()
+            .filter(|(feature, _)| self.contains(*feature))
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+        write!(f, "{}", names.join(" | "))
+    }
+}
@@ -0,0 +1,96 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use super::error::ShaderError;
+
+/// GLSL after `#include`s were expanded and `#define`s injected.
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessed {
+    pub source: String,
+    /// Every file that was read, the root file first.
+    pub files: Vec<String>,
+    /// Index into `files` and line number every line of `source` came from, `None` for injected lines.
+    pub origins: Vec<Option<(usize, u32)>>,
+}
+
+impl Preprocessed {
+    /// File and line of a line (1-based) of `source`.
+    pub fn origin(&self, line: u32) -> Option<(&str, u32)> {
+        let index = (line as usize).checked_sub(1)?;
+        let (file, line) = (*self.origins.get(index)?)?;
+        Some((self.files[file].as_str(), line))
+    }
+
+    /// Text of a line (1-based) of `source`.
+    pub fn line(&self, line: u32) -> Option<&str> {
+        self.source.lines().nth((line as usize).checked_sub(1)?)
+    }
+
+    fn push(&mut self, text: &str, origin: Option<(usize, u32)>) {
+        self.source.push_str(text);
+        self.source.push('\n');
+        self.origins.push(origin);
+    }
+}
+
+/// Reads `path`, expands `#include "file"` directives (relative to the including file,
+/// every file is included once) and puts `defines` right after the `#version` line.
+pub fn preprocess(path: &str, defines: &[(String, String)]) -> Result<Preprocessed, ShaderError> {
+    let mut output = Preprocessed::default();
+    let mut stack = Vec::new();
+    expand(path, defines, &mut output, &mut stack)?;
+    Ok(output)
+}
+
+fn expand(path: &str, defines: &[(String, String)], output: &mut Preprocessed, stack: &mut Vec<PathBuf>) -> Result<(), ShaderError> {
+    let source = fs::read_to_string(path).map_err(|err| ShaderError::read(path, &err))?;
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let root = stack.is_empty();
+
+    let file = output.files.len();
+    output.files.push(path.to_string());
+    stack.push(canonical);
+
+    let mut injected = defines.is_empty() || !root;
+    for (index, text) in source.lines().enumerate() {
+        let number = index as u32 + 1;
+        let directive = text.trim_start();
+
+        if let Some(include) = directive.strip_prefix("#include") {
+            let name = include.trim();
+            let Some(name) = name.strip_prefix('"').and_then(|name| name.strip_suffix('"')) else {
+                return Err(ShaderError::preprocess(path, number, &format!("expected #include \"file\", found {}", name)));
+            };
+
+            let included = Path::new(path).parent().unwrap_or(Path::new("")).join(name);
+            let included_canonical = fs::canonicalize(&included).unwrap_or_else(|_| included.clone());
+            if stack.contains(&included_canonical) {
+                return Err(ShaderError::preprocess(path, number, &format!("\"{}\" is included recursively", name)));
+            }
+            // Like `#pragma once`, headers included again are skipped.
+            let already = output.files.iter().any(|file| fs::canonicalize(file).is_ok_and(|file| file == included_canonical));
+            if !already {
+                let included = included.to_string_lossy().to_string();
+                expand(&included, defines, output, stack).map_err(|err| err.included_from(path, number))?;
+            }
+            continue;
+        }
+
+        output.push(text, Some((file, number)));
+        if !injected && directive.starts_with("#version") {
+            for (name, value) in defines {
+                output.push(format!("#define {} {}", name, value).trim_end(), None);
+            }
+            injected = true;
+        }
+    }
+
+    // Sources without a `#version` get the defines up front.
+    if !injected {
+        let lines = defines.iter().map(|(name, value)| format!("#define {} {}", name, value).trim_end().to_string()).collect::<Vec<_>>();
+        output.source = format!("{}\n{}", lines.join("\n"), output.source);
+        output.origins.splice(0..0, lines.iter().map(|_| None));
+    }
+
+    stack.pop();
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use gl::types::{GLchar, GLenum, GLint};
+
+/// An active uniform of a linked program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformInfo {
+    /// Name without the `[0]` drivers append to arrays.
+    pub name: String,
+    /// GL type, `gl::FLOAT_MAT4`, `gl::SAMPLER_2D`...
+    pub kind: GLenum,
+    /// Number of elements, 1 unless it's an array.
+    pub size: i32,
+    /// -1 for members of uniform blocks, they're written through the block's buffer.
+    pub location: i32,
+    /// Index of the uniform block the uniform is a member of.
+    pub block: Option<u32>,
+    /// Byte offset inside the block, -1 outside of one.
+    pub offset: i32,
+}
+
+/// An active vertex attribute of a linked program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeInfo {
+    pub name: String,
+    pub kind: GLenum,
+    pub size: i32,
+    pub location: i32,
+}
+
+/// An active uniform or shader storage block of a linked program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockInfo {
+    pub name: String,
+    pub index: u32,
+    /// Binding point the block reads its buffer from.
+    pub binding: u32,
+    /// Bytes the block's buffer needs at least, without the elements of a trailing unsized array.
+    pub size: i32,
+}
+
+/// What the driver reports to be active in a program after linking, inactive uniforms
+/// were optimized out and have no location.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramReflection {
+    pub uniforms: HashMap<String, UniformInfo>,
+    pub attributes: HashMap<String, AttributeInfo>,
+    pub blocks: HashMap<String, BlockInfo>,
+    pub storage_blocks: HashMap<String, BlockInfo>,
+}
+
+impl ProgramReflection {
+    pub fn new(program: u32) -> Self {
+        unsafe {
+            ProgramReflection {
+                uniforms: reflect_uniforms(program),
+                attributes: reflect_attributes(program),
+                blocks: reflect_blocks(program),
+                storage_blocks: reflect_storage_blocks(program),
+            }
+        }
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(name)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        self.attributes.get(name)
+    }
+
+    pub fn block(&self, name: &str) -> Option<&BlockInfo> {
+        self.blocks.get(name)
+    }
+
+    pub fn storage_block(&self, name: &str) -> Option<&BlockInfo> {
+        self.storage_blocks.get(name)
+    }
+}
+
+unsafe fn reflect_uniforms(program: u32) -> HashMap<String, UniformInfo> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+    let mut uniforms = HashMap::new();
+    for index in 0..count.max(0) as u32 {
+        let mut size = 0;
+        let mut kind = 0;
+        let name = active_name(max_length, |capacity, length, name| {
+            gl::GetActiveUniform(program, index, capacity, length, &mut size, &mut kind, name)
+        });
+
+        let mut block = -1;
+        gl::GetActiveUniformsiv(program, 1, &index, gl::UNIFORM_BLOCK_INDEX, &mut block);
+        let mut offset = -1;
+        gl::GetActiveUniformsiv(program, 1, &index, gl::UNIFORM_OFFSET, &mut offset);
+
+        let location = if block == -1 { uniform_location(program, &name) } else { -1 };
+        let name = array_name(name);
+        uniforms.insert(name.clone(), UniformInfo {
+            name,
+            kind,
+            size,
+            location,
+            block: (block != -1).then_some(block as u32),
+            offset,
+        });
+    }
+    uniforms
+}
+
+unsafe fn reflect_attributes(program: u32) -> HashMap<String, AttributeInfo> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTES, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_length);
+
+    let mut attributes = HashMap::new();
+    for index in 0..count.max(0) as u32 {
+        let mut size = 0;
+        let mut kind = 0;
+        let name = active_name(max_length, |capacity, length, name| {
+            gl::GetActiveAttrib(program, index, capacity, length, &mut size, &mut kind, name)
+        });
+
+        let c_name = std::ffi::CString::new(name.as_str()).unwrap();
+        let location = gl::GetAttribLocation(program, c_name.as_ptr());
+        let name = array_name(name);
+        attributes.insert(name.clone(), AttributeInfo { name, kind, size, location });
+    }
+    attributes
+}
+
+unsafe fn reflect_blocks(program: u32) -> HashMap<String, BlockInfo> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCKS, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH, &mut max_length);
+
+    let mut blocks = HashMap::new();
+    for index in 0..count.max(0) as u32 {
+        let name = active_name(max_length, |capacity, length, name| {
+            gl::GetActiveUniformBlockName(program, index, capacity, length, name)
+        });
+
+        let mut binding = 0;
+        gl::GetActiveUniformBlockiv(program, index, gl::UNIFORM_BLOCK_BINDING, &mut binding);
+        let mut size = 0;
+        gl::GetActiveUniformBlockiv(program, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size);
+
+        blocks.insert(name.clone(), BlockInfo { name, index, binding: binding as u32, size });
+    }
+    blocks
+}
+
+/// Shader storage blocks only have the program interface queries.
+unsafe fn reflect_storage_blocks(program: u32) -> HashMap<String, BlockInfo> {
+    let mut count = 0;
+    gl::GetProgramInterfaceiv(program, gl::SHADER_STORAGE_BLOCK, gl::ACTIVE_RESOURCES, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramInterfaceiv(program, gl::SHADER_STORAGE_BLOCK, gl::MAX_NAME_LENGTH, &mut max_length);
+
+    let mut blocks = HashMap::new();
+    for index in 0..count.max(0) as u32 {
+        let name = active_name(max_length, |capacity, length, name| {
+            gl::GetProgramResourceName(program, gl::SHADER_STORAGE_BLOCK, index, capacity, length, name)
+        });
+
+        let properties = [gl::BUFFER_BINDING, gl::BUFFER_DATA_SIZE];
+        let mut values = [0; 2];
+        gl::GetProgramResourceiv(program, gl::SHADER_STORAGE_BLOCK, index, properties.len() as i32, properties.as_ptr(), values.len() as i32, std::ptr::null_mut(), values.as_mut_ptr());
+
+        blocks.insert(name.clone(), BlockInfo { name, index, binding: values[0] as u32, size: values[1] });
+    }
+    blocks
+}
+
+/// Reads the name of an active resource with a `glGetActive*` call.
+unsafe fn active_name(max_length: GLint, get: impl FnOnce(GLint, *mut GLint, *mut GLchar)) -> String {
+    let mut name = vec![0u8; max_length.max(1) as usize];
+    let mut length = 0;
+    get(name.len() as GLint, &mut length, name.as_mut_ptr() as *mut GLchar);
+    name.truncate(length.max(0) as usize);
+    String::from_utf8_lossy(&name).to_string()
+}
+
+unsafe fn uniform_location(program: u32, name: &str) -> i32 {
+    let c_name = std::ffi::CString::new(name).unwrap();
+    gl::GetUniformLocation(program, c_name.as_ptr())
+}
+
+/// Arrays are reported as `name[0]`.
+fn array_name(name: String) -> String {
+    match name.strip_suffix("[0]") {
+        Some(name) => name.to_string(),
+        None => name,
+    }
+}
+
+pub fn is_sampler(kind: GLenum) -> bool {
+    matches!(kind,
+        gl::SAMPLER_1D | gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE |
+        gl::SAMPLER_1D_SHADOW | gl::SAMPLER_2D_SHADOW | gl::SAMPLER_CUBE_SHADOW |
+        gl::SAMPLER_1D_ARRAY | gl::SAMPLER_2D_ARRAY | gl::SAMPLER_2D_ARRAY_SHADOW |
+        gl::SAMPLER_2D_MULTISAMPLE | gl::SAMPLER_BUFFER |
+        gl::INT_SAMPLER_2D | gl::INT_SAMPLER_3D | gl::UNSIGNED_INT_SAMPLER_2D | gl::UNSIGNED_INT_SAMPLER_3D)
+}
+
+/// Image units compute shaders load from and store to.
+pub fn is_image(kind: GLenum) -> bool {
+    matches!(kind,
+        gl::IMAGE_1D | gl::IMAGE_2D | gl::IMAGE_3D | gl::IMAGE_CUBE | gl::IMAGE_2D_ARRAY | gl::IMAGE_BUFFER |
+        gl::INT_IMAGE_2D | gl::INT_IMAGE_3D | gl::UNSIGNED_INT_IMAGE_2D | gl::UNSIGNED_INT_IMAGE_3D)
+}
+
+/// GLSL name of a uniform or attribute type, for messages.
+pub fn type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        gl::IMAGE_2D => "image2D",
+        gl::IMAGE_3D => "image3D",
+        kind if is_sampler(kind) => "sampler",
+        kind if is_image(kind) => "image",
+        _ => "unknown",
+    }
+}
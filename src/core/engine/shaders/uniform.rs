@@ -0,0 +1,206 @@
+use gl::types::GLenum;
+use nalgebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+use crate::core::engine::objects::gpu::Buffer;
+use super::reflection::{is_image, is_sampler};
+
+/// A Rust type that can be uploaded to uniforms of the GL types it `accepts`.
+pub trait UniformValue: Copy {
+    /// GLSL type, for messages.
+    const TYPE: &'static str;
+
+    fn accepts(kind: GLenum) -> bool;
+
+    /// # Safety
+    ///
+    /// `location` must be a uniform of `program` whose type `accepts` and that has room for `values`.
+    unsafe fn upload(program: u32, location: i32, values: &[Self]);
+}
+
+impl UniformValue for i32 {
+    const TYPE: &'static str = "int";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::INT || kind == gl::BOOL || is_sampler(kind) || is_image(kind)
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        gl::ProgramUniform1iv(program, location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformValue for u32 {
+    const TYPE: &'static str = "uint";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::UNSIGNED_INT
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        gl::ProgramUniform1uiv(program, location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformValue for bool {
+    const TYPE: &'static str = "bool";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::BOOL
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        let values = values.iter().map(|value| *value as i32).collect::<Vec<_>>();
+        gl::ProgramUniform1iv(program, location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformValue for f32 {
+    const TYPE: &'static str = "float";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        gl::ProgramUniform1fv(program, location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformValue for Vector2<f32> {
+    const TYPE: &'static str = "vec2";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_VEC2
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        gl::ProgramUniform2fv(program, location, values.len() as i32, values.as_ptr() as *const f32);
+    }
+}
+
+impl UniformValue for Vector3<f32> {
+    const TYPE: &'static str = "vec3";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_VEC3
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        gl::ProgramUniform3fv(program, location, values.len() as i32, values.as_ptr() as *const f32);
+    }
+}
+
+impl UniformValue for Vector4<f32> {
+    const TYPE: &'static str = "vec4";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_VEC4
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        gl::ProgramUniform4fv(program, location, values.len() as i32, values.as_ptr() as *const f32);
+    }
+}
+
+impl UniformValue for Matrix3<f32> {
+    const TYPE: &'static str = "mat3";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_MAT3
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        gl::ProgramUniformMatrix3fv(program, location, values.len() as i32, gl::FALSE, values.as_ptr() as *const f32);
+    }
+}
+
+impl UniformValue for Matrix4<f32> {
+    const TYPE: &'static str = "mat4";
+
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_MAT4
+    }
+
+    unsafe fn upload(program: u32, location: i32, values: &[Self]) {
+        gl::ProgramUniformMatrix4fv(program, location, values.len() as i32, gl::FALSE, values.as_ptr() as *const f32);
+    }
+}
+
+/// A uniform buffer bound to a binding point, shared by every program whose uniform block
+/// is bound to the same point with `Shader::bind_uniform_block`. The buffer is created on
+/// the first write, so it can be made before OpenGL is loaded.
+pub struct UniformBuffer {
+    pub buffer: Option<Buffer>,
+    pub binding: u32,
+    /// Bytes, laid out as the std140 block in the shaders.
+    pub size: usize,
+}
+
+impl UniformBuffer {
+    pub fn new(binding: u32, size: usize) -> Self {
+        UniformBuffer { buffer: None, binding, size }
+    }
+
+    /// Copies `data` into the buffer at byte `offset`.
+    pub fn write<T: Copy>(&mut self, offset: usize, data: &T) {
+        let length = std::mem::size_of::<T>();
+        assert!(offset + length <= self.size, "[S:UBO] Writing {} bytes at {} overflows the {} bytes uniform buffer", length, offset, self.size);
+
+        let buffer = self.buffer.get_or_insert_with(|| {
+            let buffer = Buffer::new();
+            buffer.allocate(self.size, gl::DYNAMIC_DRAW);
+            buffer.label(&format!("uniform buffer {}", self.binding));
+            buffer.bind_base(gl::UNIFORM_BUFFER, self.binding);
+            buffer
+        });
+        buffer.sub_data(offset, std::slice::from_ref(data));
+    }
+}
+
+/// A shader storage buffer bound to a binding point, for data compute shaders read and write
+/// (`Shader::bind_storage_block` connects a program's `buffer` block to it).
+pub struct StorageBuffer {
+    pub buffer: Buffer,
+    pub binding: u32,
+    /// Bytes, laid out as the std430 block in the shaders.
+    pub size: usize,
+}
+
+impl StorageBuffer {
+    pub fn new(binding: u32, size: usize) -> Self {
+        let buffer = Buffer::new();
+        buffer.allocate(size, gl::DYNAMIC_COPY);
+        buffer.label(&format!("storage buffer {}", binding));
+        buffer.bind_base(gl::SHADER_STORAGE_BUFFER, binding);
+        StorageBuffer { buffer, binding, size }
+    }
+
+    /// A buffer holding `data`.
+    pub fn from_slice<T: Copy>(binding: u32, data: &[T]) -> Self {
+        let mut buffer = StorageBuffer::new(binding, std::mem::size_of_val(data));
+        buffer.write(0, data);
+        buffer
+    }
+
+    /// Copies `data` into the buffer at byte `offset`.
+    pub fn write<T: Copy>(&mut self, offset: usize, data: &[T]) {
+        let length = std::mem::size_of_val(data);
+        assert!(offset + length <= self.size, "[S:SSBO] Writing {} bytes at {} overflows the {} bytes storage buffer", length, offset, self.size);
+        self.buffer.sub_data(offset, data);
+    }
+
+    /// Reads `count` elements from byte `offset`, call `Shader::memory_barrier(gl::BUFFER_UPDATE_BARRIER_BIT)`
+    /// first when a dispatch wrote them.
+    pub fn read<T: Copy + Default>(&self, offset: usize, count: usize) -> Vec<T> {
+        let mut data = vec![T::default(); count];
+        let length = std::mem::size_of_val(data.as_slice());
+        assert!(offset + length <= self.size, "[S:SSBO] Reading {} bytes at {} overflows the {} bytes storage buffer", length, offset, self.size);
+        unsafe { gl::GetNamedBufferSubData(self.buffer.id(), offset as isize, length as isize, data.as_mut_ptr() as *mut _) };
+        data
+    }
+
+    /// Binds the buffer as a vertex or indirect buffer too, `gl::ARRAY_BUFFER`, `gl::DRAW_INDIRECT_BUFFER`...
+    pub fn bind_as(&self, target: u32) {
+        self.buffer.bind(target);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+
+use glfw::{Action, Key, WindowEvent};
+use image::{Rgba, RgbaImage};
+use nalgebra::{Vector2, Vector3};
+
+use crate::core::{engine::{renderstate::manager::RenderState, script::{error::ScriptLanguage, repl::completion_path}, threed::model::{ModelMatrix, ModelTransformData}}, utils::texture::bitmap::Texture};
+use super::text::ext::TextComponent;
+
+/// Lines of output kept for scrolling back.
+const MAX_OUTPUT_LINES: usize = 200;
+/// Lines of output shown above the prompt.
+const VISIBLE_LINES: usize = 14;
+
+/// What the console wants the engine to do with the input, evaluation needs the script
+/// parsers and the window so it happens outside of the console.
+pub enum ConsoleInput {
+	/// A line was entered.
+	Submit(String),
+	/// Tab was pressed, carries the path in front of the cursor to complete.
+	Complete(String),
+}
+
+/// Drop-down console evaluating Lua or JS in the running game.
+pub struct DeveloperConsole {
+	pub open: bool,
+	pub language: ScriptLanguage,
+	/// Key opening and closing the console.
+	pub toggle_key: Key,
+
+	input: String,
+	/// Byte offset of the cursor in `input`.
+	cursor: usize,
+	history: Vec<String>,
+	history_index: Option<usize>,
+	output: VecDeque<String>,
+	/// The character event of the toggle key follows its key event and must not be typed.
+	swallow_char: bool,
+
+	text: TextComponent,
+	background: Texture,
+}
+
+impl DeveloperConsole {
+	pub fn new(font: &str, scale: f32) -> Self {
+		let mut text = TextComponent::new(font, scale);
+		text.set_color(Vector3::new(220, 220, 220));
+		text.set_position(Vector2::new(8.0, 0.0));
+
+		let background = Texture::new(RgbaImage::from_pixel(1, 1, Rgba([12, 12, 16, 220])), false);
+
+		DeveloperConsole {
+			open: false,
+			language: ScriptLanguage::Lua,
+			toggle_key: Key::GraveAccent,
+
+			input: String::new(),
+			cursor: 0,
+			history: Vec::new(),
+			history_index: None,
+			output: VecDeque::new(),
+			swallow_char: false,
+
+			text,
+			background,
+		}
+	}
+
+	pub fn initialize(&mut self) {
+		self.text.initialize();
+		self.background.init();
+	}
+
+	pub fn toggle(&mut self) {
+		self.open = !self.open;
+	}
+
+	/// Appends `text` to the output, line by line.
+	pub fn print(&mut self, text: &str) {
+		for line in text.lines() {
+			self.output.push_back(line.to_string());
+		}
+		while self.output.len() > MAX_OUTPUT_LINES {
+			self.output.pop_front();
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.output.clear();
+	}
+
+	/// Feeds a window event to the console, returning the input that needs evaluating.
+	pub fn handle_event(&mut self, event: &WindowEvent) -> Option<ConsoleInput> {
+		match *event {
+			WindowEvent::Key(key, _, Action::Press, _) if key == self.toggle_key => {
+				self.toggle();
+				self.swallow_char = true;
+				None
+			}
+			WindowEvent::Char(character) if self.open => {
+				if std::mem::take(&mut self.swallow_char) && character == '`' {
+					return None;
+				}
+				self.input.insert(self.cursor, character);
+				self.cursor += character.len_utf8();
+				None
+			}
+			// Closing on release keeps the same Escape from also reaching the window.
+			WindowEvent::Key(Key::Escape, _, Action::Release, _) if self.open => {
+				self.open = false;
+				None
+			}
+			WindowEvent::Key(key, _, Action::Press | Action::Repeat, _) if self.open => self.handle_key(key),
+			_ => None,
+		}
+	}
+
+	fn handle_key(&mut self, key: Key) -> Option<ConsoleInput> {
+		match key {
+			Key::Enter | Key::KpEnter => return self.submit(),
+			Key::Tab => return Some(ConsoleInput::Complete(completion_path(&self.input[..self.cursor]).to_string())),
+			Key::Backspace => {
+				if let Some(previous) = self.input[..self.cursor].chars().next_back() {
+					self.cursor -= previous.len_utf8();
+					self.input.remove(self.cursor);
+				}
+			}
+			Key::Delete if self.cursor < self.input.len() => {
+				self.input.remove(self.cursor);
+			}
+			Key::Left => {
+				if let Some(previous) = self.input[..self.cursor].chars().next_back() {
+					self.cursor -= previous.len_utf8();
+				}
+			}
+			Key::Right => {
+				if let Some(next) = self.input[self.cursor..].chars().next() {
+					self.cursor += next.len_utf8();
+				}
+			}
+			Key::Home => self.cursor = 0,
+			Key::End => self.cursor = self.input.len(),
+			Key::Up => {
+				let index = match self.history_index {
+					Some(index) => index.saturating_sub(1),
+					None => self.history.len().checked_sub(1)?,
+				};
+				self.recall(Some(index));
+			}
+			Key::Down => {
+				let index = self.history_index.map(|index| index + 1).filter(|index| *index < self.history.len());
+				self.recall(index);
+			}
+			_ => {}
+		}
+		None
+	}
+
+	/// Puts a history entry (or an empty line for `None`) into the input.
+	fn recall(&mut self, index: Option<usize>) {
+		self.history_index = index;
+		self.input = index.map(|index| self.history[index].clone()).unwrap_or_default();
+		self.cursor = self.input.len();
+	}
+
+	fn submit(&mut self) -> Option<ConsoleInput> {
+		let line = std::mem::take(&mut self.input).trim().to_string();
+		self.cursor = 0;
+		self.history_index = None;
+		if line.is_empty() {
+			return None;
+		}
+
+		if self.history.last() != Some(&line) {
+			self.history.push(line.clone());
+		}
+		self.print(&format!("{}{}", self.prompt(), line));
+
+		if line == "clear" {
+			self.clear();
+			return None;
+		}
+		Some(ConsoleInput::Submit(line))
+	}
+
+	/// Replaces `path` in front of the cursor with what `candidates` have in common and lists
+	/// them when there is more than one.
+	pub fn complete(&mut self, path: &str, candidates: &[String]) {
+		let Some(first) = candidates.first() else {
+			return;
+		};
+
+		let common = candidates.iter().skip(1).fold(first.as_str(), |common, candidate| {
+			let length = common.char_indices()
+				.zip(candidate.chars())
+				.take_while(|((_, a), b)| a == b)
+				.last()
+				.map_or(0, |((index, c), _)| index + c.len_utf8());
+			&common[..length]
+		});
+
+		if common.len() > path.len() && self.input[..self.cursor].ends_with(path) {
+			let start = self.cursor - path.len();
+			self.input.replace_range(start..self.cursor, common);
+			self.cursor = start + common.len();
+		}
+		if candidates.len() > 1 {
+			self.print(&candidates.join("  "));
+		}
+	}
+
+	fn prompt(&self) -> &'static str {
+		match self.language {
+			ScriptLanguage::Lua => "lua> ",
+			ScriptLanguage::JavaScript => "js> ",
+		}
+	}
+
+	/// Draws the console over the top of the screen, `program_id` must already be in use and have its projection set.
+	pub fn draw(&mut self, program_id: u32, width: i32) {
+		if !self.open {
+			return;
+		}
+
+		let line_height = self.text.scale() * 1.25;
+		let height = line_height * (VISIBLE_LINES + 1) as f32 + self.text.scale();
+
+		let mut text = self.output.iter()
+			.skip(self.output.len().saturating_sub(VISIBLE_LINES))
+			.cloned()
+			.collect::<Vec<_>>();
+		// Keep the prompt on the last line even when there is little output.
+		while text.len() < VISIBLE_LINES {
+			text.insert(0, String::new());
+		}
+		text.push(format!("{}{}|{}", self.prompt(), &self.input[..self.cursor], &self.input[self.cursor..]));
+		let text = text.join("\n");
+		if text != self.text.text {
+			self.text.set_text(&text);
+		}
+
+		// The console always draws on top of the scene.
+		let previous = RenderState::current();
+		RenderState::OVERLAY.apply();
+
+		unsafe {
+			let model = ModelMatrix::new(ModelTransformData {
+				translation: Vector3::new(width as f32 / 2.0, height / 2.0, 0.0),
+				rotation: Vector3::default(),
+				scale: Vector3::new(width as f32, height, 1.0),
+			});
+			let model_name = std::ffi::CString::new("model").unwrap();
+			gl::UniformMatrix4fv(gl::GetUniformLocation(program_id, model_name.as_ptr()), 1, gl::FALSE, model.matrix.as_ptr());
+			self.background.apply(0, "texture1", program_id);
+			self.text.set_vertex();
+
+			self.text.draw(program_id);
+		}
+		previous.apply();
+	}
+}
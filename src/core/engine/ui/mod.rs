@@ -1,2 +1,4 @@
 pub mod text;
-pub mod test;
\ No newline at end of file
+pub mod test;
+pub mod overlay;
+pub mod console;
\ No newline at end of file
@@ -0,0 +1,57 @@
+use nalgebra::{Vector2, Vector3};
+
+use crate::core::engine::script::error::ScriptError;
+use super::text::ext::TextComponent;
+
+/// How many stack frames of every error are shown.
+const MAX_FRAMES: usize = 4;
+
+/// Lists the currently failing scripts on screen so designers don't need a terminal.
+pub struct ErrorOverlay {
+	text: TextComponent,
+}
+
+impl ErrorOverlay {
+	pub fn new(font: &str, scale: f32) -> Self {
+		let mut text = TextComponent::new(font, scale);
+		text.set_color(Vector3::new(255, 96, 96));
+		text.set_position(Vector2::new(8.0, scale * 3.0));
+
+		ErrorOverlay { text }
+	}
+
+	pub fn initialize(&mut self) {
+		self.text.initialize();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.text.text.is_empty()
+	}
+
+	/// Rebuilds the overlay from the current script errors.
+	pub fn update<'a>(&mut self, errors: impl Iterator<Item = &'a ScriptError>) {
+		let mut errors = errors.collect::<Vec<_>>();
+		errors.sort_by(|a, b| a.file.cmp(&b.file));
+
+		let mut text = String::new();
+		for error in errors {
+			text.push_str(&format!("{} error in {}\n  {}\n", error.language, error.location(), error.message));
+			for frame in error.stack.iter().take(MAX_FRAMES) {
+				text.push_str(&format!("    {}\n", frame));
+			}
+			if error.stack.len() > MAX_FRAMES {
+				text.push_str(&format!("    ... {} more\n", error.stack.len() - MAX_FRAMES));
+			}
+		}
+
+		if text != self.text.text {
+			self.text.set_text(&text);
+		}
+	}
+
+	pub fn draw(&mut self, program_id: u32) {
+		if !self.is_empty() {
+			self.text.draw(program_id);
+		}
+	}
+}
@@ -1,42 +1,37 @@
 use std::collections::HashMap;
 
-use nalgebra::Vector3;
+use nalgebra::{Vector2, Vector3};
 use rusttype::point;
 
-use crate::core::{engine::objects::graphics::GraphicsObjects, utils::texture::bitmap::Texture};
+use crate::core::{engine::objects::mesh::Mesh, utils::texture::bitmap::Texture};
 
 use super::glyph::GlyphExtractor;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct TextComponent {
 	pub text: String,
 
 	scale: f32,
 	color: Vector3<u8>,
+	/// Top-left corner of the text in screen pixels.
+	pub position: Vector2<f32>,
 
 	extractor: GlyphExtractor,
 
 	pub glyphs_image: HashMap<char, Texture>,
 
-	pub gob: GraphicsObjects,
-	pub tgob: GraphicsObjects
+	/// Quad the glyphs are drawn on, uploaded by `initialize`.
+	pub mesh: Option<Mesh>
 }
 
 impl TextComponent {
 	pub fn new(font: &str, scale: f32) -> Self {
-		let mut gob = GraphicsObjects::default();
-		let mut tgob = GraphicsObjects::default();
-
-		gob.generate_vbo();
-		gob.generate_vao();
-		gob.generate_ebo();
-		tgob.generate_vbo();
-
 		Self {
 			color: Vector3::new(255, 255, 255),
 
 			scale,
 			text: String::new(),
+			position: Vector2::zeros(),
 
 			extractor: GlyphExtractor::new(GlyphExtractor {
 				glyph_scale: scale,
@@ -47,8 +42,7 @@ impl TextComponent {
 
 			glyphs_image: HashMap::new(),
 
-			gob,
-			tgob
+			mesh: None
 		}
 	}
 
@@ -63,10 +57,18 @@ impl TextComponent {
 	pub fn set_color(&mut self, color: Vector3<u8>) {
 		self.color = color;
 	}
+
+	pub fn set_position(&mut self, position: Vector2<f32>) {
+		self.position = position;
+	}
+
+	pub fn scale(&self) -> f32 {
+		self.scale
+	}
 }
 
 impl TextComponent {
-	fn extract_text_as_glyphs(&mut self) {
+	pub(super) fn extract_text_as_glyphs(&mut self) {
 		let characters = self.text.split("").filter(|x| !x.trim().is_empty());
 		characters.for_each(|char| {
 			let char = char.chars().nth(0).unwrap();
@@ -81,9 +83,7 @@ impl TextComponent {
 	}
 
 	pub fn initialize(&mut self) {
-		self.upload_vertices();
-		self.upload_indices();
-		self.bind_textures();
+		self.upload_quad();
 
 		self.extract_text_as_glyphs();
 	}
@@ -1,18 +1,17 @@
-use super::ext::TextComponent;
+use std::ffi::CString;
 
-const VERTICES: [f32; 12] = [
-    // Positions   
-    -0.5, -0.5, 0.0,  // Bottom-left
-     0.5, -0.5, 0.0,  // Bottom-right
-    -0.5,  0.5, 0.0,  // Top-left
-     0.5,  0.5, 0.0,  // Top-right
-];
+use nalgebra::Vector3;
+
+use crate::core::engine::{objects::{layout::{AttributeFormat, Semantic, VertexLayout}, mesh::{as_bytes, Mesh, MeshUsage}}, threed::model::{ModelMatrix, ModelTransformData}};
+use super::ext::TextComponent;
 
-const TEX_COORDS: [f32; 8] = [
-	0.0, 0.0,
-	1.0, 0.0,
-	0.0, 1.0,
-	1.0, 1.0,
+/// Corners of the glyph quad, position then texture coordinates.
+const QUAD: [f32; 20] = [
+    // Positions       // Texture coordinates
+    -0.5, -0.5, 0.0,   0.0, 0.0,  // Bottom-left
+     0.5, -0.5, 0.0,   1.0, 0.0,  // Bottom-right
+    -0.5,  0.5, 0.0,   0.0, 1.0,  // Top-left
+     0.5,  0.5, 0.0,   1.0, 1.0,  // Top-right
 ];
 
 const INDICES: [u32; 6] = [
@@ -21,67 +20,71 @@ const INDICES: [u32; 6] = [
 ];
 
 impl TextComponent {
-	pub fn upload_vertices(&mut self) {
-		unsafe {
-			self.gob.bind_vbo();
-			self.gob.bind_vao();
-			// Bind and fill VBO
-			gl::BindBuffer(gl::ARRAY_BUFFER, self.gob.vbo);
-			gl::BufferData(gl::ARRAY_BUFFER, (VERTICES.len() * std::mem::size_of::<f32>()) as isize, VERTICES.as_ptr() as *const _, gl::STATIC_DRAW);	
-
-			// Define vertex attribute pointers (assuming positions only, 3 floats per vertex)
-			gl::VertexAttribPointer(
-				0, // attribute index
-				3, // number of components (x, y, z)
-				gl::FLOAT, // type of components
-				gl::FALSE, // normalized
-				0, // stride
-				std::ptr::null(), // pointer to the start
-			);
-			gl::EnableVertexAttribArray(0);
-		}
-	}
-
-	pub fn upload_indices(&mut self) {
-		unsafe {
-			// Bind and fill EBO
-			self.gob.bind_ebo();
-			gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.gob.ebo);
-			gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, (INDICES.len() * std::mem::size_of::<u32>()) as isize, INDICES.as_ptr() as *const _, gl::STATIC_DRAW);
-		}
-	}
-
-	pub fn bind_textures(&mut self) {
-		unsafe {
-			self.tgob.bind_vbo();
-			gl::BufferData(
-				gl::ARRAY_BUFFER,
-				(TEX_COORDS.len() * std::mem::size_of::<[f32; 2]>()) as isize,
-				TEX_COORDS.as_ptr() as *const std::ffi::c_void,
-				gl::STATIC_DRAW,
-			);
-			gl::VertexAttribPointer(
-				1,
-				2,
-				gl::FLOAT,
-				gl::FALSE,
-				0,
-				std::ptr::null(),
-			);
-			gl::EnableVertexAttribArray(1);
-		}
+	/// Uploads the quad every glyph is drawn on.
+	pub fn upload_quad(&mut self) {
+		let layout = VertexLayout::interleaved(&[(Semantic::Position, AttributeFormat::Vec3), (Semantic::TexCoord0, AttributeFormat::Vec2)]);
+		let mesh = Mesh::new(layout, &[as_bytes(&QUAD)], Some(&INDICES), MeshUsage::Static);
+		mesh.label("glyph quad");
+		self.mesh = Some(mesh);
 	}
 
 	pub fn set_vertex(&mut self) {
-		self.gob.bind_vao();
-		unsafe {
-			gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+		if let Some(mesh) = &self.mesh {
+			mesh.draw();
 		}
 	}
 }
 
 impl TextComponent {
+	/// Draws the text with `program_id`, which must already be in use and have its projection set.
 	pub fn draw(&mut self, program_id: u32) {
-		
+		// Text may have changed since `initialize`, make sure every character has a glyph
+		self.extract_text_as_glyphs();
+
+		let font_scale = self.scale();
+		let space_width = font_scale / 1.5;  // Adjust this value for the space character
+		let line_height = font_scale * 1.25;
+
+		// Find the maximum height of all glyphs (to set a common baseline)
+		let max_height = self.glyphs_image.values()
+			.map(|img| img.dimensions.1 as f32)
+			.max_by(|a, b| a.partial_cmp(b).unwrap())
+			.unwrap_or(0.0);  // Default to 0.0 if no glyphs are available
+
+		let model_name = CString::new("model").unwrap();
+		let model_location = unsafe { gl::GetUniformLocation(program_id, model_name.as_ptr()) };
+
+		let mut x = self.position.x + font_scale / 2.0;
+		let mut y = self.position.y;
+
+		for char in self.text.clone().chars() {
+			if char == '\n' {
+				x = self.position.x + font_scale / 2.0;
+				y += line_height;
+				continue;
+			}
+
+			let Some(img) = self.glyphs_image.get_mut(&char) else {
+				// If character is not found (or is a space), increment by the space_width
+				x += space_width;
+				continue;
+			};
+
+			// Align every glyph on the same baseline
+			let model = ModelMatrix::new(ModelTransformData {
+				translation: Vector3::new(x, y + max_height + (font_scale / 2.0), 0.0),
+				rotation: Vector3::default(),
+				scale: Vector3::new(img.dimensions.0 as f32, img.dimensions.1 as f32, 1.0),
+			});
+
+			unsafe { gl::UniformMatrix4fv(model_location, 1, gl::FALSE, model.matrix.as_ptr()) };
+
+			// Apply the texture and draw the character
+			img.apply(0, "texture1", program_id);
+			let advance = img.dimensions.0 as f32 + font_scale * 0.1;  // Adjust horizontal spacing
+			self.set_vertex();
+
+			x += advance;
+		}
 	}
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+use std::{collections::HashMap, fs, path::Path};
+
+/// Magic bytes every packed asset archive starts with.
+const MAGIC: &[u8; 4] = b"NPAK";
+
+/// A packed asset archive loaded fully into memory.
+///
+/// Layout (little endian):
+/// `"NPAK"`, `u32` entry count, then for every entry a `u32` name length, the
+/// UTF-8 name (forward slashes, relative to the archive root), a `u64` data
+/// length and the raw data.
+#[derive(Debug, Clone, Default)]
+pub struct Archive {
+	pub path: String,
+	entries: HashMap<String, Vec<u8>>,
+}
+
+impl Archive {
+	pub fn open(path: &str) -> Result<Self, String> {
+		let bytes = fs::read(path).map_err(|e| format!("Failed to read archive '{}': {}", path, e))?;
+		Self::from_bytes(path, &bytes)
+	}
+
+	pub fn from_bytes(path: &str, bytes: &[u8]) -> Result<Self, String> {
+		let mut reader = Reader { bytes, offset: 0 };
+
+		if reader.take(4)? != MAGIC {
+			return Err(format!("'{}' is not a packed asset archive (bad magic)", path));
+		}
+
+		let count = reader.u32()?;
+		let mut entries = HashMap::with_capacity(count as usize);
+		for _ in 0..count {
+			let name_len = reader.u32()? as usize;
+			let name = String::from_utf8(reader.take(name_len)?.to_vec())
+				.map_err(|_| format!("Archive '{}' contains a non UTF-8 entry name", path))?;
+			let data_len = reader.u64()? as usize;
+			let data = reader.take(data_len)?.to_vec();
+			entries.insert(Self::normalize(&name), data);
+		}
+
+		Ok(Archive { path: path.to_string(), entries })
+	}
+
+	/// Writes `files` (archive name, contents) into a new archive at `path`.
+	pub fn write(path: &str, files: &[(String, Vec<u8>)]) -> std::io::Result<()> {
+		let mut out = Vec::new();
+		out.extend_from_slice(MAGIC);
+		out.extend_from_slice(&(files.len() as u32).to_le_bytes());
+		for (name, data) in files {
+			let name = Self::normalize(name);
+			out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+			out.extend_from_slice(name.as_bytes());
+			out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+			out.extend_from_slice(data);
+		}
+		fs::write(path, out)
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.entries.contains_key(&Self::normalize(name))
+	}
+
+	pub fn read(&self, name: &str) -> Option<&[u8]> {
+		self.entries.get(&Self::normalize(name)).map(|data| data.as_slice())
+	}
+
+	pub fn read_to_string(&self, name: &str) -> Option<String> {
+		self.read(name).map(|data| String::from_utf8_lossy(data).into_owned())
+	}
+
+	pub fn names(&self) -> impl Iterator<Item = &String> {
+		self.entries.keys()
+	}
+
+	fn normalize(name: &str) -> String {
+		Path::new(&name.replace('\\', "/"))
+			.components()
+			.filter_map(|c| match c {
+				std::path::Component::Normal(part) => part.to_str(),
+				_ => None,
+			})
+			.collect::<Vec<_>>()
+			.join("/")
+	}
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	offset: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+		let end = self.offset.checked_add(len).filter(|end| *end <= self.bytes.len())
+			.ok_or_else(|| "Unexpected end of archive".to_string())?;
+		let slice = &self.bytes[self.offset..end];
+		self.offset = end;
+		Ok(slice)
+	}
+
+	fn u32(&mut self) -> Result<u32, String> {
+		Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+
+	fn u64(&mut self) -> Result<u64, String> {
+		Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+	}
+}
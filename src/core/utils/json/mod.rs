@@ -0,0 +1 @@
+pub mod value;
\ No newline at end of file
@@ -0,0 +1,342 @@
+use std::fmt;
+
+/// A parsed JSON document, object members keep the order they were written in.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Json {
+	#[default]
+	Null,
+	Bool(bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Json>),
+	Object(Vec<(String, Json)>),
+}
+
+impl Json {
+	pub fn parse(text: &str) -> Result<Json, String> {
+		let mut parser = Parser { bytes: text.as_bytes(), offset: 0 };
+		let value = parser.value()?;
+		parser.whitespace();
+		if parser.offset != parser.bytes.len() {
+			return Err(format!("Unexpected trailing characters at offset {}", parser.offset));
+		}
+		Ok(value)
+	}
+
+	/// Builds an object from `(key, value)` pairs.
+	pub fn object<K: Into<String>>(members: impl IntoIterator<Item = (K, Json)>) -> Json {
+		Json::Object(members.into_iter().map(|(key, value)| (key.into(), value)).collect())
+	}
+
+	pub fn get(&self, key: &str) -> Option<&Json> {
+		match self {
+			Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+			_ => None,
+		}
+	}
+
+	/// Walks nested objects, `value.at(&["arguments", "source", "path"])`.
+	pub fn at(&self, path: &[&str]) -> Option<&Json> {
+		path.iter().try_fold(self, |value, key| value.get(key))
+	}
+
+	/// Adds or replaces a member, does nothing on anything but objects.
+	pub fn set(&mut self, key: &str, value: Json) {
+		if let Json::Object(members) = self {
+			match members.iter_mut().find(|(name, _)| name == key) {
+				Some((_, existing)) => *existing = value,
+				None => members.push((key.to_string(), value)),
+			}
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Json::String(text) => Some(text),
+			_ => None,
+		}
+	}
+
+	pub fn as_f64(&self) -> Option<f64> {
+		match self {
+			Json::Number(number) => Some(*number),
+			_ => None,
+		}
+	}
+
+	pub fn as_i64(&self) -> Option<i64> {
+		self.as_f64().filter(|number| number.fract() == 0.0).map(|number| number as i64)
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Json::Bool(value) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&Vec<Json>> {
+		match self {
+			Json::Array(items) => Some(items),
+			_ => None,
+		}
+	}
+}
+
+impl From<bool> for Json {
+	fn from(value: bool) -> Self {
+		Json::Bool(value)
+	}
+}
+
+impl From<f64> for Json {
+	fn from(value: f64) -> Self {
+		Json::Number(value)
+	}
+}
+
+impl From<i64> for Json {
+	fn from(value: i64) -> Self {
+		Json::Number(value as f64)
+	}
+}
+
+impl From<u32> for Json {
+	fn from(value: u32) -> Self {
+		Json::Number(value as f64)
+	}
+}
+
+impl From<usize> for Json {
+	fn from(value: usize) -> Self {
+		Json::Number(value as f64)
+	}
+}
+
+impl From<&str> for Json {
+	fn from(value: &str) -> Self {
+		Json::String(value.to_string())
+	}
+}
+
+impl From<String> for Json {
+	fn from(value: String) -> Self {
+		Json::String(value)
+	}
+}
+
+impl From<Vec<Json>> for Json {
+	fn from(value: Vec<Json>) -> Self {
+		Json::Array(value)
+	}
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+	fn from(value: Option<T>) -> Self {
+		value.map_or(Json::Null, Into::into)
+	}
+}
+
+impl fmt::Display for Json {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Json::Null => write!(f, "null"),
+			Json::Bool(value) => write!(f, "{}", value),
+			Json::Number(number) if !number.is_finite() => write!(f, "null"),
+			Json::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => write!(f, "{}", *number as i64),
+			Json::Number(number) => write!(f, "{}", number),
+			Json::String(text) => write_string(f, text),
+			Json::Array(items) => {
+				write!(f, "[")?;
+				for (index, item) in items.iter().enumerate() {
+					if index > 0 {
+						write!(f, ",")?;
+					}
+					write!(f, "{}", item)?;
+				}
+				write!(f, "]")
+			}
+			Json::Object(members) => {
+				write!(f, "{{")?;
+				for (index, (key, value)) in members.iter().enumerate() {
+					if index > 0 {
+						write!(f, ",")?;
+					}
+					write_string(f, key)?;
+					write!(f, ":{}", value)?;
+				}
+				write!(f, "}}")
+			}
+		}
+	}
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+	write!(f, "\"")?;
+	for c in text.chars() {
+		match c {
+			'"' => write!(f, "\\\"")?,
+			'\\' => write!(f, "\\\\")?,
+			'\n' => write!(f, "\\n")?,
+			'\r' => write!(f, "\\r")?,
+			'\t' => write!(f, "\\t")?,
+			c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+			c => write!(f, "{}", c)?,
+		}
+	}
+	write!(f, "\"")
+}
+
+struct Parser<'a> {
+	bytes: &'a [u8],
+	offset: usize,
+}
+
+impl Parser<'_> {
+	fn whitespace(&mut self) {
+		while self.offset < self.bytes.len() && self.bytes[self.offset].is_ascii_whitespace() {
+			self.offset += 1;
+		}
+	}
+
+	fn error(&self, expected: &str) -> String {
+		format!("Expected {} at offset {}", expected, self.offset)
+	}
+
+	fn literal(&mut self, text: &str, value: Json) -> Result<Json, String> {
+		if self.bytes[self.offset..].starts_with(text.as_bytes()) {
+			self.offset += text.len();
+			Ok(value)
+		} else {
+			Err(self.error(text))
+		}
+	}
+
+	fn value(&mut self) -> Result<Json, String> {
+		self.whitespace();
+		match self.bytes.get(self.offset) {
+			Some(b'n') => self.literal("null", Json::Null),
+			Some(b't') => self.literal("true", Json::Bool(true)),
+			Some(b'f') => self.literal("false", Json::Bool(false)),
+			Some(b'"') => self.string().map(Json::String),
+			Some(b'[') => self.array(),
+			Some(b'{') => self.object(),
+			Some(b'-' | b'0'..=b'9') => self.number(),
+			_ => Err(self.error("a value")),
+		}
+	}
+
+	fn number(&mut self) -> Result<Json, String> {
+		let start = self.offset;
+		while self.offset < self.bytes.len() && matches!(self.bytes[self.offset], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') {
+			self.offset += 1;
+		}
+		let text = std::str::from_utf8(&self.bytes[start..self.offset]).unwrap_or_default();
+		text.parse().map(Json::Number).map_err(|_| format!("Invalid number \"{}\" at offset {}", text, start))
+	}
+
+	fn string(&mut self) -> Result<String, String> {
+		// Skip the opening quote.
+		self.offset += 1;
+		let mut text = Vec::new();
+		loop {
+			let Some(&byte) = self.bytes.get(self.offset) else {
+				return Err(self.error("a closing quote"));
+			};
+			self.offset += 1;
+			match byte {
+				b'"' => break,
+				b'\\' => {
+					let Some(&escape) = self.bytes.get(self.offset) else {
+						return Err(self.error("an escape sequence"));
+					};
+					self.offset += 1;
+					match escape {
+						b'n' => text.push(b'\n'),
+						b'r' => text.push(b'\r'),
+						b't' => text.push(b'\t'),
+						b'b' => text.push(0x08),
+						b'f' => text.push(0x0c),
+						b'u' => {
+							let mut code = self.hex()?;
+							// Characters outside the basic plane come as a surrogate pair.
+							if (0xd800..0xdc00).contains(&code) && self.bytes[self.offset..].starts_with(b"\\u") {
+								self.offset += 2;
+								let low = self.hex()?;
+								code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+							}
+							let c = char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+							text.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+						}
+						other => text.push(other),
+					}
+				}
+				byte => text.push(byte),
+			}
+		}
+		String::from_utf8(text).map_err(|_| self.error("UTF-8 text"))
+	}
+
+	fn hex(&mut self) -> Result<u32, String> {
+		let digits = self.bytes.get(self.offset..self.offset + 4).ok_or_else(|| self.error("four hex digits"))?;
+		let code = std::str::from_utf8(digits).ok()
+			.and_then(|digits| u32::from_str_radix(digits, 16).ok())
+			.ok_or_else(|| self.error("four hex digits"))?;
+		self.offset += 4;
+		Ok(code)
+	}
+
+	fn array(&mut self) -> Result<Json, String> {
+		self.offset += 1;
+		let mut items = Vec::new();
+		self.whitespace();
+		if self.bytes.get(self.offset) == Some(&b']') {
+			self.offset += 1;
+			return Ok(Json::Array(items));
+		}
+		loop {
+			items.push(self.value()?);
+			self.whitespace();
+			match self.bytes.get(self.offset) {
+				Some(b',') => self.offset += 1,
+				Some(b']') => {
+					self.offset += 1;
+					return Ok(Json::Array(items));
+				}
+				_ => return Err(self.error("',' or ']'")),
+			}
+		}
+	}
+
+	fn object(&mut self) -> Result<Json, String> {
+		self.offset += 1;
+		let mut members = Vec::new();
+		self.whitespace();
+		if self.bytes.get(self.offset) == Some(&b'}') {
+			self.offset += 1;
+			return Ok(Json::Object(members));
+		}
+		loop {
+			self.whitespace();
+			if self.bytes.get(self.offset) != Some(&b'"') {
+				return Err(self.error("a member name"));
+			}
+			let key = self.string()?;
+			self.whitespace();
+			if self.bytes.get(self.offset) != Some(&b':') {
+				return Err(self.error("':'"));
+			}
+			self.offset += 1;
+			members.push((key, self.value()?));
+			self.whitespace();
+			match self.bytes.get(self.offset) {
+				Some(b',') => self.offset += 1,
+				Some(b'}') => {
+					self.offset += 1;
+					return Ok(Json::Object(members));
+				}
+				_ => return Err(self.error("',' or '}'")),
+			}
+		}
+	}
+}
@@ -1,2 +1,4 @@
 pub mod model;
-pub mod texture;
\ No newline at end of file
+pub mod texture;
+pub mod archive;
+pub mod json;
\ No newline at end of file
@@ -1,10 +1,523 @@
-use std::fs;
-
-pub fn load(path: &str) {
-	let file_bytes = fs::read(path).expect("[GLTF:L] Failed to read the model's file. Possible a bad path.");
-	let (gltf, _): (
-		goth_gltf::Gltf<goth_gltf::default_extensions::Extensions>,
-		_,
-	) = goth_gltf::Gltf::from_bytes(&file_bytes).unwrap();
-	println!("{:#?}", gltf);
+use std::{fmt, fs, ops::Range, path::{Path, PathBuf}};
+
+use goth_gltf::{default_extensions::Extensions, ComponentType, FilterMode, NodeTransform, PrimitiveMode, SamplerWrap, TargetPath};
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+
+use crate::{core::{engine::{animation::{clip::{Channel, Clip, Interpolation, Property}, skin::{Skin, MAX_JOINTS}}, material::manager::Material}, utils::texture::manager::{SamplerSettings, Texture}}, log, utils::log::manager::{LogLevel, Logger}};
+use super::{node::{self, Node}, obj::{smooth_normals, MeshData}};
+
+type Gltf = goth_gltf::Gltf<Extensions>;
+
+/// Everything `load` reads from a glTF file.
+pub struct GltfModel {
+	/// Every primitive of every mesh of the file, nodes place ranges of them.
+	pub meshes: Vec<MeshData>,
+	pub materials: Vec<Material>,
+	pub nodes: Vec<Node>,
+	/// Nodes of the scene without a parent.
+	pub roots: Vec<usize>,
+	pub skins: Vec<Skin>,
+	/// Animations of the file, morph target weights are left out.
+	pub clips: Vec<Clip>,
+}
+
+/// Why a glTF file couldn't be loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GltfError {
+	/// The file or one of its buffers couldn't be read.
+	Read { path: String, reason: String },
+	/// The JSON isn't valid glTF.
+	Parse(String),
+	Buffer { index: usize, reason: String },
+	Accessor { index: usize, reason: String },
+	Mesh { name: String, reason: String },
+	/// The nodes don't form a tree.
+	Hierarchy(String),
+	Skin { index: usize, reason: String },
+	Animation { index: usize, reason: String },
+}
+
+impl fmt::Display for GltfError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			GltfError::Read { path, reason } => write!(f, "[GLTF:L] Failed to read \"{}\": {}", path, reason),
+			GltfError::Parse(reason) => write!(f, "[GLTF:PARSE] {}", reason),
+			GltfError::Buffer { index, reason } => write!(f, "[GLTF:BUFFER] Buffer {}: {}", index, reason),
+			GltfError::Accessor { index, reason } => write!(f, "[GLTF:ACCESSOR] Accessor {}: {}", index, reason),
+			GltfError::Mesh { name, reason } => write!(f, "[GLTF:MESH] Mesh \"{}\": {}", name, reason),
+			GltfError::Hierarchy(reason) => write!(f, "[GLTF:NODE] {}", reason),
+			GltfError::Skin { index, reason } => write!(f, "[GLTF:SKIN] Skin {}: {}", index, reason),
+			GltfError::Animation { index, reason } => write!(f, "[GLTF:ANIMATION] Animation {}: {}", index, reason),
+		}
+	}
+}
+
+impl std::error::Error for GltfError {}
+
+/// Reads the meshes, materials, node tree, skins and animations of a `.gltf` or `.glb` file.
+/// Buffers and images are embedded in the file, in its binary chunk or in files relative to it.
+pub fn load(path: &str) -> Result<GltfModel, GltfError> {
+	let file_bytes = fs::read(path).map_err(|err| GltfError::Read { path: path.to_string(), reason: err.to_string() })?;
+	let (gltf, binary): (Gltf, _) = Gltf::from_bytes(&file_bytes).map_err(|err| GltfError::Parse(err.to_string()))?;
+
+	let directory = Path::new(path).parent().unwrap_or(Path::new("")).to_path_buf();
+	let buffers = gltf.buffers.iter().enumerate()
+		.map(|(index, buffer)| read_buffer(buffer, index, binary, &directory))
+		.collect::<Result<Vec<_>, _>>()?;
+	let mut reader = Reader { gltf: &gltf, buffers: &buffers, directory, logger: Logger::new("debug/gltf.log") };
+
+	let materials = (0..gltf.materials.len())
+		.map(|index| Material::from_gltf(&gltf, index, |texture| reader.texture(texture)))
+		.collect::<Vec<_>>();
+
+	let mut meshes = Vec::new();
+	let mut ranges = Vec::new();
+	for (index, mesh) in gltf.meshes.iter().enumerate() {
+		let start = meshes.len();
+		let name = mesh.name.clone().unwrap_or_else(|| format!("mesh {}", index));
+		for (primitive, data) in mesh.primitives.iter().enumerate() {
+			let mut data = reader.primitive(data, &format!("{} {}", name, primitive))?;
+			data.material_id = data.material_id.filter(|id| *id < materials.len());
+			meshes.push(data);
+		}
+		ranges.push(start..meshes.len());
+	}
+
+	let (nodes, roots) = read_nodes(&gltf, &ranges)?;
+	let skins = (0..gltf.skins.len()).map(|index| reader.skin(index, nodes.len())).collect::<Result<Vec<_>, _>>()?;
+	let clips = (0..gltf.animations.len()).map(|index| reader.clip(index, nodes.len())).collect::<Result<Vec<_>, _>>()?;
+	log!(
+		reader.logger, LogLevel::Info, "Loaded \"{}\": {} meshes, {} materials, {} nodes, {} skins, {} animations",
+		path, meshes.len(), materials.len(), nodes.len(), skins.len(), clips.len()
+	);
+	Ok(GltfModel { meshes, materials, nodes, roots, skins, clips })
+}
+
+/// Bytes of a buffer, the binary chunk of `.glb` files for the one without a URI.
+fn read_buffer(buffer: &goth_gltf::Buffer<Extensions>, index: usize, binary: Option<&[u8]>, directory: &Path) -> Result<Vec<u8>, GltfError> {
+	let error = |reason: String| GltfError::Buffer { index, reason };
+	let mut bytes = match &buffer.uri {
+		Some(uri) if uri.starts_with("data:") => decode_data_uri(uri).ok_or_else(|| error(String::from("invalid data URI")))?,
+		Some(uri) => {
+			let path = directory.join(decode_percent(uri));
+			fs::read(&path).map_err(|err| GltfError::Read { path: path.to_string_lossy().to_string(), reason: err.to_string() })?
+		}
+		None => binary.ok_or_else(|| error(String::from("no URI and no binary chunk")))?.to_vec(),
+	};
+	if bytes.len() < buffer.byte_length {
+		return Err(error(format!("{} bytes long instead of {}", bytes.len(), buffer.byte_length)));
+	}
+	// The binary chunk is padded to 4 bytes
+	bytes.truncate(buffer.byte_length);
+	Ok(bytes)
+}
+
+/// Reads the nodes and their transforms, checking every node has one parent at most.
+fn read_nodes(gltf: &Gltf, meshes: &[Range<usize>]) -> Result<(Vec<Node>, Vec<usize>), GltfError> {
+	let mut nodes = Vec::with_capacity(gltf.nodes.len());
+	for (index, gltf_node) in gltf.nodes.iter().enumerate() {
+		let mut node = Node::new(&gltf_node.name.clone().unwrap_or_else(|| format!("node {}", index)));
+		match gltf_node.transform() {
+			NodeTransform::Matrix(matrix) => node.matrix = Some(Matrix4::from_column_slice(&matrix)),
+			NodeTransform::Set { translation, rotation, scale } => {
+				node.translation = Vector3::from(translation);
+				node.rotation = UnitQuaternion::from_quaternion(Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2]));
+				node.scale = Vector3::from(scale);
+			}
+		}
+		node.skin = gltf_node.skin.filter(|skin| *skin < gltf.skins.len());
+		if let Some(mesh) = gltf_node.mesh {
+			node.meshes = meshes.get(mesh).cloned().ok_or_else(|| GltfError::Hierarchy(format!("Node {} uses mesh {} which does not exist", index, mesh)))?;
+		}
+		node.children = gltf_node.children.clone();
+		nodes.push(node);
+	}
+
+	for index in 0..nodes.len() {
+		for child in nodes[index].children.clone() {
+			let Some(child) = nodes.get_mut(child) else {
+				return Err(GltfError::Hierarchy(format!("Node {} has child {} which does not exist", index, child)));
+			};
+			if child.parent.is_some() {
+				return Err(GltfError::Hierarchy(format!("Node \"{}\" has more than one parent", child.name)));
+			}
+			child.parent = Some(index);
+		}
+	}
+
+	// Files without scenes show every node
+	let roots = match gltf.scenes.get(gltf.scene) {
+		Some(scene) => scene.nodes.clone(),
+		None => (0..nodes.len()).filter(|index| nodes[*index].parent.is_none()).collect(),
+	};
+	if let Some(root) = roots.iter().find(|root| nodes.get(**root).is_none_or(|node| node.parent.is_some())) {
+		return Err(GltfError::Hierarchy(format!("Node {} of the scene is missing or not a root", root)));
+	}
+	node::update_globals(&mut nodes, &roots);
+	Ok((nodes, roots))
+}
+
+/// Reads the accessors, primitives, images, skins and animations of a file whose buffers are loaded.
+struct Reader<'a> {
+	gltf: &'a Gltf,
+	buffers: &'a [Vec<u8>],
+	directory: PathBuf,
+	logger: Logger,
+}
+
+impl Reader<'_> {
+	/// The geometry of a primitive. Missing normals are computed, missing texture coordinates are 0.
+	fn primitive(&self, primitive: &goth_gltf::Primitive, name: &str) -> Result<MeshData, GltfError> {
+		let error = |reason: String| GltfError::Mesh { name: name.to_string(), reason };
+		let attributes = &primitive.attributes;
+		let position = attributes.position.ok_or_else(|| error(String::from("no POSITION attribute")))?;
+		let vertices = self.floats(position)?;
+		let vertex_count = vertices.len() / 3;
+
+		let indices = match primitive.indices {
+			Some(indices) => self.integers(indices)?,
+			None => (0..vertex_count as u32).collect(),
+		};
+		if let Some(index) = indices.iter().find(|index| **index as usize >= vertex_count) {
+			return Err(error(format!("index {} is past the {} vertices", index, vertex_count)));
+		}
+
+		let mode = match primitive.mode {
+			PrimitiveMode::Points => gl::POINTS,
+			PrimitiveMode::Lines => gl::LINES,
+			PrimitiveMode::LineLoop => gl::LINE_LOOP,
+			PrimitiveMode::LineStrip => gl::LINE_STRIP,
+			PrimitiveMode::Triangles => gl::TRIANGLES,
+			PrimitiveMode::TriangleStrip => gl::TRIANGLE_STRIP,
+			PrimitiveMode::TriangleFan => gl::TRIANGLE_FAN,
+		};
+		let normals = match attributes.normal {
+			Some(normals) => self.floats(normals)?,
+			None if mode == gl::TRIANGLES => smooth_normals(&vertices, &indices),
+			None => vec![0.0; vertex_count * 3],
+		};
+		let texcoords = match attributes.texcoord_0 {
+			Some(texcoords) => self.floats(texcoords)?,
+			None => vec![0.0; vertex_count * 2],
+		};
+		// Skinned vertices follow up to 4 joints, the others have neither
+		let (joints, weights) = match (attributes.joints_0, attributes.weights_0) {
+			(Some(joints), Some(weights)) => (self.integers(joints)?, self.floats(weights)?),
+			_ => (Vec::new(), Vec::new()),
+		};
+		if joints.iter().any(|joint| *joint as usize >= MAX_JOINTS) {
+			return Err(error(format!("a vertex follows a joint past the {} a skin can have", MAX_JOINTS)));
+		}
+		if normals.len() != vertex_count * 3 || texcoords.len() != vertex_count * 2 || joints.len() != weights.len()
+			|| (!joints.is_empty() && joints.len() != vertex_count * 4) {
+			return Err(error(String::from("attributes have different vertex counts")));
+		}
+
+		Ok(MeshData {
+			name: name.to_string(),
+			vertices,
+			indices,
+			normals,
+			texcoords,
+			joints,
+			weights,
+			material_id: primitive.material,
+			primitive: mode,
+		})
+	}
+
+	/// A skin and its inverse bind matrices, identities when the file has none.
+	fn skin(&self, index: usize, nodes: usize) -> Result<Skin, GltfError> {
+		let skin = &self.gltf.skins[index];
+		let error = |reason: String| GltfError::Skin { index, reason };
+		if skin.joints.len() > MAX_JOINTS {
+			return Err(error(format!("{} joints, the shaders have room for {}", skin.joints.len(), MAX_JOINTS)));
+		}
+		if let Some(joint) = skin.joints.iter().find(|joint| **joint >= nodes) {
+			return Err(error(format!("joint {} does not exist", joint)));
+		}
+
+		let inverse_binds = match skin.inverse_bind_matrices {
+			Some(matrices) => self.floats(matrices)?.chunks_exact(16).map(Matrix4::from_column_slice).collect::<Vec<_>>(),
+			None => vec![Matrix4::identity(); skin.joints.len()],
+		};
+		if inverse_binds.len() != skin.joints.len() {
+			return Err(error(format!("{} inverse bind matrices for {} joints", inverse_binds.len(), skin.joints.len())));
+		}
+		Ok(Skin::new(&skin.name.clone().unwrap_or_else(|| format!("skin {}", index)), skin.joints.clone(), inverse_binds))
+	}
+
+	/// An animation, its channels of morph target weights are left out.
+	fn clip(&self, index: usize, nodes: usize) -> Result<Clip, GltfError> {
+		let animation = &self.gltf.animations[index];
+		let mut channels = Vec::new();
+		for channel in animation.channels.iter() {
+			let property = match channel.target.path {
+				TargetPath::Translation => Property::Translation,
+				TargetPath::Rotation => Property::Rotation,
+				TargetPath::Scale => Property::Scale,
+				TargetPath::Weights => continue,
+			};
+			let Some(node) = channel.target.node.filter(|node| *node < nodes) else {
+				continue;
+			};
+			let sampler = animation.samplers.get(channel.sampler)
+				.ok_or_else(|| GltfError::Animation { index, reason: format!("sampler {} does not exist", channel.sampler) })?;
+			let channel = Channel {
+				node,
+				property,
+				interpolation: match sampler.interpolation {
+					goth_gltf::Interpolation::Step => Interpolation::Step,
+					goth_gltf::Interpolation::Linear => Interpolation::Linear,
+					goth_gltf::Interpolation::CubicSpline => Interpolation::CubicSpline,
+				},
+				times: self.floats(sampler.input)?,
+				values: self.floats(sampler.output)?,
+			};
+			if !channel.is_valid() {
+				return Err(GltfError::Animation { index, reason: format!("{} keyframes of node {} don't match their values", channel.times.len(), node) });
+			}
+			channels.push(channel);
+		}
+		Ok(Clip::new(&animation.name.clone().unwrap_or_else(|| format!("animation {}", index)), channels))
+	}
+
+	/// A texture of the file with its image and sampler, `None` when its image can't be found.
+	fn texture(&mut self, index: usize) -> Option<Texture> {
+		let texture = self.gltf.textures.get(index)?;
+		let source = texture.source?;
+		let image = self.gltf.images.get(source)?;
+		let name = image.name.clone().unwrap_or_else(|| format!("image {}", source));
+
+		// glTF texture coordinates start at the top left, like the image rows.
+		let decoded = match (&image.uri, image.buffer_view) {
+			(Some(uri), _) if uri.starts_with("data:") => Texture::from_bytes(&name, &decode_data_uri(uri)?, false),
+			(Some(uri), _) => {
+				let path = self.directory.join(decode_percent(uri));
+				if !path.is_file() {
+					log!(self.logger, LogLevel::Warning, "Image \"{}\" does not exist", path.to_string_lossy());
+					return None;
+				}
+				Texture::open(&path.to_string_lossy(), false)
+			}
+			(None, Some(view)) => Texture::from_bytes(&name, self.view(view).ok()?.0, false),
+			(None, None) => return None,
+		};
+		let mut result = match decoded {
+			Ok(result) => result,
+			Err(err) => {
+				log!(Logger::new("debug/model.log"), LogLevel::Error, "Failed to decode image \"{}\": {}", name, err);
+				return None;
+			}
+		};
+		if let Some(sampler) = texture.sampler.and_then(|sampler| self.gltf.samplers.get(sampler)) {
+			result.sampler = sampler_settings(sampler);
+		}
+		Some(result)
+	}
+
+	/// Every component of an accessor as floats, normalised integers are scaled to 0..1 or -1..1.
+	fn floats(&self, index: usize) -> Result<Vec<f32>, GltfError> {
+		let normalized = self.gltf.accessors.get(index).is_some_and(|accessor| accessor.normalized);
+		self.read(index, |bytes, kind| float(bytes, kind, normalized))
+	}
+
+	/// Every component of an accessor of unsigned integers, indices or joints.
+	fn integers(&self, index: usize) -> Result<Vec<u32>, GltfError> {
+		self.read(index, integer)
+	}
+
+	/// Reads the components of an accessor from its buffer view, then its sparse values.
+	fn read<T: Copy + Default>(&self, index: usize, convert: impl Fn(&[u8], ComponentType) -> T) -> Result<Vec<T>, GltfError> {
+		let error = |reason: &str| GltfError::Accessor { index, reason: reason.to_string() };
+		let accessor = self.gltf.accessors.get(index).ok_or_else(|| error("does not exist"))?;
+		let kind = accessor.component_type;
+		let size = kind.byte_size();
+		let components = accessor.accessor_type.num_components();
+		let element = size * components;
+		let total = accessor.count.checked_mul(components).ok_or_else(|| error("has too many elements"))?;
+
+		// The count comes from the file, it's checked against the data before anything is allocated
+		let source = match accessor.buffer_view {
+			Some(view) => {
+				let (data, stride) = self.view(view)?;
+				let stride = stride.unwrap_or(element);
+				let end = match accessor.count.checked_sub(1) {
+					Some(last) => stride.checked_mul(last)
+						.and_then(|span| span.checked_add(accessor.byte_offset))
+						.and_then(|start| start.checked_add(element)),
+					None => Some(0),
+				};
+				if end.is_none_or(|end| end > data.len()) {
+					return Err(error("reads past the end of its buffer view"));
+				}
+				Some((data, stride))
+			}
+			None => {
+				// Zeros have no data to check against, they can't outgrow the buffers of the file though
+				let available = self.buffers.iter().map(Vec::len).sum::<usize>();
+				if accessor.count.checked_mul(element).is_none_or(|bytes| bytes > available) {
+					return Err(error("has more elements than the buffers hold"));
+				}
+				None
+			}
+		};
+
+		let sparse = match &accessor.sparse {
+			Some(sparse) => {
+				if sparse.count > accessor.count {
+					return Err(error("has more sparse values than elements"));
+				}
+				let (indices, _) = self.view(sparse.indices.buffer_view)?;
+				let (data, _) = self.view(sparse.values.buffer_view)?;
+				let index_size = sparse.indices.component_type.byte_size();
+				let fits = |bytes: &[u8], offset: usize, width: usize| {
+					sparse.count.checked_mul(width).and_then(|length| length.checked_add(offset)).is_some_and(|end| end <= bytes.len())
+				};
+				if !fits(indices, sparse.indices.byte_offset, index_size) {
+					return Err(error("sparse indices past the end of their buffer view"));
+				}
+				if !fits(data, sparse.values.byte_offset, element) {
+					return Err(error("sparse values past the end of their buffer view"));
+				}
+				Some((sparse, indices, data, index_size))
+			}
+			None => None,
+		};
+
+		// Accessors without a buffer view are zeros until the sparse values
+		let mut values = vec![T::default(); total];
+		if let Some((data, stride)) = source {
+			for (component, value) in values.iter_mut().enumerate() {
+				let offset = accessor.byte_offset + component / components * stride + component % components * size;
+				*value = convert(&data[offset..offset + size], kind);
+			}
+		}
+
+		if let Some((sparse, indices, data, index_size)) = sparse {
+			for value in 0..sparse.count {
+				let offset = sparse.indices.byte_offset + value * index_size;
+				let element = integer(&indices[offset..offset + index_size], sparse.indices.component_type) as usize;
+				for component in 0..components {
+					let offset = sparse.values.byte_offset + (value * components + component) * size;
+					*values.get_mut(element * components + component).ok_or_else(|| error("sparse index past the end"))? = convert(&data[offset..offset + size], kind);
+				}
+			}
+		}
+		Ok(values)
+	}
+
+	/// Bytes of a buffer view and its stride.
+	fn view(&self, index: usize) -> Result<(&[u8], Option<usize>), GltfError> {
+		let view = self.gltf.buffer_views.get(index).ok_or_else(|| GltfError::Buffer { index, reason: String::from("buffer view does not exist") })?;
+		let error = |reason: String| GltfError::Buffer { index: view.buffer, reason };
+		let buffer = self.buffers.get(view.buffer).ok_or_else(|| error(String::from("does not exist")))?;
+		let data = view.byte_offset.checked_add(view.byte_length)
+			.and_then(|end| buffer.get(view.byte_offset..end))
+			.ok_or_else(|| error(format!("buffer view {} is past the end", index)))?;
+		Ok((data, view.byte_stride))
+	}
+}
+
+fn integer(bytes: &[u8], kind: ComponentType) -> u32 {
+	match kind {
+		ComponentType::UnsignedByte => bytes[0] as u32,
+		ComponentType::Byte => bytes[0] as i8 as u32,
+		ComponentType::UnsignedShort => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+		ComponentType::Short => i16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+		ComponentType::UnsignedInt => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+		ComponentType::Float => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u32,
+	}
+}
+
+fn float(bytes: &[u8], kind: ComponentType, normalized: bool) -> f32 {
+	match (kind, normalized) {
+		(ComponentType::Float, _) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+		(ComponentType::UnsignedByte, true) => bytes[0] as f32 / 255.0,
+		(ComponentType::Byte, true) => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+		(ComponentType::UnsignedShort, true) => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0,
+		(ComponentType::Short, true) => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32767.0).max(-1.0),
+		(ComponentType::Byte, false) => bytes[0] as i8 as f32,
+		(ComponentType::Short, false) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+		_ => integer(bytes, kind) as f32,
+	}
+}
+
+fn sampler_settings(sampler: &goth_gltf::Sampler) -> SamplerSettings {
+	let wrap = |wrap: &SamplerWrap| match wrap {
+		SamplerWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+		SamplerWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+		SamplerWrap::Repeat => gl::REPEAT,
+	};
+	let defaults = SamplerSettings::default();
+	SamplerSettings {
+		wrap_s: wrap(&sampler.wrap_s),
+		wrap_t: wrap(&sampler.wrap_t),
+		min_filter: sampler.min_filter.as_ref().map_or(defaults.min_filter, |filter| match (&filter.mode, &filter.mipmap) {
+			(FilterMode::Nearest, None) => gl::NEAREST,
+			(FilterMode::Linear, None) => gl::LINEAR,
+			(FilterMode::Nearest, Some(FilterMode::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+			(FilterMode::Linear, Some(FilterMode::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+			(FilterMode::Nearest, Some(FilterMode::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+			(FilterMode::Linear, Some(FilterMode::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+		}),
+		mag_filter: sampler.mag_filter.as_ref().map_or(defaults.mag_filter, |filter| match filter {
+			FilterMode::Nearest => gl::NEAREST,
+			FilterMode::Linear => gl::LINEAR,
+		}),
+	}
+}
+
+/// Bytes of a `data:[<mime>];base64,<data>` URI.
+fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+	let (header, data) = uri.split_once(',')?;
+	if header.ends_with(";base64") {
+		decode_base64(data)
+	} else {
+		Some(decode_percent(data).into_bytes())
+	}
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+	let value = |character: u8| match character {
+		b'A'..=b'Z' => Some(character - b'A'),
+		b'a'..=b'z' => Some(character - b'a' + 26),
+		b'0'..=b'9' => Some(character - b'0' + 52),
+		b'+' | b'-' => Some(62),
+		b'/' | b'_' => Some(63),
+		_ => None,
+	};
+
+	let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+	let (mut bits, mut count) = (0u32, 0);
+	for character in text.bytes().filter(|character| !character.is_ascii_whitespace() && *character != b'=') {
+		bits = bits << 6 | value(character)? as u32;
+		count += 6;
+		if count >= 8 {
+			count -= 8;
+			bytes.push((bits >> count) as u8);
+		}
+	}
+	Some(bytes)
+}
+
+/// Replaces the `%XX` escapes of a URI with the characters.
+fn decode_percent(uri: &str) -> String {
+	let bytes = uri.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut index = 0;
+	while index < bytes.len() {
+		let escaped = (bytes[index] == b'%').then(|| uri.get(index + 1..index + 3)).flatten().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+		match escaped {
+			Some(byte) => {
+				decoded.push(byte);
+				index += 3;
+			}
+			None => {
+				decoded.push(bytes[index]);
+				index += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&decoded).to_string()
 }
\ No newline at end of file
@@ -1,17 +1,41 @@
-use super::{gltf, obj::MeshData};
-use crate::core::engine::objects::graphics::GraphicsObjects;
+use nalgebra::Matrix4;
 
-pub struct ModelGobs {
-	default: GraphicsObjects,
-	texture: GraphicsObjects
+use super::{gltf, node::{self, Node}, obj::MeshData};
+use crate::{core::engine::{animation::{clip::Clip, player::AnimationPlayer, skin::Skin}, material::manager::Material, objects::{layout::{AttributeFormat, Semantic, VertexLayout}, mesh::{as_bytes, Mesh, MeshUsage}}, shaders::{manager::Shader, permutation::ShaderFeatures}}, log, utils::log::manager::{LogLevel, Logger}};
+
+/// Where a node places a mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshInstance {
+	/// From the mesh to the model, the identity for skinned meshes which their joints place.
+	pub transform: Matrix4<f32>,
+	/// Skin that deforms the mesh, an index into `ModelLoader::skins`.
+	pub skin: Option<usize>,
+}
+
+/// One mesh of a model file and its geometry on the GPU.
+pub struct ModelMesh {
+	pub data: MeshData,
+	pub mesh: Mesh,
+	/// Every node placing the mesh, it's drawn once for each.
+	pub instances: Vec<MeshInstance>,
 }
 
 pub struct ModelLoader {
     pub path: String,
 
 	pub has_texture: bool,
-    pub mesh_data: Option<MeshData>, // To store the loaded mesh data
-	pub gobs: ModelGobs
+	/// Every mesh of the file, uploaded by `load`.
+    pub meshes: Vec<ModelMesh>,
+	/// Materials of the `.mtl` files or of the glTF file, the meshes point into them.
+	pub materials: Vec<Material>,
+	/// Node tree of glTF files, OBJ files have none and their meshes are drawn in place.
+	pub nodes: Vec<Node>,
+	/// Nodes of the scene without a parent.
+	pub roots: Vec<usize>,
+	pub skins: Vec<Skin>,
+	/// Animations of the nodes, played by `player`.
+	pub clips: Vec<Clip>,
+	pub player: AnimationPlayer,
 }
 
 impl ModelLoader {
@@ -19,11 +43,13 @@ impl ModelLoader {
         ModelLoader {
             path: String::from(path),
 			
-            mesh_data: None,
-			gobs: ModelGobs {
-				default: GraphicsObjects::default(),
-				texture: GraphicsObjects::default(),
-			},
+            meshes: Vec::new(),
+			materials: Vec::new(),
+			nodes: Vec::new(),
+			roots: Vec::new(),
+			skins: Vec::new(),
+			clips: Vec::new(),
+			player: AnimationPlayer::default(),
 
 			has_texture,
         }
@@ -31,94 +57,123 @@ impl ModelLoader {
 
     // Load the model depending on file extension
     pub fn load(&mut self) {
+        let mut logger = Logger::new("debug/model.log");
         let extension = self.path.as_str().rsplit_once('.').unwrap().1;
         match extension {
-            "gltf" | "glb" => gltf::load(self.path.as_str()),
-            "obj" => {
-                if let Ok(mesh_data) = MeshData::load(self.path.as_str()) {
-                    self.mesh_data = Some(mesh_data);
+            "gltf" | "glb" => match gltf::load(self.path.as_str()) {
+                Ok(model) => {
+                    self.meshes = model.meshes.into_iter().map(|data| self.upload(data)).collect();
+                    self.materials = model.materials;
+                    self.nodes = model.nodes;
+                    self.roots = model.roots;
+                    self.skins = model.skins;
+                    self.clips = model.clips;
+                    self.player = AnimationPlayer::new(&self.nodes);
+                    self.update_transforms();
                 }
-            }
+                Err(err) => {
+                    log!(logger, LogLevel::Error, "Failed to load \"{}\": {}", self.path, err);
+                }
+            },
+            "obj" => match MeshData::load(self.path.as_str()) {
+                Ok((meshes, materials)) => {
+                    self.meshes = meshes.into_iter().map(|data| self.upload(data)).collect();
+                    self.materials = materials;
+                }
+                Err(err) => {
+                    log!(logger, LogLevel::Error, "Failed to load \"{}\": {}", self.path, err);
+                }
+            },
             _ => {
                 println!("Unsupported model format");
             }
         }
+    }
 
-		// Generate the VAO, VBO, and EBO only once before drawing
-		self.gobs.default.generate_vao();
-		self.gobs.default.generate_vbo();
-		self.gobs.default.generate_ebo();
-
-		// Generate the texture's VAO, VBO, and EBO only once before drawing if the model has texture
-		if self.has_texture {
-			self.gobs.texture.generate_vao();
-			self.gobs.texture.generate_vbo();
-			self.gobs.texture.generate_ebo();
+	/// Uploads the vertices, texture coordinates, normals, joints and indices only once before drawing.
+	fn upload(&self, data: MeshData) -> ModelMesh {
+		let mut layout = VertexLayout::new().with(Semantic::Position, AttributeFormat::Vec3);
+		let mut buffers = vec![as_bytes(&data.vertices)];
+		if self.has_texture && !data.texcoords.is_empty() {
+			layout = layout.next_buffer().with(Semantic::TexCoord0, AttributeFormat::Vec2);
+			buffers.push(as_bytes(&data.texcoords));
+		}
+		layout = layout.next_buffer().with(Semantic::Normal, AttributeFormat::Vec3);
+		buffers.push(as_bytes(&data.normals));
+		// Joint indices are below `MAX_JOINTS`, read as integers
+		let joints = data.joints.iter().map(|joint| *joint as u16).collect::<Vec<_>>();
+		if !joints.is_empty() {
+			layout = layout.next_buffer().with(Semantic::Joints, AttributeFormat::UShort4)
+				.next_buffer().with(Semantic::Weights, AttributeFormat::Vec4);
+			buffers.push(as_bytes(&joints));
+			buffers.push(as_bytes(&data.weights));
 		}
-    }
 
-	// Function to draw the model using GraphicsObjects
-	pub fn draw(&mut self) {
-		if let Some(mesh_data) = &self.mesh_data {
-			// Upload vertex data to VBO
-			unsafe {
-				self.gobs.default.bind_vao();
-				self.gobs.default.bind_vbo();
-				gl::BufferData(
-					gl::ARRAY_BUFFER,
-					(mesh_data.vertices.len() * std::mem::size_of::<f32>()) as isize,
-					mesh_data.vertices.as_ptr() as *const std::ffi::c_void,
-					gl::STATIC_DRAW,
-				);
-				// Define vertex attribute pointers (assuming positions only, 3 floats per vertex)
-				gl::VertexAttribPointer(
-					0, // attribute index
-					3, // number of components (x, y, z)
-					gl::FLOAT, // type of components
-					gl::FALSE, // normalized
-					0, // stride
-					std::ptr::null(), // pointer to the start
-				);
-				gl::EnableVertexAttribArray(0);
+		let mut mesh = Mesh::new(layout, &buffers, Some(&data.indices), MeshUsage::Static);
+		mesh.primitive = data.primitive;
+		mesh.label(&format!("{} \"{}\"", self.path, data.name));
+		ModelMesh { data, mesh, instances: vec![MeshInstance { transform: Matrix4::identity(), skin: None }] }
+	}
 
-				if self.has_texture {
-					self.gobs.texture.bind_vbo();
-					gl::BufferData(
-						gl::ARRAY_BUFFER,
-						(mesh_data.texcoords.len() * std::mem::size_of::<[f32; 2]>()) as isize,
-						mesh_data.texcoords.as_ptr() as *const std::ffi::c_void,
-						gl::STATIC_DRAW,
-					);
-					gl::VertexAttribPointer(
-						1,
-						2,
-						gl::FLOAT,
-						gl::FALSE,
-						0,
-						std::ptr::null(),
-					);
-					gl::EnableVertexAttribArray(1);
-				}
+	/// Places the meshes where the nodes of the scene put them and poses the skins, after the
+	/// nodes moved.
+	pub fn update_transforms(&mut self) {
+		if self.nodes.is_empty() {
+			return;
+		}
+		node::update_globals(&mut self.nodes, &self.roots);
+		for mesh in self.meshes.iter_mut() {
+			mesh.instances.clear();
+		}
+		for index in node::in_scene(&self.nodes, &self.roots) {
+			let node = &self.nodes[index];
+			for mesh in node.meshes.clone() {
+				let mesh = &mut self.meshes[mesh];
+				// Meshes without joints can't be skinned, the node places them
+				let skin = node.skin.filter(|_| !mesh.data.joints.is_empty());
+				let transform = if skin.is_some() { Matrix4::identity() } else { node.global };
+				mesh.instances.push(MeshInstance { transform, skin });
+			}
+		}
+		for skin in self.skins.iter_mut() {
+			skin.update(&self.nodes);
+		}
+	}
 
-				self.gobs.default.bind_ebo();
-				gl::BufferData(
-					gl::ELEMENT_ARRAY_BUFFER,
-					(mesh_data.indices.len() * std::mem::size_of::<u32>()) as isize,
-					mesh_data.indices.as_ptr() as *const std::ffi::c_void,
-					gl::STATIC_DRAW,
-				);
+	/// Advances the clips `player` plays by `delta` seconds and poses the model.
+	pub fn animate(&mut self, delta: f32) {
+		if self.player.tracks.is_empty() {
+			return;
+		}
+		self.player.update(delta, &self.clips, &mut self.nodes);
+		self.update_transforms();
+	}
 
-				// Bind the VAO and draw the object
-				gl::DrawElements(
-					gl::TRIANGLES,
-					mesh_data.indices.len() as i32,
-					gl::UNSIGNED_INT,
-					std::ptr::null(),
-				);
-			}
+	/// Calls `f` on every mesh with its material, `None` for the meshes the file has none for,
+	/// and the skins its instances point to.
+	pub fn each_mesh(&mut self, mut f: impl FnMut(&mut ModelMesh, Option<&mut Material>, &[Skin])) {
+		for mesh in self.meshes.iter_mut() {
+			let material = mesh.data.material_id.and_then(|id| self.materials.get_mut(id));
+			f(mesh, material, &self.skins);
+		}
+	}
 
-			// Unbind the VAO after drawing
-			self.gobs.default.unbind_vao();
+	/// Draws every mesh with `shader` where `matrix` puts the model, for passes that don't need
+	/// the materials. Skinned meshes are drawn with its `SKINNED` variant.
+	pub fn draw(&self, shader: &mut Shader, matrix: &Matrix4<f32>) {
+		for mesh in self.meshes.iter() {
+			for instance in mesh.instances.iter() {
+				let skin = instance.skin.and_then(|skin| self.skins.get(skin));
+				if let Some(skin) = skin {
+					shader.use_variant(ShaderFeatures::SKINNED);
+					skin.bind();
+				}
+				shader.set_uniform_matrix4fv("model", &(matrix * instance.transform));
+				mesh.mesh.draw();
+				if skin.is_some() {
+					shader.use_program();
+				}
+			}
 		}
 	}
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
 pub mod manager;
+pub mod node;
 pub mod obj;
 pub mod gltf;
\ No newline at end of file
@@ -0,0 +1,72 @@
+use std::ops::Range;
+
+use nalgebra::{Matrix4, UnitQuaternion, Vector3};
+
+/// A node of a model's hierarchy, placed relative to its parent.
+#[derive(Debug, Clone)]
+pub struct Node {
+	pub name: String,
+	pub parent: Option<usize>,
+	pub children: Vec<usize>,
+
+	pub translation: Vector3<f32>,
+	pub rotation: UnitQuaternion<f32>,
+	pub scale: Vector3<f32>,
+	/// Local transform of the nodes given as a matrix, used instead of the translation,
+	/// rotation and scale.
+	pub matrix: Option<Matrix4<f32>>,
+
+	/// Meshes of the model the node places, indices into `ModelLoader::meshes`.
+	pub meshes: Range<usize>,
+	/// Skin that deforms the node's meshes, an index into `ModelLoader::skins`.
+	pub skin: Option<usize>,
+	/// Transform from the node to the model, computed by `update_globals`.
+	pub global: Matrix4<f32>,
+}
+
+impl Node {
+	pub fn new(name: &str) -> Self {
+		Node {
+			name: name.to_string(),
+			parent: None,
+			children: Vec::new(),
+
+			translation: Vector3::zeros(),
+			rotation: UnitQuaternion::identity(),
+			scale: Vector3::new(1.0, 1.0, 1.0),
+			matrix: None,
+
+			meshes: 0..0,
+			skin: None,
+			global: Matrix4::identity(),
+		}
+	}
+
+	/// Transform from the node to its parent.
+	pub fn local(&self) -> Matrix4<f32> {
+		self.matrix.unwrap_or_else(|| {
+			Matrix4::new_translation(&self.translation)
+				* self.rotation.to_homogeneous()
+				* Matrix4::new_nonuniform_scaling(&self.scale)
+		})
+	}
+}
+
+/// Computes the `global` transform of every node under `roots`.
+pub fn update_globals(nodes: &mut [Node], roots: &[usize]) {
+	for index in in_scene(nodes, roots) {
+		let parent = nodes[index].parent.map_or(Matrix4::identity(), |parent| nodes[parent].global);
+		nodes[index].global = parent * nodes[index].local();
+	}
+}
+
+/// Nodes under `roots`, parents before their children.
+pub fn in_scene(nodes: &[Node], roots: &[usize]) -> Vec<usize> {
+	let mut scene = roots.to_vec();
+	let mut index = 0;
+	while index < scene.len() {
+		scene.extend(nodes[scene[index]].children.iter().copied());
+		index += 1;
+	}
+	scene
+}
\ No newline at end of file
@@ -1,35 +1,101 @@
+use std::path::Path;
+use nalgebra::Vector3;
+
+use crate::core::engine::material::manager::Material;
+
+/// Geometry of one model of a file, in the file's space.
 #[derive(Debug)]
 pub struct MeshData {
+    /// Name of the object or group in the file.
+    pub name: String,
     pub vertices: Vec<f32>,
     pub indices: Vec<u32>,
 	pub normals: Vec<f32>,
 	pub texcoords: Vec<f32>,
+	/// 4 joints per vertex of skinned meshes, empty otherwise.
+	pub joints: Vec<u32>,
+	/// How much each of `joints` moves the vertex.
+	pub weights: Vec<f32>,
+	/// Index of the mesh's material among the ones loaded with it.
+	pub material_id: Option<usize>,
+	/// How the indices make primitives, `gl::TRIANGLES` for OBJ files.
+	pub primitive: u32,
 }
 
 impl MeshData {
-    pub fn load(path: &str) -> Result<MeshData, String> {
-        // Load the model using tobj
-        let (models, _materials) = tobj::load_obj(path, &tobj::LoadOptions {
+    /// Loads every model of the file and the materials of its `.mtl` files, whose textures
+    /// are relative to the file. Objects using several materials are split into a mesh per material.
+    pub fn load(path: &str) -> Result<(Vec<MeshData>, Vec<Material>), String> {
+        // Load the models using tobj
+        let (models, materials) = tobj::load_obj(path, &tobj::LoadOptions {
             triangulate: true,
+            // Positions, normals and texture coordinates share the indices of the EBO
+            single_index: true,
             ..Default::default()
         }).map_err(|e| e.to_string())?;
 
-        // Extract vertices and indices from the first model
-        let mut vertices = Vec::new();
-        let mut indices = Vec::new();
-        let mut normals = Vec::new();
-        let mut texcoords = Vec::new();
+        // A missing `.mtl` file leaves the models without materials.
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+        let materials = match materials {
+            Ok(materials) => materials.iter().map(|material| Material::from_obj(material, directory)).collect::<Vec<_>>(),
+            Err(_) => Vec::new(),
+        };
 
-        if let Some(model) = models.first() {
-            let mesh = &model.mesh;
+        let meshes = models.into_iter()
+            .filter(|model| !model.mesh.indices.is_empty())
+            .map(|model| {
+                let mesh = model.mesh;
+                let vertex_count = mesh.positions.len() / 3;
 
-            vertices = mesh.positions.clone();  // Vertices are already in the positions field
-            indices = mesh.indices.clone();     // Indices for the EBO
-			normals = mesh.normals.clone();
-			texcoords = mesh.texcoords.clone();
-        }
+                let normals = if mesh.normals.len() == mesh.positions.len() {
+                    mesh.normals
+                } else {
+                    smooth_normals(&mesh.positions, &mesh.indices)
+                };
+                // Untextured meshes still feed the texture coordinates the shaders read
+                let texcoords = if mesh.texcoords.len() == vertex_count * 2 {
+                    mesh.texcoords
+                } else {
+                    vec![0.0; vertex_count * 2]
+                };
 
-        // Return the mesh data
-        Ok(MeshData { vertices, indices, normals, texcoords })
+                MeshData {
+                    name: model.name,
+                    vertices: mesh.positions,
+                    indices: mesh.indices,
+                    normals,
+                    texcoords,
+                    joints: Vec::new(),
+                    weights: Vec::new(),
+                    // Ids past the parsed materials point to ones the `.mtl` didn't have
+                    material_id: mesh.material_id.filter(|id| *id < materials.len()),
+                    primitive: gl::TRIANGLES,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok((meshes, materials))
     }
 }
+
+
+/// Normals of a mesh without any, the average of the faces around each vertex weighted by their area.
+pub fn smooth_normals(vertices: &[f32], indices: &[u32]) -> Vec<f32> {
+    let position = |index: u32| Vector3::new(vertices[index as usize * 3], vertices[index as usize * 3 + 1], vertices[index as usize * 3 + 2]);
+
+    let mut normals = vec![Vector3::<f32>::zeros(); vertices.len() / 3];
+    for face in indices.chunks_exact(3) {
+        // The cross product's length is twice the face's area
+        let normal = (position(face[1]) - position(face[0])).cross(&(position(face[2]) - position(face[0])));
+        for index in face {
+            normals[*index as usize] += normal;
+        }
+    }
+
+    normals.iter()
+        .flat_map(|normal| {
+            let normal = normal.try_normalize(f32::EPSILON).unwrap_or(Vector3::y());
+            [normal.x, normal.y, normal.z]
+        })
+        .collect()
+}
\ No newline at end of file
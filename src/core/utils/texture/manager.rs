@@ -1,15 +1,40 @@
-use gl::types::GLuint;
-use image::{DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView, ImageError};
 use std::ffi::CString;
 
-use crate::{log, utils::log::manager::{gl_error_to_message, LogLevel, Logger}};
+use crate::{core::engine::objects::gpu::{Sampler, Texture2D}, log, utils::log::manager::{LogLevel, Logger}};
 
-#[derive(Debug, Clone)]
+/// How a texture is filtered and wrapped, read by `init`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplerSettings {
+    pub wrap_s: u32,
+    pub wrap_t: u32,
+    pub min_filter: u32,
+    pub mag_filter: u32,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        SamplerSettings {
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+            min_filter: gl::LINEAR_MIPMAP_LINEAR,
+            mag_filter: gl::LINEAR,
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Texture {
-    pub id: GLuint,
+    /// Created by `init`.
+    pub texture: Option<Texture2D>,
+    /// Path of the image, or its name when it's decoded from memory.
     pub src: String,
+    /// Image `init` uploads, decoded when the texture is loaded so broken images are
+    /// reported then instead of in the middle of a frame.
+    pub image: Option<DynamicImage>,
+    pub sampler: SamplerSettings,
 	pub flipv: bool,
-    pub linear_sampler: GLuint,
+    pub linear_sampler: Option<Sampler>,
 	pub has_alpha: bool,
 	logger: Logger
 }
@@ -17,111 +42,127 @@ pub struct Texture {
 impl Texture {
     pub fn new(path: &str, flip_verticall: bool) -> Self {
         Texture {
-            id: 0,
+            texture: None,
             src: String::from(path),
-            linear_sampler: 0,
+            image: None,
+            sampler: SamplerSettings::default(),
+            linear_sampler: None,
 			has_alpha: false,
 			flipv: flip_verticall,
 			logger: Logger::new("debug/texture.log")
         }
     }
 
-    pub fn init(&mut self) {
-        unsafe {
-            // Create texture and set up storage
-            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut self.id);
-            if self.id == 0 {
-				log!(self.logger, LogLevel::Error, "Failed to generate a texture id for \"{}\"", self.src);
-            }
+    /// A texture of the image at `path`, decoded right away.
+    pub fn open(path: &str, flip_vertical: bool) -> Result<Self, ImageError> {
+        Ok(Texture {
+            image: Some(image::open(path)?),
+            ..Texture::new(path, flip_vertical)
+        })
+    }
 
-			// Create and configure sampler
-			gl::CreateSamplers(1, &mut self.linear_sampler);
-			if self.linear_sampler == 0 {
-				log!(self.logger, LogLevel::Error, "Failed to generate a sampler id for \"{}\"", self.src);
-			}
+    /// A texture of an encoded image (PNG, JPEG...) in memory, `name` labels it.
+    pub fn from_bytes(name: &str, bytes: &[u8], flip_vertical: bool) -> Result<Self, ImageError> {
+        Ok(Texture {
+            image: Some(image::load_from_memory(bytes)?),
+            ..Texture::new(name, flip_vertical)
+        })
+    }
 
-			gl::SamplerParameteri(self.linear_sampler, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-			gl::SamplerParameteri(self.linear_sampler, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-			gl::SamplerParameteri(self.linear_sampler, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
-			gl::SamplerParameteri(self.linear_sampler, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-			
-			// fn bad_image_log(src: &str) {
-			// 	panic!("Failed to load an image");
-			// }
-
-			// fn get_debug_image(src: &str) -> DynamicImage {
-			// 	let dbg_path = "assets/debug/texture.jpg";
-			// 	bad_image_log(src);
-			// 	if let Ok(dbg_img) = image::open(dbg_path) {
-			// 		dbg_img
-			// 	} else {
-			// 		Log::send(
-			// 			3850, "texture struct", "init", 
-			// 			LogStatus::CRASH, 
-			// 			"Could not find debug texture", dbg_path);
-			// 		panic!("");
-			// 	}
-			// }
-
-            // Load image data
-            let mut img = image::open(self.src.clone()).unwrap();
-			
-			img = if self.flipv {
-				img.flipv()
-			} else {
-				img
-			};
-
-            let (width, height) = img.dimensions();
-
-			let (internal_format, format) = match img {
-                DynamicImage::ImageRgba8(_) => (gl::RGBA8 as i32, gl::RGBA),
-                DynamicImage::ImageRgb8(_) => (gl::RGB8 as i32, gl::RGB),
-                _ => (gl::RGB8 as i32, gl::RGB),
-			};
-
-			if format == gl::RGBA {
-				self.has_alpha = true;
-			}
+    /// Whether `init` created the texture.
+    pub fn is_initialized(&self) -> bool {
+        self.texture.is_some()
+    }
 
-			let printable_format = if internal_format == gl::RGB8 as i32 {
-				"gl::RGB8"
-			} else { "gl::RGBA8" };
-
-			log!(self.logger, LogLevel::Info, "Detected texture's image format is \"{}\"", printable_format);
-
-            gl::TextureStorage2D(self.id, 1, internal_format as u32, width as i32, height as i32);
-
-			match format {
-				gl::RGBA => gl::TextureSubImage2D(
-					self.id, 0, 
-					0, 0, 
-					width as i32, height as i32, 
-					format, gl::UNSIGNED_BYTE,
-					img.to_rgba8().as_ptr() as *const std::ffi::c_void
-				),
-				_ => gl::TextureSubImage2D(
-					self.id, 0, 
-					0, 0, 
-					width as i32, height as i32, 
-					format, gl::UNSIGNED_BYTE,
-					img.to_rgb8().as_ptr() as *const std::ffi::c_void
-				),
-			};
-
-            // Generate mipmaps
-            gl::GenerateTextureMipmap(self.id);
-			log!(self.logger, LogLevel::Info, "Generated texture mipmaps for texture's id number: \"{}\"", self.id);
-        }
+    pub fn init(&mut self) {
+        // Load image data
+        let img = match self.image.take() {
+            Some(image) => Ok(image),
+            None => image::open(&self.src),
+        };
+        let mut img = match img {
+            Ok(img) => img,
+            Err(err) => {
+                log!(self.logger, LogLevel::Error, "Failed to decode texture \"{}\": {}", self.src, err);
+                return;
+            }
+        };
+
+		// Create and configure sampler
+		let sampler = Sampler::new();
+		sampler.parameter(gl::TEXTURE_WRAP_S, self.sampler.wrap_s);
+		sampler.parameter(gl::TEXTURE_WRAP_T, self.sampler.wrap_t);
+		sampler.parameter(gl::TEXTURE_MIN_FILTER, self.sampler.min_filter);
+		sampler.parameter(gl::TEXTURE_MAG_FILTER, self.sampler.mag_filter);
+		
+		// fn bad_image_log(src: &str) {
+		// 	panic!("Failed to load an image");
+		// }
+
+		// fn get_debug_image(src: &str) -> DynamicImage {
+		// 	let dbg_path = "assets/debug/texture.jpg";
+		// 	bad_image_log(src);
+		// 	if let Ok(dbg_img) = image::open(dbg_path) {
+		// 		dbg_img
+		// 	} else {
+		// 		Log::send(
+		// 			3850, "texture struct", "init", 
+		// 			LogStatus::CRASH, 
+		// 			"Could not find debug texture", dbg_path);
+		// 		panic!("");
+		// 	}
+		// }
+
+		img = if self.flipv {
+			img.flipv()
+		} else {
+			img
+		};
+
+        let (width, height) = img.dimensions();
+        let (width, height) = (width as i32, height as i32);
+
+		let (internal_format, format) = match img {
+            DynamicImage::ImageRgba8(_) => (gl::RGBA8, gl::RGBA),
+            DynamicImage::ImageRgb8(_) => (gl::RGB8, gl::RGB),
+            _ => (gl::RGB8, gl::RGB),
+		};
+
+		if format == gl::RGBA {
+			self.has_alpha = true;
+		}
+
+		let printable_format = if internal_format == gl::RGB8 {
+			"gl::RGB8"
+		} else { "gl::RGBA8" };
+
+		log!(self.logger, LogLevel::Info, "Detected texture's image format is \"{}\"", printable_format);
+
+        // Create texture and set up storage
+        let texture = Texture2D::new(internal_format, width, height, Texture2D::full_levels(width, height));
+		texture.label(&self.src);
+
+		match format {
+			gl::RGBA => texture.upload(width, height, format, gl::UNSIGNED_BYTE, img.to_rgba8().as_raw()),
+			_ => texture.upload(width, height, format, gl::UNSIGNED_BYTE, img.to_rgb8().as_raw()),
+		};
+
+        // Generate mipmaps
+        texture.generate_mipmaps();
+		log!(self.logger, LogLevel::Info, "Generated texture mipmaps for texture's id number: \"{}\"", texture.id());
+
+		self.texture = Some(texture);
+		self.linear_sampler = Some(sampler);
     }
 
 
     pub fn apply(&mut self, texture_unit: u32, sampler_name: &str, program_id: u32) {
-        unsafe {
-            if self.id == 0 {
-				log!(self.logger, LogLevel::Error, "Couldn't apply texture \"{}\" because texture id was \"{}\"", self.src, self.id);
-			}
+        let (Some(texture), Some(sampler)) = (&self.texture, &self.linear_sampler) else {
+			log!(self.logger, LogLevel::Error, "Couldn't apply texture \"{}\" because it wasn't initialized", self.src);
+			return;
+		};
 
+        unsafe {
             // Set the uniform to use the texture unit
             let texture_name = CString::new(sampler_name).unwrap();
             let texture_location = gl::GetUniformLocation(program_id, texture_name.as_ptr());
@@ -130,30 +171,11 @@ impl Texture {
 			}
             gl::ProgramUniform1i(program_id, texture_location, texture_unit as i32);
 
-			let has_alpha_name = CString::new("has_alpha").unwrap();
-			let has_alpha_location = gl::GetUniformLocation(program_id, has_alpha_name.as_ptr());
-			if has_alpha_location == -1 {
-				log!(self.logger, LogLevel::Error, "Invalid boolean's \"has_alpha\" uniform location for \"{}\" texture", self.src);
-			}
-			gl::ProgramUniform1i(program_id, has_alpha_location, self.has_alpha as i32);
-
-            // Check for OpenGL errors
-            let error = gl::GetError();
-            if error != gl::NO_ERROR {
-				log!(self.logger, LogLevel::Error, "Opengl error occured while applying texture \"{}\"\n- Opengl Code: {}\n- Opengl Formatted Reason: {}", self.src, error, gl_error_to_message(error));
-            }
-
             // Activate texture unit
             gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
-            gl::BindSampler(texture_unit, self.linear_sampler);
-
-			// Check for OpenGL errors
-            let error = gl::GetError();
-            if error != gl::NO_ERROR {
-				log!(self.logger, LogLevel::Error, "Opengl error occured after setting uniform location of the texture \"{}\"\n- Opengl Code: {}\n- Opengl Formatted Reason: {}", self.src, error, gl_error_to_message(error));
-            }
+            gl::BindTexture(gl::TEXTURE_2D, texture.id());
         }
+        sampler.bind(texture_unit);
     }
 
 	pub fn free(&self, texture_unit: u32) {
@@ -1,4 +1,4 @@
-use core::engine::platform::implementations::{self, Size, WindowOptions, WindowProperties};
+use core::engine::{objects::gpu, platform::implementations::{self, Size, WindowOptions, WindowProperties}};
 
 pub mod core;
 pub mod utils;
@@ -8,11 +8,15 @@ fn main() {
 		window_options: WindowOptions {
 			title: String::from("nengine"),
 			size: Size::from(1024, 600),
-			// ..Default::default()
+			..Default::default()
 		},
 		display_options: None
 	}, vec!["examples/script/test.lua".to_string(), "examples/script/test.js".to_string()]);
 	
 	platform.initialize_opengl();
 	platform.run();
+
+	// Everything the window owned is deleted with it, what's still alive leaked
+	drop(platform);
+	gpu::report_leaks();
 }
\ No newline at end of file
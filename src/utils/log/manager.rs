@@ -66,6 +66,7 @@ impl LogEntry {
 pub struct Logger {
     logs: Vec<LogEntry>, // Store log entries in memory
     log_file: String,
+    deduplicate: bool,
 }
 
 impl Logger {
@@ -74,21 +75,29 @@ impl Logger {
         Self {
             logs: Vec::new(),
             log_file: log_file.to_string(),
+            deduplicate: true,
         }
     }
 
+    // When disabled every message is printed and nothing is kept in memory
+    pub fn set_deduplicate(&mut self, deduplicate: bool) {
+        self.deduplicate = deduplicate;
+    }
+
     // Log a new entry, but avoid duplicates
     pub fn log(&mut self, level: LogLevel, message: &str, file: &'static str, line: u32) {
         // Create the log entry
         let entry = LogEntry::new(level.clone(), message.to_string(), file, line);
         
         // Check if the log entry already exists in memory
-        if !self.logs.iter().any(|log| log.message == entry.message && log.level == entry.level) {
-            // Add to logs
-            self.logs.push(entry.clone());
-            
-            // Sort the logs by level and timestamp
-            self.logs.sort_by(|a, b| a.level.cmp(&b.level).then(a.timestamp.cmp(&b.timestamp)));
+        if !self.deduplicate || !self.logs.iter().any(|log| log.message == entry.message && log.level == entry.level) {
+            if self.deduplicate {
+                // Add to logs
+                self.logs.push(entry.clone());
+
+                // Sort the logs by level and timestamp
+                self.logs.sort_by(|a, b| a.level.cmp(&b.level).then(a.timestamp.cmp(&b.timestamp)));
+            }
 
             // Print the log to the terminal with color and file name extraction
             self.print_to_terminal(&entry);
@@ -156,20 +165,4 @@ macro_rules! log {
     ($logger:expr, $level:expr, $($arg:tt)*) => {
         $logger.log($level, &format!($($arg)*), file!(), line!());
     };
-}
-
-pub fn gl_error_to_message(error_code: u32) -> String {
-    let error_message = match error_code {
-        gl::NO_ERROR => "No error",
-        gl::INVALID_ENUM => "Invalid enum: An unacceptable value is specified for an enumerated argument.",
-        gl::INVALID_VALUE => "Invalid value: A numeric argument is out of range.",
-        gl::INVALID_OPERATION => "Invalid operation: The specified operation is not allowed in the current state.",
-        gl::STACK_OVERFLOW => "Stack overflow: This command would cause a stack overflow.",
-        gl::STACK_UNDERFLOW => "Stack underflow: This command would cause a stack underflow.",
-        gl::OUT_OF_MEMORY => "Out of memory: There is not enough memory left to execute the command.",
-        gl::INVALID_FRAMEBUFFER_OPERATION => "Invalid framebuffer operation: The framebuffer object is not complete.",
-        _ => "Unknown error code",
-    };
-
-	error_message.bold()
 }
\ No newline at end of file
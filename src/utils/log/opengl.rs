@@ -0,0 +1,155 @@
+use std::{cell::RefCell, collections::HashMap, ffi::{c_void, CStr, CString}};
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+use crate::log;
+use super::manager::{LogLevel, Logger};
+
+/// Repeats of a message are reported when their count reaches a power of this.
+const REPEAT_BASE: u32 = 10;
+
+/// Driver messages of the current thread's context, logged to `debug/opengl.log`.
+struct DebugOutput {
+    /// Times every message (source, type, id) was received.
+    counts: HashMap<(GLenum, GLenum, GLuint), u32>,
+    logger: Logger,
+}
+
+thread_local! {
+    static OUTPUT: RefCell<Option<DebugOutput>> = const { RefCell::new(None) };
+}
+
+/// Routes the driver's debug messages into the logger. Messages are reported on the thread
+/// and in the call that caused them. `notifications` keeps the informational ones, which
+/// most drivers send for every buffer allocation.
+pub fn install_debug_callback(notifications: bool) {
+    OUTPUT.with(|output| {
+        output.replace(Some(DebugOutput {
+            counts: HashMap::new(),
+            logger: Logger::new("debug/opengl.log"),
+        }));
+    });
+
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(debug_callback), std::ptr::null());
+        gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, 0, std::ptr::null(), gl::TRUE);
+        if !notifications {
+            gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DEBUG_SEVERITY_NOTIFICATION, 0, std::ptr::null(), gl::FALSE);
+        }
+        // Our own debug groups would be echoed back as messages
+        gl::DebugMessageControl(gl::DEBUG_SOURCE_APPLICATION, gl::DEBUG_TYPE_PUSH_GROUP, gl::DONT_CARE, 0, std::ptr::null(), gl::FALSE);
+        gl::DebugMessageControl(gl::DEBUG_SOURCE_APPLICATION, gl::DEBUG_TYPE_POP_GROUP, gl::DONT_CARE, 0, std::ptr::null(), gl::FALSE);
+    }
+}
+
+extern "system" fn debug_callback(source: GLenum, kind: GLenum, id: GLuint, severity: GLenum, length: GLsizei, message: *const GLchar, _: *mut c_void) {
+    if message.is_null() {
+        return;
+    }
+    let message = unsafe {
+        if length < 0 {
+            CStr::from_ptr(message).to_string_lossy().to_string()
+        } else {
+            String::from_utf8_lossy(std::slice::from_raw_parts(message as *const u8, length as usize)).to_string()
+        }
+    };
+
+    OUTPUT.with(|output| {
+        // A message raised while logging one is dropped rather than borrowed twice
+        let Ok(mut output) = output.try_borrow_mut() else {
+            return;
+        };
+        let Some(output) = output.as_mut() else {
+            return;
+        };
+
+        let count = output.counts.entry((source, kind, id)).or_insert(0);
+        *count += 1;
+        let count = *count;
+        if count > 1 && !is_power_of(count, REPEAT_BASE) {
+            return;
+        }
+
+        let level = level(kind, severity);
+        let text = format!("[GL:{}:{}:{}] {}", source_name(source), type_name(kind), id, message.trim_end());
+        if count == 1 {
+            log!(output.logger, level, "{}", text);
+        } else {
+            log!(output.logger, level, "{} (repeated {} times)", text, count);
+        }
+    });
+}
+
+fn is_power_of(mut value: u32, base: u32) -> bool {
+    while value.is_multiple_of(base) {
+        value /= base;
+    }
+    value == 1
+}
+
+/// Errors and undefined behaviour are errors whatever the driver rates them.
+fn level(kind: GLenum, severity: GLenum) -> LogLevel {
+    match (kind, severity) {
+        (gl::DEBUG_TYPE_ERROR | gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR, _) | (_, gl::DEBUG_SEVERITY_HIGH) => LogLevel::Error,
+        (_, gl::DEBUG_SEVERITY_MEDIUM | gl::DEBUG_SEVERITY_LOW) => LogLevel::Warning,
+        _ => LogLevel::Info,
+    }
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "API",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "WINDOW",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "COMPILER",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "THIRD_PARTY",
+        gl::DEBUG_SOURCE_APPLICATION => "APPLICATION",
+        _ => "OTHER",
+    }
+}
+
+fn type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::DEBUG_TYPE_ERROR => "ERROR",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "DEPRECATED",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "UNDEFINED",
+        gl::DEBUG_TYPE_PORTABILITY => "PORTABILITY",
+        gl::DEBUG_TYPE_PERFORMANCE => "PERFORMANCE",
+        gl::DEBUG_TYPE_MARKER => "MARKER",
+        _ => "OTHER",
+    }
+}
+
+/// Names a GL object in debug messages and graphics debuggers. `identifier` is its kind,
+/// `gl::BUFFER`, `gl::TEXTURE`, `gl::PROGRAM`, `gl::FRAMEBUFFER`...
+pub fn label(identifier: GLenum, name: GLuint, label: &str) {
+    if name == 0 {
+        return;
+    }
+    let mut length = 0;
+    unsafe { gl::GetIntegerv(gl::MAX_LABEL_LENGTH, &mut length) };
+    // The limit is in bytes, a cut in the middle of a character moves back to its start
+    let mut end = label.len().min((length.max(1) - 1) as usize);
+    while !label.is_char_boundary(end) {
+        end -= 1;
+    }
+    let label = &label[..end];
+    unsafe { gl::ObjectLabel(identifier, name, label.len() as GLsizei, label.as_ptr() as *const GLchar) };
+}
+
+/// A named region of GL calls in debuggers like RenderDoc, closed when dropped.
+pub struct DebugGroup;
+
+impl DebugGroup {
+    pub fn new(name: &str) -> Self {
+        let name = CString::new(name.replace('\0', "")).unwrap_or_default();
+        unsafe { gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, -1, name.as_ptr()) };
+        DebugGroup
+    }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        unsafe { gl::PopDebugGroup() };
+    }
+}
\ No newline at end of file